@@ -0,0 +1,83 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Measures the bandwidth saved by each compression codec while forming a section and exchanging
+//! user messages in it, together with the time it takes.
+//!
+//! Run with `cargo bench --features mock --bench compression`.
+
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate log;
+
+#[path = "../tests/mock_network/utils.rs"]
+mod utils;
+
+use self::utils::*;
+use rand::Rng;
+use routing::{
+    mock::Environment, Compression, CompressionStats, DstLocation, NetworkParams, SrcLocation,
+};
+use std::time::Instant;
+
+// Number of nodes of the section.
+const SECTION_SIZE: usize = 2 * MIN_ELDER_SIZE;
+// Number of user messages each node sends.
+const MESSAGES_PER_NODE: usize = 8;
+// Size of each user message.
+const MESSAGE_SIZE: usize = 16 * 1024;
+
+fn run(compression: Option<Compression>) -> CompressionStats {
+    let env = Environment::new(NetworkParams {
+        elder_size: MIN_ELDER_SIZE,
+        recommended_section_size: SECTION_SIZE,
+        compression,
+        ..Default::default()
+    });
+    let mut rng = env.new_rng();
+    let mut nodes = create_connected_nodes(&env, SECTION_SIZE);
+
+    // Compressible, but not trivially so.
+    for node in &mut nodes {
+        let src = SrcLocation::Node(*node.name());
+        for _ in 0..MESSAGES_PER_NODE {
+            let dst = DstLocation::Section(rng.gen());
+            let content = (0..MESSAGE_SIZE).map(|_| rng.gen_range(0, 16)).collect();
+            let _ = node.inner.send_message(src, dst, content);
+        }
+    }
+    poll_all(&env, &mut nodes);
+
+    nodes
+        .iter()
+        .map(|node| node.inner.compression_stats())
+        .fold(CompressionStats::default(), |total, stats| {
+            CompressionStats {
+                uncompressed: total.uncompressed + stats.uncompressed,
+                sent: total.sent + stats.sent,
+            }
+        })
+}
+
+fn main() {
+    for &compression in &[None, Some(Compression::Deflate), Some(Compression::Snappy)] {
+        let start = Instant::now();
+        let stats = run(compression);
+        let elapsed = start.elapsed();
+
+        println!(
+            "{:?}: sent {} of {} bytes ({:.1}% saved) in {:?}",
+            compression,
+            stats.sent,
+            stats.uncompressed,
+            100.0 * (1.0 - stats.sent as f64 / stats.uncompressed as f64),
+            elapsed
+        );
+    }
+}
@@ -355,6 +355,85 @@ fn handle_event(
             index,
             HexFmt(payload)
         ),
+        Event::Notarised(receipt) => log::info!(
+            "Node #{} notarised {:?} at {}",
+            index,
+            receipt.hash(),
+            receipt.timestamp()
+        ),
+        Event::Located(located) => log::info!(
+            "Node #{} located section {:?}",
+            index,
+            located.elders_info.value
+        ),
+        Event::BeaconRound { round, value } => log::debug!(
+            "Node #{} beacon round {} - value: {:?}",
+            index,
+            round,
+            value
+        ),
+        Event::SectionDecrypted { plaintext, .. } => log::info!(
+            "Node #{} section decrypted - plaintext: {}",
+            index,
+            HexFmt(plaintext)
+        ),
+        Event::DkgCompleted { elders, key } => log::info!(
+            "Node #{} completed DKG - key: {:?}, elders: {:?}",
+            index,
+            key,
+            elders
+        ),
+        Event::DkgFailed {
+            elders,
+            non_contributors,
+        } => log::warn!(
+            "Node #{} failed DKG - elders: {:?}, non-contributors: {:?}",
+            index,
+            elders,
+            non_contributors
+        ),
+        Event::NetworkIdMismatch { peer, ours, theirs } => log::warn!(
+            "Node #{} rejected peer {} from a different network - ours: {}, theirs: {}",
+            index,
+            peer,
+            ours,
+            theirs
+        ),
+        Event::ProtocolMismatch { peer, ours, theirs } => log::warn!(
+            "Node #{} rejected peer {} speaking an incompatible protocol - ours: {}, theirs: {}",
+            index,
+            peer,
+            ours,
+            theirs
+        ),
+        Event::Delivered { dst, msg_hash } => log::debug!(
+            "Node #{} delivered message {:?} to {:?}",
+            index,
+            msg_hash,
+            dst
+        ),
+        Event::DeliveryTimeout { dst, msg_hash } => log::warn!(
+            "Node #{} got no ack of message {:?} to {:?}",
+            index,
+            msg_hash,
+            dst
+        ),
+        Event::DeliveryFailed { dst, msg_hash } => log::warn!(
+            "Node #{} failed to deliver message {:?} to {:?}",
+            index,
+            msg_hash,
+            dst
+        ),
+        Event::RestartForUpgrade { version } => log::info!(
+            "Node #{} should restart into protocol version {}",
+            index,
+            version
+        ),
+        Event::UpgradeCompleted { version } => log::info!(
+            "Node #{} section upgraded to protocol version {}",
+            index,
+            version
+        ),
         Event::Terminated => {
             log::info!("Node #{} terminated", index);
             return false;
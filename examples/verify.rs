@@ -0,0 +1,89 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Offline verifier of section-signed payloads.
+//!
+//! Checks that a payload was signed by a section, given the section proof chain and one or more
+//! trusted section keys (typically the genesis key). No connection to the network is needed.
+//!
+//! # Usage
+//!
+//!     cargo run --example verify -- --trusted-key KEY_FILE --chain CHAIN_FILE \
+//!         --proof PROOF_FILE PAYLOAD_FILE
+//!
+//! The key, chain and proof files contain the bincode serialisation of `bls::PublicKey`,
+//! `SectionProofChain` and `Proof` respectively. The payload file contains the raw signed bytes.
+//!
+//! Prints the trust status (`Trusted`, `Invalid` or `Unknown`) and the index of the signing key in
+//! the chain. The exit code is zero only if the payload is trusted.
+
+use routing::{Proof, SectionProofChain, TrustStatus};
+use serde::de::DeserializeOwned;
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+use structopt::StructOpt;
+
+/// Offline verifier of section-signed payloads.
+#[derive(Debug, StructOpt)]
+struct Options {
+    /// File with a trusted section public key. Can be specified multiple times.
+    #[structopt(short, long, value_name = "KEY_FILE", required = true)]
+    trusted_key: Vec<PathBuf>,
+    /// File with the section proof chain.
+    #[structopt(short, long, value_name = "CHAIN_FILE")]
+    chain: PathBuf,
+    /// File with the proof (section public key and signature) of the payload.
+    #[structopt(short, long, value_name = "PROOF_FILE")]
+    proof: PathBuf,
+    /// File with the signed payload.
+    #[structopt(value_name = "PAYLOAD_FILE")]
+    payload: PathBuf,
+}
+
+fn main() {
+    let opts = Options::from_args();
+
+    match verify(&opts) {
+        Ok(TrustStatus::Trusted) => (),
+        Ok(_) => process::exit(1),
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(2)
+        }
+    }
+}
+
+fn verify(opts: &Options) -> Result<TrustStatus, Box<dyn Error>> {
+    let trusted_keys = opts
+        .trusted_key
+        .iter()
+        .map(|path| read::<bls::PublicKey>(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let chain: SectionProofChain = read(&opts.chain)?;
+    let proof: Proof = read(&opts.proof)?;
+    let payload = fs::read(&opts.payload)?;
+
+    let (status, index) = chain.verify_proof(&trusted_keys, &payload, &proof);
+
+    println!("status: {:?}", status);
+    match index {
+        Some(index) => println!("signing key index: {}", index),
+        None => println!("signing key index: not in the chain"),
+    }
+
+    Ok(status)
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    Ok(bincode::deserialize(&bytes)?)
+}
@@ -0,0 +1,558 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    consensus::Proven,
+    core::Core,
+    delivery_group,
+    error::{Result, RoutingError},
+    event::Event,
+    id::{FullId, PublicId},
+    location::DstLocation,
+    messages::{
+        self, BootstrapResponse, ChunkAssembler, CreateError, Message, Variant, VerifyStatus,
+    },
+    network_id::NetworkId,
+    node::NodeConfig,
+    protocol::{Protocol, PROTOCOL_VERSION},
+    quic_p2p::{EventSenders, Peer},
+    rng::{self, MainRng},
+    section::{CompactProofChain, EldersInfo, TrustStatus},
+    time::Duration,
+    TransportConfig, TransportEvent,
+};
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, RecvError, Select};
+use fxhash::FxHashSet;
+use std::net::SocketAddr;
+use xor_name::{Prefix, XorName};
+
+/// Interval at which a light client asks the elders of its section for the current section key.
+/// Must be shorter than the time the elders keep delivering messages to an inactive client.
+pub const CLIENT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Light client configuration.
+pub struct ClientConfig {
+    /// The ID of the client or `None` for randomly generated one.
+    pub full_id: Option<FullId>,
+    /// Configuration for the underlying network transport.
+    pub transport_config: TransportConfig,
+    /// The id of the network to connect to. If `None`, the client learns it from the section it
+    /// bootstraps against.
+    pub network_id: Option<NetworkId>,
+    /// Section keys the client trusts from the start, typically the genesis key. If empty, the key
+    /// of the section the client bootstraps against is trusted on first use.
+    pub trusted_keys: Vec<bls::PublicKey>,
+    /// Random number generator to be used by the client.
+    pub rng: MainRng,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            full_id: None,
+            transport_config: TransportConfig::default(),
+            network_id: None,
+            trusted_keys: Vec::new(),
+            rng: rng::new(),
+        }
+    }
+}
+
+/// Light client that observes the network without joining it.
+///
+/// The client bootstraps like a node, but instead of joining the section that matches its name it
+/// only follows the elders and the key of that section, by periodically asking the elders for a
+/// proof of the current key. It can send messages to any location through those elders and
+/// receives the messages sent to it as `DstLocation::Node`. Section-signed messages are passed
+/// on only if their proof chain starts at a key the client trusts.
+///
+/// The client raises the same events as `Node`, namely `EldersChanged` every time the elders or
+/// the key of its section change, `MessageReceived` and `Terminated`.
+pub struct Client {
+    core: Core,
+    // Elders of the section we follow, once verified.
+    elders_info: Option<EldersInfo>,
+    // Section keys we trust, the last one being the current key of the section we follow.
+    trusted_keys: Vec<bls::PublicKey>,
+    // Elders we ask for the proof of the current key, not verified yet.
+    contacts: Vec<SocketAddr>,
+    // Using `FxHashSet` for deterministic iteration order.
+    pending_bootstrap: FxHashSet<SocketAddr>,
+    refresh_timer_token: u64,
+    running: bool,
+    chunk_assembler: ChunkAssembler,
+
+    timer_rx: Receiver<u64>,
+    timer_rx_idx: usize,
+    transport_rx: Receiver<TransportEvent>,
+    transport_rx_idx: usize,
+}
+
+impl Client {
+    ////////////////////////////////////////////////////////////////////////////
+    // Public API
+    ////////////////////////////////////////////////////////////////////////////
+
+    /// Create new client using the given config and start bootstrapping.
+    ///
+    /// Returns the client itself and the event receiver.
+    pub fn new(config: ClientConfig) -> (Self, Receiver<Event>) {
+        let (timer_tx, timer_rx) = crossbeam_channel::unbounded();
+        // We never accept client connections, so events from both peer types share one channel.
+        let (node_tx, transport_rx) = crossbeam_channel::unbounded();
+        let client_tx = node_tx.clone();
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
+
+        let node_config = NodeConfig {
+            full_id: config.full_id,
+            transport_config: config.transport_config,
+            network_id: config.network_id,
+            rng: config.rng,
+            ..Default::default()
+        };
+        let mut core = Core::new(
+            node_config,
+            timer_tx,
+            EventSenders { node_tx, client_tx },
+            event_tx,
+        );
+
+        info!("{} Bootstrapping a light client.", core.name());
+        core.transport.bootstrap();
+        let refresh_timer_token = core.timer.schedule(CLIENT_REFRESH_INTERVAL);
+
+        let client = Self {
+            core,
+            elders_info: None,
+            trusted_keys: config.trusted_keys,
+            contacts: Vec::new(),
+            pending_bootstrap: Default::default(),
+            refresh_timer_token,
+            running: true,
+            chunk_assembler: Default::default(),
+            timer_rx,
+            timer_rx_idx: 0,
+            transport_rx,
+            transport_rx_idx: 0,
+        };
+
+        (client, event_rx)
+    }
+
+    /// Register the client event channels with the provided [selector](mpmc::Select).
+    pub fn register<'a>(&'a mut self, select: &mut Select<'a>) {
+        #[cfg(feature = "mock_base")]
+        self.core.timer.process_timers();
+
+        self.timer_rx_idx = select.recv(&self.timer_rx);
+        self.transport_rx_idx = select.recv(&self.transport_rx);
+    }
+
+    /// Processes events received externally from one of the channels. Same as
+    /// `Node::handle_selected_operation`.
+    pub fn handle_selected_operation(&mut self, op_index: usize) -> Result<(), RecvError> {
+        if !self.running {
+            return Err(RecvError);
+        }
+
+        match op_index {
+            idx if idx == self.transport_rx_idx => {
+                let event = self.transport_rx.recv()?;
+                self.handle_transport_event(event);
+            }
+            idx if idx == self.timer_rx_idx => {
+                let token = self.timer_rx.recv()?;
+                self.handle_timeout(token);
+            }
+            _idx => return Err(RecvError),
+        };
+
+        Ok(())
+    }
+
+    /// Returns whether this client is running or has been terminated.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Returns the `PublicId` of this client.
+    pub fn id(&self) -> &PublicId {
+        self.core.id()
+    }
+
+    /// The name of this client.
+    pub fn name(&self) -> &XorName {
+        self.id().name()
+    }
+
+    /// Returns the elders of the section we follow, or `None` if not known yet.
+    pub fn elders_info(&self) -> Option<&EldersInfo> {
+        self.elders_info.as_ref()
+    }
+
+    /// Returns the current key of the section we follow, or `None` if not known yet.
+    pub fn section_key(&self) -> Option<&bls::PublicKey> {
+        self.elders_info
+            .as_ref()
+            .and_then(|_| self.trusted_keys.last())
+    }
+
+    /// Send a message signed by this client to the given location, through the elders of the
+    /// section we follow. Replies sent to `DstLocation::Node` with our name are delivered to us.
+    /// Returns `InvalidState` error if we don't know the elders yet.
+    pub fn send_message(&mut self, dst: DstLocation, content: Vec<u8>) -> Result<()> {
+        if let DstLocation::Direct = dst {
+            return Err(RoutingError::BadLocation);
+        }
+
+        let targets: Vec<_> = self
+            .elders_info
+            .as_ref()
+            .ok_or(RoutingError::InvalidState)?
+            .elders
+            .values()
+            .map(|p2p_node| *p2p_node.peer_addr())
+            .collect();
+
+        let dg_size = delivery_group::delivery_group_size(targets.len());
+        for variant in messages::user_message_variants(content)? {
+            let msg = Message::single_src(&self.core.full_id, dst, None, variant)?;
+            self.core.send_message_to_targets(
+                &targets,
+                dg_size,
+                msg.to_bytes(),
+                msg.variant().priority(),
+            );
+        }
+
+        Ok(())
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Input handling
+    ////////////////////////////////////////////////////////////////////////////
+
+    fn handle_transport_event(&mut self, event: TransportEvent) {
+        use crate::TransportEvent::*;
+
+        match event {
+            BootstrappedTo { node } => self.send_bootstrap_request(node),
+            BootstrapFailure => {
+                info!("Failed to bootstrap. Terminating.");
+                self.terminate();
+            }
+            NewMessage {
+                peer: Peer::Node(peer_addr),
+                msg,
+            } => self.handle_new_message(peer_addr, msg),
+            UnsentUserMessage {
+                peer: Peer::Node(peer_addr),
+                msg,
+                token,
+            } => {
+                let _ = self.core.handle_unsent_message(peer_addr, msg, token);
+            }
+            SentUserMessage {
+                peer: Peer::Node(peer_addr),
+                token,
+                ..
+            } => self.core.transport.target_succeeded(token, peer_addr),
+            Finish => self.running = false,
+            _ => (),
+        }
+    }
+
+    fn handle_timeout(&mut self, token: u64) {
+        if self.core.transport.handle_timeout(token) || token != self.refresh_timer_token {
+            return;
+        }
+
+        self.refresh_timer_token = self.core.timer.schedule(CLIENT_REFRESH_INTERVAL);
+
+        if self.contacts.is_empty() {
+            if self.pending_bootstrap.is_empty() {
+                self.core.transport.bootstrap();
+            }
+        } else {
+            self.send_section_proof_request();
+        }
+    }
+
+    fn handle_new_message(&mut self, sender: SocketAddr, bytes: Bytes) {
+        let msg = match Message::from_bytes(&bytes) {
+            Ok(msg) => msg,
+            Err(CreateError::Unsupported { hash, protocol })
+                if protocol.version > PROTOCOL_VERSION =>
+            {
+                debug!(
+                    "Rejecting message {:?} from {} speaking newer protocol {}",
+                    hash, sender, protocol
+                );
+                self.core.send_direct_message(
+                    &sender,
+                    Variant::MessageRejected {
+                        hash,
+                        protocol_version: PROTOCOL_VERSION,
+                    },
+                );
+                return;
+            }
+            Err(error) => {
+                debug!("Failed to deserialize message: {:?}", error);
+                return;
+            }
+        };
+
+        self.core.record_protocol(sender, *msg.protocol());
+
+        if let Err(error) = self.handle_message(sender, msg) {
+            debug!("Failed to handle message: {:?}", error);
+        }
+    }
+
+    fn handle_message(&mut self, sender: SocketAddr, msg: Message) -> Result<()> {
+        trace!("Got {:?}", msg);
+
+        match msg.variant() {
+            Variant::AddressChallenge { nonce, network_id } => {
+                self.core.check_network_id(&sender, *network_id)?;
+                let variant = Variant::AddressChallengeResponse {
+                    nonce: *nonce,
+                    network_id: self.core.network_id,
+                };
+                self.core.send_direct_message(&sender, variant);
+            }
+            Variant::BootstrapResponse(response) => {
+                self.handle_bootstrap_response(sender, response.clone(), msg.protocol())?
+            }
+            Variant::SectionProofResponse {
+                elders_info,
+                proof_chain,
+            } => self.handle_section_proof_response(elders_info.clone(), proof_chain),
+            Variant::UserMessage(_) | Variant::UserMessageChunk { .. } => {
+                self.handle_user_message(sender, &msg)?
+            }
+            _ => trace!("Useless message from {}: {:?}", sender, msg),
+        }
+
+        Ok(())
+    }
+
+    fn handle_user_message(&mut self, sender: SocketAddr, msg: &Message) -> Result<()> {
+        if msg.dst().as_node().ok() != Some(self.core.name()) {
+            return Err(RoutingError::BadLocation);
+        }
+
+        // Our trusted keys come from the genesis lineage, so they are compatible with any
+        // prefix.
+        let any_prefix = Prefix::default();
+        let status = msg.verify(self.trusted_keys.iter().map(|key| (&any_prefix, key)))?;
+        if status != VerifyStatus::Full {
+            debug!("Untrusted message from {}: {:?}", sender, msg);
+            return Err(RoutingError::UntrustedMessage);
+        }
+
+        let src = msg.src().src_location();
+        let content = match msg.variant() {
+            Variant::UserMessage(content) => content.clone(),
+            Variant::UserMessageChunk {
+                hash,
+                index,
+                count,
+                data,
+            } => {
+                if let Some(content) =
+                    self.chunk_assembler
+                        .add(&src, msg.dst(), *hash, *index, *count, data.clone())
+                {
+                    content
+                } else {
+                    return Ok(());
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        self.core.send_event(Event::MessageReceived {
+            content,
+            src,
+            dst: *msg.dst(),
+        });
+
+        Ok(())
+    }
+
+    fn handle_bootstrap_response(
+        &mut self,
+        sender: SocketAddr,
+        response: BootstrapResponse,
+        protocol: &Protocol,
+    ) -> Result<()> {
+        if !self.pending_bootstrap.remove(&sender) {
+            debug!(
+                "Ignoring BootstrapResponse from unexpected peer: {}",
+                sender
+            );
+            return Ok(());
+        }
+
+        self.core.check_protocol(&sender, protocol)?;
+
+        match response {
+            BootstrapResponse::Join {
+                elders_info,
+                section_key,
+                network_id,
+            } => {
+                self.core.check_network_id(&sender, network_id)?;
+                if self.core.network_id.is_none() {
+                    self.core.network_id = network_id;
+                }
+
+                info!("Following section {:?}", elders_info);
+
+                for addr in self.pending_bootstrap.drain() {
+                    self.core.transport.disconnect(addr);
+                }
+
+                self.contacts = elders_info
+                    .elders
+                    .values()
+                    .map(|p2p_node| *p2p_node.peer_addr())
+                    .collect();
+
+                if self.trusted_keys.is_empty() {
+                    // Trust on first use.
+                    self.trusted_keys.push(section_key);
+                    self.update_elders_info(elders_info);
+                }
+
+                self.send_section_proof_request();
+            }
+            BootstrapResponse::Rebootstrap(addrs) => {
+                info!(
+                    "Bootstrapping redirected to another set of peers: {:?}",
+                    addrs
+                );
+                for addr in addrs {
+                    self.send_bootstrap_request(addr);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_section_proof_response(
+        &mut self,
+        elders_info: Proven<EldersInfo>,
+        proof_chain: &CompactProofChain,
+    ) {
+        match proof_chain.check_trust(&self.trusted_keys) {
+            TrustStatus::Trusted => (),
+            status => {
+                debug!("Ignoring SectionProofResponse with {:?} proof", status);
+                return;
+            }
+        }
+
+        // Only the keys after the latest trusted one have been verified.
+        let keys: Vec<_> = proof_chain.keys().map(|(_, key)| *key).collect();
+        let start = keys
+            .iter()
+            .rposition(|key| self.trusted_keys.contains(key))
+            .unwrap_or(0);
+        for key in keys.into_iter().skip(start + 1) {
+            if !self.trusted_keys.contains(&key) {
+                self.trusted_keys.push(key);
+            }
+        }
+
+        let elders_info_valid = self.trusted_keys.contains(&elders_info.proof.public_key)
+            && bincode::serialize(&elders_info.value)
+                .map(|bytes| elders_info.proof.verify(&bytes))
+                .unwrap_or(false);
+        if !elders_info_valid {
+            debug!("Ignoring SectionProofResponse with invalid EldersInfo");
+            return;
+        }
+
+        self.contacts = elders_info
+            .value
+            .elders
+            .values()
+            .map(|p2p_node| *p2p_node.peer_addr())
+            .collect();
+
+        if !elders_info.value.prefix.matches(self.core.name()) {
+            // Our section split. Find the half we belong to.
+            for addr in self.contacts.clone() {
+                self.send_bootstrap_request(addr);
+            }
+            return;
+        }
+
+        self.update_elders_info(elders_info.value);
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Helpers
+    ////////////////////////////////////////////////////////////////////////////
+
+    fn send_bootstrap_request(&mut self, addr: SocketAddr) {
+        if !self.pending_bootstrap.insert(addr) {
+            return;
+        }
+
+        debug!("Sending BootstrapRequest to {}.", addr);
+        self.core.send_direct_message(
+            &addr,
+            Variant::BootstrapRequest {
+                name: *self.core.name(),
+                network_id: self.core.network_id,
+            },
+        );
+    }
+
+    fn send_section_proof_request(&mut self) {
+        let known_key = if let Some(key) = self.trusted_keys.last() {
+            *key
+        } else {
+            return;
+        };
+
+        for addr in &self.contacts {
+            self.core
+                .send_direct_message(addr, Variant::SectionProofRequest { known_key });
+        }
+    }
+
+    fn update_elders_info(&mut self, elders_info: EldersInfo) {
+        let key = *self
+            .trusted_keys
+            .last()
+            .expect("no trusted key for EldersInfo");
+
+        if self.elders_info.as_ref() == Some(&elders_info) && self.section_key() == Some(&key) {
+            return;
+        }
+
+        self.core.send_event(Event::EldersChanged {
+            prefix: elders_info.prefix,
+            key,
+            elders: elders_info.elders.keys().copied().collect(),
+        });
+        self.elders_info = Some(elders_info);
+    }
+
+    fn terminate(&mut self) {
+        self.core.send_event(Event::Terminated);
+        self.running = false;
+    }
+}
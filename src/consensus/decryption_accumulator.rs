@@ -0,0 +1,220 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::signature_accumulator::{AccumulationError, DEFAULT_EXPIRATION};
+use crate::{
+    crypto::{self, Digest256},
+    time::{Duration, Instant},
+};
+use std::collections::HashMap;
+
+/// Accumulator for decryption shares of ciphertexts encrypted with a section key.
+///
+/// Collects the decryption shares of the elders one by one until enough of them are collected to
+/// decrypt the ciphertext. Like `SignatureAccumulator`, it rejects invalid shares, keeps shares
+/// created with different key sets apart and expires entries that did not collect enough shares
+/// within a given time.
+pub struct DecryptionAccumulator {
+    map: HashMap<Digest256, State>,
+    expiration: Duration,
+}
+
+impl DecryptionAccumulator {
+    /// Create new accumulator with default expiration.
+    pub fn new() -> Self {
+        Self::with_expiration(DEFAULT_EXPIRATION)
+    }
+
+    /// Create new accumulator with the given expiration.
+    pub fn with_expiration(expiration: Duration) -> Self {
+        Self {
+            map: Default::default(),
+            expiration,
+        }
+    }
+
+    /// Add the decryption share of `ciphertext` created by the holder of the `index`-th secret key
+    /// share of `public_key_set`. If enough valid shares were collected, returns the decrypted
+    /// plaintext. The errors have the same meaning as for `SignatureAccumulator::add`.
+    pub fn add(
+        &mut self,
+        ciphertext: &bls::Ciphertext,
+        public_key_set: &bls::PublicKeySet,
+        index: usize,
+        share: bls::DecryptionShare,
+    ) -> Result<Vec<u8>, AccumulationError> {
+        self.remove_expired();
+
+        if !public_key_set
+            .public_key_share(index)
+            .verify_decryption_share(&share, ciphertext)
+        {
+            return Err(AccumulationError::InvalidShare);
+        }
+
+        let mut bytes = bincode::serialize(ciphertext)?;
+        bytes.extend_from_slice(&public_key_set.public_key().to_bytes());
+        let hash = crypto::sha3_256(&bytes);
+
+        self.map.entry(hash).or_insert_with(State::new).add(
+            ciphertext,
+            public_key_set,
+            index,
+            share,
+        )
+    }
+
+    fn remove_expired(&mut self) {
+        let expiration = self.expiration;
+        self.map
+            .retain(|_, state| state.modified().elapsed() < expiration)
+    }
+}
+
+impl Default for DecryptionAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum State {
+    Accumulating {
+        shares: HashMap<usize, bls::DecryptionShare>,
+        modified: Instant,
+    },
+    Accumulated {
+        modified: Instant,
+    },
+}
+
+impl State {
+    fn new() -> Self {
+        Self::Accumulating {
+            shares: Default::default(),
+            modified: Instant::now(),
+        }
+    }
+
+    fn add(
+        &mut self,
+        ciphertext: &bls::Ciphertext,
+        public_key_set: &bls::PublicKeySet,
+        index: usize,
+        share: bls::DecryptionShare,
+    ) -> Result<Vec<u8>, AccumulationError> {
+        match self {
+            Self::Accumulating { shares, modified } => {
+                if shares.insert(index, share).is_none() {
+                    *modified = Instant::now();
+                } else {
+                    // Duplicate share
+                    return Err(AccumulationError::NotEnoughShares);
+                }
+
+                if shares.len() > public_key_set.threshold() {
+                    let plaintext = public_key_set.decrypt(
+                        shares.iter().map(|(&index, share)| (index, share)),
+                        ciphertext,
+                    )?;
+
+                    let modified = *modified;
+                    *self = Self::Accumulated { modified };
+                    Ok(plaintext)
+                } else {
+                    Err(AccumulationError::NotEnoughShares)
+                }
+            }
+            Self::Accumulated { .. } => Err(AccumulationError::AlreadyAccumulated),
+        }
+    }
+
+    fn modified(&self) -> Instant {
+        match self {
+            Self::Accumulating { modified, .. } | Self::Accumulated { modified } => *modified,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    #[test]
+    fn smoke() {
+        let mut rng = rng::new();
+        let threshold = 3;
+        let sk_set = bls::SecretKeySet::random(threshold, &mut rng);
+        let pk_set = sk_set.public_keys();
+
+        let plaintext = b"sealed bid".to_vec();
+        let ciphertext = pk_set.public_key().encrypt(&plaintext);
+
+        let mut accumulator = DecryptionAccumulator::new();
+
+        // Not enough shares yet
+        for index in 0..threshold {
+            let share = create_share(&sk_set, index, &ciphertext);
+            let result = accumulator.add(&ciphertext, &pk_set, index, share);
+
+            match result {
+                Err(AccumulationError::NotEnoughShares) => (),
+                _ => panic!("unexpected result: {:?}", result),
+            }
+        }
+
+        // Enough shares now
+        let share = create_share(&sk_set, threshold, &ciphertext);
+        let decrypted = accumulator
+            .add(&ciphertext, &pk_set, threshold, share)
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        // Extra shares are ignored
+        let share = create_share(&sk_set, threshold + 1, &ciphertext);
+        let result = accumulator.add(&ciphertext, &pk_set, threshold + 1, share);
+
+        match result {
+            Err(AccumulationError::AlreadyAccumulated) => (),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn invalid_share() {
+        let mut rng = rng::new();
+        let threshold = 3;
+        let sk_set = bls::SecretKeySet::random(threshold, &mut rng);
+        let pk_set = sk_set.public_keys();
+
+        let ciphertext = pk_set.public_key().encrypt(b"escrow");
+        let other_ciphertext = pk_set.public_key().encrypt(b"other");
+
+        let mut accumulator = DecryptionAccumulator::new();
+
+        // A share of a different ciphertext is rejected.
+        let share = create_share(&sk_set, 0, &other_ciphertext);
+        let result = accumulator.add(&ciphertext, &pk_set, 0, share);
+
+        match result {
+            Err(AccumulationError::InvalidShare) => (),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    fn create_share(
+        sk_set: &bls::SecretKeySet,
+        index: usize,
+        ciphertext: &bls::Ciphertext,
+    ) -> bls::DecryptionShare {
+        sk_set
+            .secret_key_share(index)
+            .decrypt_share(ciphertext)
+            .unwrap()
+    }
+}
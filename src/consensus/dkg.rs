@@ -11,6 +11,7 @@ use crate::{
     id::{FullId, PublicId},
     rng::{MainRng, RngCompat},
     section::EldersInfo,
+    time::{Duration, Instant},
     QUORUM_DENOMINATOR, QUORUM_NUMERATOR,
 };
 use bls::{PublicKeySet, SecretKeyShare};
@@ -18,7 +19,7 @@ use bls_dkg::key_gen::{message::Message as DkgMessage, KeyGen};
 use std::{
     collections::{BTreeMap, BTreeSet, VecDeque},
     fmt::{self, Debug, Formatter},
-    mem,
+    iter, mem,
 };
 
 /// Returns the number of minumn responsive participants expected for the DKG process
@@ -80,6 +81,8 @@ pub struct DkgVoter {
     // Cache of notified dkg_result. During split or demote,
     // old elders will be notified by the new elders.
     dkg_result_cache: BTreeMap<DkgKey, DkgResult>,
+    // Holds the progress of each key generator, to detect the ones that take too long.
+    progress_map: BTreeMap<DkgKey, DkgProgress>,
     timer_token: u64,
 }
 
@@ -90,6 +93,7 @@ impl Default for DkgVoter {
             key_gen_map: Default::default(),
             pending_accumulated_events: Default::default(),
             dkg_result_cache: Default::default(),
+            progress_map: Default::default(),
             timer_token: 0,
         }
     }
@@ -131,6 +135,31 @@ impl DkgVoter {
     // Free a completed key generator.
     pub fn remove_voter(&mut self, dkg_key: &DkgKey) {
         let _ = self.key_gen_map.remove(dkg_key);
+        let _ = self.progress_map.remove(dkg_key);
+    }
+
+    // Returns the key generators that were started more than `deadline` ago and are still not
+    // finalized, together with the participants we haven't received any message from. Each key
+    // generator is returned only once, but it is kept running in case it completes after all.
+    pub fn take_expired(&mut self, deadline: Duration) -> Vec<(DkgKey, BTreeSet<PublicId>)> {
+        let mut expired = Vec::new();
+        for (key, progress) in self.progress_map.iter_mut() {
+            if progress.reported || progress.started.elapsed() < deadline {
+                continue;
+            }
+
+            progress.reported = true;
+
+            let non_contributors = key
+                .0
+                .iter()
+                .filter(|pub_id| !progress.contributors.contains(pub_id))
+                .copied()
+                .collect();
+            expired.push((key.clone(), non_contributors));
+        }
+
+        expired
     }
 
     // Make key generator progress with timed phase. Returns with DkgMessages to broadcast if any.
@@ -153,8 +182,15 @@ impl DkgVoter {
         &mut self,
         rng: &mut MainRng,
         dkg_key: &DkgKey,
+        sender: PublicId,
         message: DkgMessage<PublicId>,
     ) -> Vec<DkgMessage<PublicId>> {
+        if let Some(progress) = self.progress_map.get_mut(dkg_key) {
+            if dkg_key.0.contains(&sender) {
+                let _ = progress.contributors.insert(sender);
+            }
+        }
+
         if let Some(key_gen) = self.key_gen_map.get_mut(dkg_key) {
             if let Ok(responses) = key_gen.handle_message(rng, message) {
                 return responses;
@@ -180,6 +216,9 @@ impl DkgVoter {
             debug!("started key_gen of {:?}", dkg_key);
 
             let _ = self.key_gen_map.insert(dkg_key.clone(), key_gen);
+            let _ = self
+                .progress_map
+                .insert(dkg_key.clone(), DkgProgress::new(*full_id.public_id()));
             vec![message]
         } else {
             vec![]
@@ -252,3 +291,23 @@ impl DkgVoter {
         self.pending_accumulated_events.push_front((event, proof));
     }
 }
+
+// Progress of a single key generator.
+struct DkgProgress {
+    // When the key generator was started.
+    started: Instant,
+    // Participants that sent us at least one DKG message, including ourselves.
+    contributors: BTreeSet<PublicId>,
+    // Whether the key generator was already reported as expired.
+    reported: bool,
+}
+
+impl DkgProgress {
+    fn new(our_id: PublicId) -> Self {
+        Self {
+            started: Instant::now(),
+            contributors: iter::once(our_id).collect(),
+            reported: false,
+        }
+    }
+}
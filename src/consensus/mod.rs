@@ -6,6 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod decryption_accumulator;
 mod dkg;
 mod event_accumulator;
 mod genesis_prefix_info;
@@ -17,7 +18,8 @@ mod signature_accumulator;
 pub mod test_utils;
 
 pub use self::{
-    dkg::{generate_secret_key_set, threshold_count, DkgResult, DkgVoter},
+    decryption_accumulator::DecryptionAccumulator,
+    dkg::{generate_secret_key_set, threshold_count, DkgKey, DkgResult, DkgVoter},
     genesis_prefix_info::GenesisPrefixInfo,
     network_event::{AccumulatingEvent, NetworkEvent},
     parsec::{
@@ -8,11 +8,15 @@
 
 use super::{Observation, ParsecNetworkEvent, ProofShare};
 use crate::{
+    crypto::Digest256,
     error::Result,
     id::{P2pNode, PublicId},
     messages::MessageHash,
+    notary::NotaryBatch,
     relocation::RelocateDetails,
-    section::{member_info, EldersInfo, MemberState, SectionKeyShare},
+    section::{
+        member_info, BeaconInput, EldersInfo, MemberState, RecoverySnapshot, SectionKeyShare,
+    },
     Prefix, XorName,
 };
 use hex_fmt::HexFmt;
@@ -84,6 +88,77 @@ pub enum AccumulatingEvent {
     // Voted for node to be relocated out of our section.
     Relocate(RelocateDetails),
 
+    // Voted to admit one additional member on request of the upper layers. Carries the index of
+    // the request, so that requests don't collide and elders asking for different numbers of
+    // members agree on as many as a quorum of them asked for.
+    RequestMoreNodes(u64),
+
+    // Voted to update the address of a member that proved to be reachable at a new one.
+    AddressUpdate(P2pNode),
+
+    // Voted to remove a member from our section on request of the upper layers.
+    Evict(XorName),
+
+    // Voted to start taking a recovery snapshot of our section with the given index.
+    StartRecoverySnapshot(u64),
+
+    // Voted for the recovery snapshot of our section.
+    RecoverySnapshot(Box<RecoverySnapshot>),
+
+    // Voted to generate a new section key by the current elders, replacing the key with the
+    // given index in our section proof chain.
+    RefreshKey(u64),
+
+    // Voted to abandon the DKG among the given participants that didn't complete in time and to
+    // restart it without `non_contributor`, which failed to contribute to it. Elders vote for each
+    // non-contributor they noticed separately, so their votes accumulate even if they noticed
+    // different ones.
+    DkgFailure {
+        participants: BTreeSet<PublicId>,
+        section_key_index: u64,
+        non_contributor: PublicId,
+    },
+
+    // Voted to decrypt the given ciphertext, encrypted with our section key at the given index of
+    // our section proof chain, on request of the upper layers.
+    Decrypt {
+        key_index: u64,
+        ciphertext: Box<bls::Ciphertext>,
+    },
+
+    // Voted for the next round of our section randomness beacon.
+    BeaconRound(BeaconInput),
+
+    // Voted to notarise the given document hash on request of the upper layers.
+    Notarise(Digest256),
+
+    // Voted to advance the notary time to the given timestamp, which is always one step after the
+    // current one, and to put the pending notarised hashes, if any, into a new batch.
+    NotaryTick(u64),
+
+    // Voted for the Merkle root of a batch of notarised hashes.
+    NotaryBatch(NotaryBatch),
+
+    // Voted to roll our section out to the given protocol version on request of the upper layers,
+    // possibly allowing our elders to restart even if that costs us the quorum for a while.
+    ProposeUpgrade {
+        version: u16,
+        allow_quorum_loss: bool,
+    },
+
+    // Voted that the given node runs the given protocol version.
+    UpgradeReport {
+        name: XorName,
+        version: u16,
+    },
+
+    // Voted for the skip link to the key at the given index of our section proof chain, with the
+    // signature accumulated from the shares of the elders holding the older key.
+    SkipLink {
+        index: u64,
+        signature: bls::Signature,
+    },
+
     // Opaque user-defined event.
     User(Vec<u8>),
 }
@@ -181,6 +256,34 @@ impl AccumulatingEvent {
                     MemberState::Left,
                 ))
             }
+            Self::Evict(name) => {
+                // Note: same as `Offline` because the proof is stored in the members map.
+                bincode::serialize(&member_info::to_sign(name, MemberState::Left))
+            }
+            Self::RequestMoreNodes(index) => bincode::serialize(index),
+            Self::AddressUpdate(p2p_node) => bincode::serialize(p2p_node),
+            Self::StartRecoverySnapshot(index) => bincode::serialize(index),
+            Self::RecoverySnapshot(snapshot) => bincode::serialize(snapshot),
+            Self::RefreshKey(index) => bincode::serialize(index),
+            Self::DkgFailure {
+                participants,
+                section_key_index,
+                non_contributor,
+            } => bincode::serialize(&(participants, section_key_index, non_contributor)),
+            Self::Decrypt {
+                key_index,
+                ciphertext,
+            } => bincode::serialize(&(key_index, ciphertext)),
+            Self::BeaconRound(input) => bincode::serialize(input),
+            Self::Notarise(hash) => bincode::serialize(hash),
+            Self::NotaryTick(timestamp) => bincode::serialize(timestamp),
+            Self::NotaryBatch(batch) => bincode::serialize(batch),
+            Self::ProposeUpgrade {
+                version,
+                allow_quorum_loss,
+            } => bincode::serialize(&(version, allow_quorum_loss)),
+            Self::UpgradeReport { name, version } => bincode::serialize(&(name, version)),
+            Self::SkipLink { index, signature } => bincode::serialize(&(index, signature)),
 
             // TODO: serialise these variants properly
             Self::SendNeighbourInfo { .. } | Self::ParsecPrune | Self::User(_) => Ok(vec![]),
@@ -238,6 +341,54 @@ impl Debug for AccumulatingEvent {
             ),
             Self::ParsecPrune => write!(formatter, "ParsecPrune"),
             Self::Relocate(payload) => write!(formatter, "Relocate({:?})", payload),
+            Self::RequestMoreNodes(index) => write!(formatter, "RequestMoreNodes({})", index),
+            Self::AddressUpdate(p2p_node) => write!(formatter, "AddressUpdate({})", p2p_node),
+            Self::Evict(name) => write!(formatter, "Evict({})", name),
+            Self::StartRecoverySnapshot(index) => {
+                write!(formatter, "StartRecoverySnapshot({})", index)
+            }
+            Self::RecoverySnapshot(snapshot) => formatter
+                .debug_struct("RecoverySnapshot")
+                .field("index", &snapshot.index)
+                .field("elders_info", &snapshot.elders_info)
+                .field("members", &snapshot.members.len())
+                .finish(),
+            Self::RefreshKey(index) => write!(formatter, "RefreshKey({})", index),
+            Self::DkgFailure {
+                participants,
+                section_key_index,
+                non_contributor,
+            } => formatter
+                .debug_struct("DkgFailure")
+                .field("participants", participants)
+                .field("section_key_index", section_key_index)
+                .field("non_contributor", non_contributor)
+                .finish(),
+            Self::Decrypt { key_index, .. } => write!(formatter, "Decrypt({}, ..)", key_index),
+            Self::BeaconRound(input) => write!(formatter, "BeaconRound({})", input.round),
+            Self::Notarise(hash) => write!(formatter, "Notarise({:10})", HexFmt(hash)),
+            Self::NotaryTick(timestamp) => write!(formatter, "NotaryTick({})", timestamp),
+            Self::NotaryBatch(batch) => write!(
+                formatter,
+                "NotaryBatch {{ index: {}, timestamp: {}, root: {:10} }}",
+                batch.index,
+                batch.timestamp,
+                HexFmt(&batch.root)
+            ),
+            Self::ProposeUpgrade {
+                version,
+                allow_quorum_loss,
+            } => write!(
+                formatter,
+                "ProposeUpgrade {{ version: {}, allow_quorum_loss: {} }}",
+                version, allow_quorum_loss
+            ),
+            Self::UpgradeReport { name, version } => write!(
+                formatter,
+                "UpgradeReport {{ name: {}, version: {} }}",
+                name, version
+            ),
+            Self::SkipLink { index, .. } => write!(formatter, "SkipLink({})", index),
             Self::User(payload) => write!(formatter, "User({:<8})", HexFmt(payload)),
         }
     }
@@ -78,15 +78,19 @@ impl Debug for ProofShare {
 /// A value together with the proof that it was agreed on by the quorum of the section elders.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct Proven<T: Serialize> {
+    /// The proven value.
     pub value: T,
+    /// Proof of the bincode serialisation of `value`.
     pub proof: Proof,
 }
 
 impl<T: Serialize> Proven<T> {
+    /// Creates new proven value.
     pub fn new(value: T, proof: Proof) -> Self {
         Self { value, proof }
     }
 
+    /// Verifies the proof against the value and checks it was signed by a key of `history`.
     pub fn verify(&self, history: &SectionProofChain) -> bool {
         if let Ok(bytes) = bincode::serialize(&self.value) {
             history.has_key(&self.proof.public_key) && self.proof.verify(&bytes)
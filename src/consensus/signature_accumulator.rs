@@ -138,7 +138,7 @@ where
     }
 }
 
-/// Error returned from SignatureAccumulator::add.
+/// Error returned from SignatureAccumulator::add and DecryptionAccumulator::add.
 #[derive(Debug, Error)]
 pub enum AccumulationError {
     /// There are not enough signature shares yet, more need to be added. This is not a failure.
@@ -7,33 +7,52 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    error::Result,
+    error::{Result, RoutingError},
     event::Event,
     id::{FullId, PublicId},
     location::DstLocation,
     message_filter::MessageFilter,
-    messages::{Message, QueuedMessage, Variant},
-    network_params::NetworkParams,
+    messages::{self, CompressionStats, Message, QueuedMessage, Variant},
+    network_id::NetworkId,
+    network_params::{Compression, NetworkParams},
     node::NodeConfig,
+    peer_binding::PeerBindings,
+    protocol::{Protocol, COMPRESSION_VERSION, MIN_PROTOCOL_VERSION},
     quic_p2p::{EventSenders, OurType, Token},
+    rate_limit::RateLimiter,
     rng::{self, MainRng},
     timer::Timer,
-    transport::{PeerStatus, Transport},
+    transport::{PeerStatus, Priority, Transport},
 };
 use bytes::Bytes;
 use crossbeam_channel::Sender;
+use lru_time_cache::LruCache;
 use std::{collections::VecDeque, net::SocketAddr, slice};
 use xor_name::XorName;
 
+// Maximum number of peers whose protocol we remember.
+const MAX_PEER_PROTOCOLS: usize = 1024;
+
 // Core components of the node.
 pub struct Core {
     pub network_params: NetworkParams,
+    // Id of our network, if already known.
+    pub network_id: Option<NetworkId>,
     pub full_id: FullId,
     pub transport: Transport,
     pub msg_filter: MessageFilter,
     pub msg_queue: VecDeque<QueuedMessage>,
+    pub peer_bindings: PeerBindings,
     pub timer: Timer,
     pub rng: MainRng,
+    pub compression_stats: CompressionStats,
+    // Number of messages dropped instead of relayed for exceeding `NetworkParams::max_hops`.
+    pub hop_limit_drops: u64,
+    pub rate_limiter: RateLimiter,
+    // Protocol version every elder of our section runs, which gates the features we use.
+    pub active_version: u16,
+    // Protocols declared by the peers in their latest messages to us.
+    peer_protocols: LruCache<SocketAddr, Protocol>,
     user_event_tx: Sender<Event>,
 }
 
@@ -48,40 +67,62 @@ impl Core {
         let full_id = config.full_id.unwrap_or_else(|| FullId::gen(&mut rng));
 
         config.transport_config.our_type = OurType::Node;
-        let transport = match Transport::new(transport_event_tx, config.transport_config) {
+        let transport = match Transport::new(
+            transport_event_tx,
+            config.transport_config,
+            config.resend_policy,
+        ) {
             Ok(transport) => transport,
             Err(err) => panic!("Unable to start network transport: {:?}", err),
         };
 
         Self {
             network_params: config.network_params,
+            network_id: config.network_id,
             full_id,
             transport,
             msg_filter: Default::default(),
             msg_queue: Default::default(),
+            peer_bindings: Default::default(),
             timer: Timer::new(timer_tx),
             rng,
+            compression_stats: Default::default(),
+            hop_limit_drops: 0,
+            rate_limiter: RateLimiter::new(config.rate_limits),
+            active_version: MIN_PROTOCOL_VERSION,
+            peer_protocols: LruCache::with_capacity(MAX_PEER_PROTOCOLS),
             user_event_tx,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn resume(
         network_params: NetworkParams,
+        network_id: Option<NetworkId>,
         full_id: FullId,
         transport: Transport,
         msg_filter: MessageFilter,
         msg_queue: VecDeque<QueuedMessage>,
+        peer_bindings: PeerBindings,
+        rate_limiter: RateLimiter,
         timer_tx: Sender<u64>,
         user_event_tx: Sender<Event>,
     ) -> Self {
         Self {
             network_params,
+            network_id,
             full_id,
             transport,
             msg_filter,
             msg_queue,
+            peer_bindings,
             timer: Timer::new(timer_tx),
             rng: rng::new(),
+            compression_stats: Default::default(),
+            hop_limit_drops: 0,
+            rate_limiter,
+            active_version: MIN_PROTOCOL_VERSION,
+            peer_protocols: LruCache::with_capacity(MAX_PEER_PROTOCOLS),
             user_event_tx,
         }
     }
@@ -106,17 +147,123 @@ impl Core {
         conn_infos: &[SocketAddr],
         delivery_group_size: usize,
         msg: Bytes,
+        priority: Priority,
     ) {
+        let msg = messages::for_peer(msg, &self.targets_protocol(conn_infos));
+        let uncompressed_len = msg.len() as u64;
+        let params = NetworkParams {
+            compression: self.compression(),
+            ..self.network_params
+        };
+        let msg = messages::compress(msg, &params);
+        let count = delivery_group_size.min(conn_infos.len()) as u64;
+        self.compression_stats.uncompressed += count * uncompressed_len;
+        self.compression_stats.sent += count * msg.len() as u64;
+
         self.transport
-            .send_message_to_targets(conn_infos, delivery_group_size, msg)
+            .send_message_to_targets(conn_infos, delivery_group_size, msg, priority)
     }
 
-    pub fn send_message_to_target(&mut self, recipient: &SocketAddr, msg: Bytes) {
-        self.transport
-            .send_message_to_targets(slice::from_ref(recipient), 1, msg)
+    // Records the protocol the peer at `addr` declared in its message to us, so we only send it
+    // messages it can decode.
+    pub fn record_protocol(&mut self, addr: SocketAddr, protocol: Protocol) {
+        let _ = self.peer_protocols.insert(addr, protocol);
+    }
+
+    // Returns the protocol all of `conn_infos` can decode: the oldest version and the features
+    // common to all of them. Peers we haven't heard from yet are assumed to speak ours.
+    fn targets_protocol(&mut self, conn_infos: &[SocketAddr]) -> Protocol {
+        let mut result = Protocol::OURS;
+        for addr in conn_infos {
+            if let Some(protocol) = self.peer_protocols.get(addr) {
+                result.version = result.version.min(protocol.version);
+                result.features &= protocol.features;
+            }
+        }
+        result
+    }
+
+    pub fn send_message_to_target(
+        &mut self,
+        recipient: &SocketAddr,
+        msg: Bytes,
+        priority: Priority,
+    ) {
+        self.send_message_to_targets(slice::from_ref(recipient), 1, msg, priority)
+    }
+
+    // Challenges the peer at `addr` to prove which `PublicId` it owns, unless already challenged.
+    pub fn challenge_peer(&mut self, addr: SocketAddr) {
+        if let Some(nonce) = self.peer_bindings.challenge(addr, &mut self.rng) {
+            trace!("Sending AddressChallenge to {}", addr);
+            self.send_direct_message(
+                &addr,
+                Variant::AddressChallenge {
+                    nonce,
+                    network_id: self.network_id,
+                },
+            )
+        }
+    }
+
+    // Checks the network id received from the peer at `addr` against ours. On mismatch, disconnects
+    // the peer, notifies the user and returns an error. Ids not known to either side are not
+    // checked.
+    pub fn check_network_id(&mut self, addr: &SocketAddr, theirs: Option<NetworkId>) -> Result<()> {
+        match (self.network_id, theirs) {
+            (Some(ours), Some(theirs)) if ours != theirs => {
+                warn!(
+                    "Rejecting peer {} from a different network (ours: {}, theirs: {})",
+                    addr, ours, theirs
+                );
+                self.transport.disconnect(*addr);
+                self.peer_bindings.remove(addr);
+                self.send_event(Event::NetworkIdMismatch {
+                    peer: *addr,
+                    ours,
+                    theirs,
+                });
+                Err(RoutingError::NetworkIdMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Codec to compress our messages with: the configured one, but only once every elder of our
+    // section runs a protocol version that can decode compressed messages.
+    pub fn compression(&self) -> Option<Compression> {
+        self.network_params
+            .compression
+            .filter(|_| self.active_version >= COMPRESSION_VERSION)
+    }
+
+    // Checks the protocol declared by the peer at `addr` against ours. The peer must be able to
+    // decode our messages, including the compressed ones. On mismatch, disconnects the peer,
+    // notifies the user and returns an error.
+    pub fn check_protocol(&mut self, addr: &SocketAddr, theirs: &Protocol) -> Result<()> {
+        let required_features = self.compression().map(Compression::feature).unwrap_or(0);
+        if theirs.is_compatible(required_features) {
+            return Ok(());
+        }
+
+        warn!(
+            "Rejecting peer {} speaking an incompatible protocol (ours: {}, theirs: {})",
+            addr,
+            Protocol::OURS,
+            theirs
+        );
+        self.transport.disconnect(*addr);
+        self.peer_bindings.remove(addr);
+        self.send_event(Event::ProtocolMismatch {
+            peer: *addr,
+            ours: Protocol::OURS,
+            theirs: *theirs,
+        });
+        Err(RoutingError::ProtocolMismatch)
     }
 
     pub fn send_direct_message(&mut self, recipient: &SocketAddr, variant: Variant) {
+        let priority = variant.priority();
         let message = match Message::single_src(&self.full_id, DstLocation::Direct, None, variant) {
             Ok(message) => message,
             Err(error) => {
@@ -125,7 +272,7 @@ impl Core {
             }
         };
 
-        self.send_message_to_target(recipient, message.to_bytes())
+        self.send_message_to_target(recipient, message.to_bytes(), priority)
     }
 
     pub fn handle_unsent_message(
@@ -134,8 +281,44 @@ impl Core {
         msg: Bytes,
         msg_token: Token,
     ) -> PeerStatus {
-        self.transport
-            .target_failed(msg, msg_token, addr, &self.timer)
+        let (peer_status, undelivered) =
+            self.transport
+                .target_failed(msg.clone(), msg_token, addr, &self.timer, &mut self.rng);
+
+        if let PeerStatus::Normal = peer_status {
+            // The message is resent to another target.
+            self.compression_stats.uncompressed += messages::uncompressed_len(&msg) as u64;
+            self.compression_stats.sent += msg.len() as u64;
+        }
+
+        if let Some(msg) = undelivered {
+            self.report_undelivered(&msg);
+        }
+
+        peer_status
+    }
+
+    // Notifies the user about a user message that couldn't be delivered to any of its targets, if
+    // it is from us or, as elders, from our section. The messages we only relay for others and our
+    // control messages are not the user's business, so they are only logged.
+    fn report_undelivered(&self, msg: &Bytes) {
+        let msg = match Message::from_bytes(msg) {
+            Ok(msg) => msg,
+            Err(error) => {
+                debug!("Failed to decode undelivered message: {:?}", error);
+                return;
+            }
+        };
+
+        if !msg.variant().is_user_message() || !msg.src().src_location().contains(self.name()) {
+            debug!("Failed to deliver {:?}", msg);
+            return;
+        }
+
+        self.send_event(Event::DeliveryFailed {
+            dst: *msg.dst(),
+            msg_hash: *msg.hash(),
+        });
     }
 
     pub fn send_event(&self, event: Event) {
@@ -13,6 +13,7 @@ use crate::{
     id::{P2pNode, PublicId},
     location::DstLocation,
     section::{SectionMap, SectionMembers},
+    transport::FailureHistory,
 };
 
 use itertools::Itertools;
@@ -38,11 +39,27 @@ pub const fn delivery_group_size(n: usize) -> usize {
 ///     - if our name *is* the destination, returns an empty set; otherwise
 ///     - if the destination name is an entry in the routing table, returns it; otherwise
 ///     - returns the `N/3` closest members of the RT to the target
+///
+/// Targets we recently failed to send to are moved behind the others, so they become the spare
+/// ones if there are enough targets.
 pub fn delivery_targets(
     dst: &DstLocation,
     our_id: &PublicId,
     our_members: &SectionMembers,
     sections: &SectionMap,
+    failures: &FailureHistory,
+) -> Result<(Vec<P2pNode>, usize)> {
+    let (mut targets, dg_size) = prioritised_targets(dst, our_id, our_members, sections)?;
+    // Stable sort, so targets with the same number of failures keep their priority.
+    targets.sort_by_key(|node| failures.failures(node.peer_addr()));
+    Ok((targets, dg_size))
+}
+
+fn prioritised_targets(
+    dst: &DstLocation,
+    our_id: &PublicId,
+    our_members: &SectionMembers,
+    sections: &SectionMap,
 ) -> Result<(Vec<P2pNode>, usize)> {
     if !sections.is_elder(our_id.name()) {
         // We are not Elder - return all the elders of our section, so the message can be properly
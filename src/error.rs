@@ -38,4 +38,10 @@ pub enum RoutingError {
     InvalidSignatureShares,
     #[error(display = "An Elder DKG result is invalid.")]
     InvalidElderDkgResult,
+    #[error(display = "The peer belongs to a different network.")]
+    NetworkIdMismatch,
+    #[error(display = "The peer speaks an incompatible protocol.")]
+    ProtocolMismatch,
+    #[error(display = "The message payload exceeds the maximum payload size.")]
+    PayloadTooLarge,
 }
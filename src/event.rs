@@ -6,12 +6,20 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::location::{DstLocation, SrcLocation};
+use crate::{
+    location::{DstLocation, SrcLocation},
+    messages::MessageHash,
+    network_id::NetworkId,
+    notary::NotaryReceipt,
+    protocol::Protocol,
+    section::LocatedSection,
+};
 
 use hex_fmt::HexFmt;
 use std::{
     collections::BTreeSet,
     fmt::{self, Debug, Formatter},
+    net::SocketAddr,
 };
 use xor_name::{Prefix, XorName};
 
@@ -48,6 +56,25 @@ pub enum Event {
     },
     /// Consensus on a custom event.
     Consensus(Vec<u8>),
+    /// Our section decrypted a ciphertext encrypted with its key, requested through
+    /// `Node::request_section_decryption`.
+    SectionDecrypted {
+        /// The decrypted ciphertext.
+        ciphertext: bls::Ciphertext,
+        /// The plaintext.
+        plaintext: Vec<u8>,
+    },
+    /// Our section produced a new round of its randomness beacon.
+    BeaconRound {
+        /// Number of the round.
+        round: u64,
+        /// The random value: the section signature of the round number and the previous value.
+        value: bls::Signature,
+    },
+    /// A hash passed to `Node::notarise` was included in a batch signed by our section.
+    Notarised(Box<NotaryReceipt>),
+    /// The section responsible for the name passed to `Node::locate` answered our query.
+    Located(Box<LocatedSection>),
     /// The node has been promoted to elder
     Promoted,
     /// The node has been demoted from elder
@@ -84,6 +111,73 @@ pub enum Event {
         /// The set of elders of our section.
         elders: BTreeSet<XorName>,
     },
+    /// Our elders completed the generation of a new section key.
+    DkgCompleted {
+        /// The elders that generated the key.
+        elders: BTreeSet<XorName>,
+        /// The new BLS public key of our section.
+        key: bls::PublicKey,
+    },
+    /// The generation of a new section key didn't complete in time. Unless all the elders took
+    /// part in it, the elders vote to restart it without the ones that didn't.
+    DkgFailed {
+        /// The elders that were supposed to generate the key.
+        elders: BTreeSet<XorName>,
+        /// The elders we didn't receive any contribution from.
+        non_contributors: BTreeSet<XorName>,
+    },
+    /// A peer turned out to belong to a different network than ours and was disconnected.
+    NetworkIdMismatch {
+        /// Address of the peer.
+        peer: SocketAddr,
+        /// Our network id.
+        ours: NetworkId,
+        /// The network id the peer claims.
+        theirs: NetworkId,
+    },
+    /// A peer turned out to speak a protocol incompatible with ours and was disconnected.
+    ProtocolMismatch {
+        /// Address of the peer.
+        peer: SocketAddr,
+        /// Our protocol.
+        ours: Protocol,
+        /// The protocol the peer declared.
+        theirs: Protocol,
+    },
+    /// The destination of a message sent with `Node::send_message_with_ack` acknowledged it.
+    Delivered {
+        /// Destination of the message.
+        dst: DstLocation,
+        /// Hash of the message.
+        msg_hash: MessageHash,
+    },
+    /// The destination of a message sent with `Node::send_message_with_ack` didn't acknowledge
+    /// it within `ACK_TIMEOUT`. The message might still have been delivered.
+    DeliveryTimeout {
+        /// Destination of the message.
+        dst: DstLocation,
+        /// Hash of the message.
+        msg_hash: MessageHash,
+    },
+    /// A message we sent couldn't be delivered to any of its targets, even after resending it.
+    DeliveryFailed {
+        /// Destination of the message.
+        dst: DstLocation,
+        /// Hash of the message.
+        msg_hash: MessageHash,
+    },
+    /// It is our turn to restart into the protocol version our section is being upgraded to. The
+    /// other elders wait for us to come back running it before restarting themselves.
+    RestartForUpgrade {
+        /// The protocol version to restart into.
+        version: u16,
+    },
+    /// Every elder of our section runs the given protocol version, so the features it introduces
+    /// can be switched on.
+    UpgradeCompleted {
+        /// The new active protocol version of our section.
+        version: u16,
+    },
     /// Disconnected or failed to connect - restart required.
     RestartRequired,
     /// Startup failed - terminate.
@@ -102,6 +196,19 @@ impl Debug for Event {
                 dst
             ),
             Self::Consensus(payload) => write!(formatter, "Consensus({:<8})", HexFmt(payload)),
+            Self::SectionDecrypted { plaintext, .. } => write!(
+                formatter,
+                "SectionDecrypted {{ plaintext: \"{:<8}\" }}",
+                HexFmt(plaintext)
+            ),
+            Self::BeaconRound { round, .. } => write!(formatter, "BeaconRound({})", round),
+            Self::Notarised(receipt) => write!(
+                formatter,
+                "Notarised({:<8}, {})",
+                HexFmt(receipt.hash()),
+                receipt.timestamp()
+            ),
+            Self::Located(located) => write!(formatter, "Located({:?})", located.elders_info.value),
             Self::Promoted => write!(formatter, "Promoted"),
             Self::Demoted => write!(formatter, "Demoted"),
             Self::MemberJoined {
@@ -134,6 +241,52 @@ impl Debug for Event {
                 .field("key", key)
                 .field("elders", elders)
                 .finish(),
+            Self::DkgCompleted { elders, key } => formatter
+                .debug_struct("DkgCompleted")
+                .field("elders", elders)
+                .field("key", key)
+                .finish(),
+            Self::DkgFailed {
+                elders,
+                non_contributors,
+            } => formatter
+                .debug_struct("DkgFailed")
+                .field("elders", elders)
+                .field("non_contributors", non_contributors)
+                .finish(),
+            Self::NetworkIdMismatch { peer, ours, theirs } => formatter
+                .debug_struct("NetworkIdMismatch")
+                .field("peer", peer)
+                .field("ours", ours)
+                .field("theirs", theirs)
+                .finish(),
+            Self::ProtocolMismatch { peer, ours, theirs } => formatter
+                .debug_struct("ProtocolMismatch")
+                .field("peer", peer)
+                .field("ours", ours)
+                .field("theirs", theirs)
+                .finish(),
+            Self::Delivered { dst, msg_hash } => formatter
+                .debug_struct("Delivered")
+                .field("dst", dst)
+                .field("msg_hash", msg_hash)
+                .finish(),
+            Self::DeliveryTimeout { dst, msg_hash } => formatter
+                .debug_struct("DeliveryTimeout")
+                .field("dst", dst)
+                .field("msg_hash", msg_hash)
+                .finish(),
+            Self::DeliveryFailed { dst, msg_hash } => formatter
+                .debug_struct("DeliveryFailed")
+                .field("dst", dst)
+                .field("msg_hash", msg_hash)
+                .finish(),
+            Self::RestartForUpgrade { version } => {
+                write!(formatter, "RestartForUpgrade {{ version: {} }}", version)
+            }
+            Self::UpgradeCompleted { version } => {
+                write!(formatter, "UpgradeCompleted {{ version: {} }}", version)
+            }
             Self::RestartRequired => write!(formatter, "RestartRequired"),
             Self::Terminated => write!(formatter, "Terminated"),
         }
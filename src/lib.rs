@@ -79,16 +79,29 @@ mod macros;
 // Public API
 // ############################################################################
 pub use self::{
-    consensus::{AccumulationError, Proof, ProofShare, SignatureAccumulator},
+    client::{Client, ClientConfig, CLIENT_REFRESH_INTERVAL},
+    consensus::{AccumulationError, Proof, ProofShare, Proven, SignatureAccumulator},
     error::RoutingError,
     id::{FullId, P2pNode, PublicId},
     location::{DstLocation, SrcLocation},
-    network_params::NetworkParams,
-    node::{Node, NodeConfig},
+    messages::{CompressionStats, MessageHash, MAX_CHUNK_SIZE, MAX_PAYLOAD_SIZE},
+    network_id::NetworkId,
+    network_params::{Compression, NetworkParams, COMPRESSION_THRESHOLD, MAX_HOPS, MAX_INFANTS},
+    node::{Node, NodeConfig, ACK_TIMEOUT},
+    notary::{NotaryReceipt, NOTARY_TIMESTAMP_GRANULARITY},
     pause::PausedState,
+    protocol::{
+        Protocol, FEATURE_DEFLATE, FEATURE_ROUTE, FEATURE_SNAPPY, MIN_PROTOCOL_VERSION,
+        PROTOCOL_VERSION,
+    },
     quic_p2p::Config as TransportConfig,
     quic_p2p::Event as TransportEvent,
-    section::SectionProofChain,
+    rate_limit::{RateLimit, RateLimitStats, RateLimits},
+    section::{
+        CompactProofChain, LocatedSection, MemberState, MembershipCertificate, MembershipStatement,
+        NetworkMap, SectionEntry, SectionProofChain, TrustStatus,
+    },
+    transport::{QueueDepths, ResendPolicy},
 };
 
 pub use xor_name::{Prefix, XorName, XOR_NAME_LEN}; // TODO remove pub on API update
@@ -131,6 +144,7 @@ pub use self::mock::parsec::init_mock;
 // Private
 // ############################################################################
 
+mod client;
 mod consensus;
 mod core;
 mod delivery_group;
@@ -139,9 +153,14 @@ mod id;
 mod location;
 mod message_filter;
 mod messages;
+mod network_id;
 mod network_params;
 mod node;
+mod notary;
 mod pause;
+mod peer_binding;
+mod protocol;
+mod rate_limit;
 mod relocation;
 #[cfg(not(feature = "mock_base"))]
 mod rng;
@@ -165,7 +184,7 @@ const QUORUM_NUMERATOR: usize = 2;
 const QUORUM_DENOMINATOR: usize = 3;
 
 /// Recommended section size. Routing will keep adding nodes until the section reaches this size.
-/// More nodes might be added if requested by the upper layers.
+/// More nodes might be added if requested by the upper layers (see `Node::request_more_nodes`).
 /// This number also detemines when split happens - if both post-split sections would have at least
 /// this number of nodes.
 const RECOMMENDED_SECTION_SIZE: usize = 60;
@@ -0,0 +1,361 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::Variant;
+use crate::{
+    crypto::{self, Digest256},
+    error::{Result, RoutingError},
+    location::{DstLocation, SrcLocation},
+    time::Duration,
+};
+use lru_time_cache::LruCache;
+
+/// User payloads larger than this are split into chunks of at most this size, each sent, signed,
+/// accumulated and relayed as a separate message.
+pub const MAX_CHUNK_SIZE: usize = 512 * 1024;
+
+// Maximum number of chunks of a single payload. Chunks claiming more are rejected, which bounds
+// the memory an incomplete payload can take.
+const MAX_CHUNK_COUNT: u32 = 32;
+
+/// Largest user payload that can be sent, in chunks of at most `MAX_CHUNK_SIZE`.
+pub const MAX_PAYLOAD_SIZE: usize = MAX_CHUNK_COUNT as usize * MAX_CHUNK_SIZE;
+
+// How long to keep the chunks of a source that stopped sending any.
+const INCOMPLETE_PAYLOAD_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+// Maximum number of sources whose incomplete payloads are kept at the same time.
+const MAX_SOURCES: usize = 16;
+
+// Maximum number of incomplete payloads kept per source. When exceeded, the oldest payload of
+// that source is dropped, so no source can evict the payloads of the others.
+const MAX_INCOMPLETE_PAYLOADS_PER_SOURCE: usize = 4;
+
+// Maximum total size of the chunks kept per source. Fits one whole payload of the maximum size,
+// so overall the assembler never holds more than `MAX_SOURCES * MAX_PAYLOAD_SIZE` bytes.
+const MAX_INCOMPLETE_BYTES_PER_SOURCE: usize = MAX_PAYLOAD_SIZE;
+
+/// Returns the variants to send `content` with: a single `UserMessage` if it's small enough,
+/// otherwise one `UserMessageChunk` per chunk.
+/// Returns `PayloadTooLarge` error if `content` exceeds `MAX_PAYLOAD_SIZE`.
+pub fn user_message_variants(content: Vec<u8>) -> Result<Vec<Variant>> {
+    if content.len() <= MAX_CHUNK_SIZE {
+        return Ok(vec![Variant::UserMessage(content)]);
+    }
+
+    if content.len() > MAX_PAYLOAD_SIZE {
+        return Err(RoutingError::PayloadTooLarge);
+    }
+
+    let hash = crypto::sha3_256(&content);
+    let count = ((content.len() + MAX_CHUNK_SIZE - 1) / MAX_CHUNK_SIZE) as u32;
+
+    Ok(content
+        .chunks(MAX_CHUNK_SIZE)
+        .enumerate()
+        .map(|(index, data)| Variant::UserMessageChunk {
+            hash,
+            index: index as u32,
+            count,
+            data: data.to_vec(),
+        })
+        .collect())
+}
+
+/// Reassembles the payloads of the `UserMessageChunk`s received.
+pub struct ChunkAssembler {
+    // Incomplete payloads by the hash of their source.
+    sources: LruCache<Digest256, Source>,
+}
+
+impl ChunkAssembler {
+    /// Adds the chunk of the payload with the given hash. Returns the whole payload once all its
+    /// chunks were received and it matches the hash.
+    pub fn add(
+        &mut self,
+        src: &SrcLocation,
+        dst: &DstLocation,
+        hash: Digest256,
+        index: u32,
+        count: u32,
+        data: Vec<u8>,
+    ) -> Option<Vec<u8>> {
+        if index >= count || count > MAX_CHUNK_COUNT || data.len() > MAX_CHUNK_SIZE {
+            trace!("Invalid chunk {}/{} of {:?}", index, count, src);
+            return None;
+        }
+
+        // Chunks of the same payload sent from different sources or to different destinations are
+        // assembled separately, so nobody can complete a payload on behalf of someone else.
+        let source_key = bincode::serialize(src)
+            .map(|bytes| crypto::sha3_256(&bytes))
+            .ok()?;
+        let key = bincode::serialize(&(dst, &hash, count))
+            .map(|bytes| crypto::sha3_256(&bytes))
+            .ok()?;
+
+        let source = self
+            .sources
+            .entry(source_key)
+            .or_insert_with(Source::default);
+        let payload = source.add(key, index, count, data)?;
+
+        if source.payloads.is_empty() {
+            let _ = self.sources.remove(&source_key);
+        }
+
+        if crypto::sha3_256(&payload) == hash {
+            Some(payload)
+        } else {
+            debug!("Reassembled payload from {:?} doesn't match its hash", src);
+            None
+        }
+    }
+}
+
+impl Default for ChunkAssembler {
+    fn default() -> Self {
+        Self {
+            sources: LruCache::with_expiry_duration_and_capacity(
+                INCOMPLETE_PAYLOAD_EXPIRY,
+                MAX_SOURCES,
+            ),
+        }
+    }
+}
+
+// Incomplete payloads of a single source, oldest first.
+#[derive(Default)]
+struct Source {
+    payloads: Vec<(Digest256, Incomplete)>,
+}
+
+impl Source {
+    // Adds the chunk and returns the whole payload once complete, without checking its hash.
+    fn add(&mut self, key: Digest256, index: u32, count: u32, data: Vec<u8>) -> Option<Vec<u8>> {
+        let position = if let Some(position) = self.payloads.iter().position(|(k, _)| *k == key) {
+            position
+        } else {
+            if self.payloads.len() >= MAX_INCOMPLETE_PAYLOADS_PER_SOURCE {
+                let _ = self.payloads.remove(0);
+            }
+
+            self.payloads.push((key, Incomplete::new(count)));
+            self.payloads.len() - 1
+        };
+
+        if self.payloads[position].1.has(index) {
+            return None;
+        }
+
+        // Make room by dropping the oldest other payloads of this source.
+        let mut position = position;
+        while position > 0 && self.bytes() + data.len() > MAX_INCOMPLETE_BYTES_PER_SOURCE {
+            let _ = self.payloads.remove(0);
+            position -= 1;
+        }
+
+        let incomplete = &mut self.payloads[position].1;
+        incomplete.insert(index, data);
+        if incomplete.is_complete() {
+            Some(self.payloads.remove(position).1.into_payload())
+        } else {
+            None
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.payloads
+            .iter()
+            .map(|(_, incomplete)| incomplete.bytes)
+            .sum()
+    }
+}
+
+struct Incomplete {
+    chunks: Vec<Option<Vec<u8>>>,
+    missing: usize,
+    bytes: usize,
+}
+
+impl Incomplete {
+    fn new(count: u32) -> Self {
+        Self {
+            chunks: vec![None; count as usize],
+            missing: count as usize,
+            bytes: 0,
+        }
+    }
+
+    fn has(&self, index: u32) -> bool {
+        self.chunks[index as usize].is_some()
+    }
+
+    fn insert(&mut self, index: u32, data: Vec<u8>) {
+        let slot = &mut self.chunks[index as usize];
+        if slot.is_none() {
+            self.bytes += data.len();
+            *slot = Some(data);
+            self.missing -= 1;
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.missing == 0
+    }
+
+    fn into_payload(self) -> Vec<u8> {
+        self.chunks.into_iter().flatten().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+    use rand::{seq::SliceRandom, Rng};
+    use xor_name::XorName;
+
+    #[test]
+    fn small_payload_is_not_chunked() {
+        let content = vec![7; MAX_CHUNK_SIZE];
+        assert_eq!(
+            user_message_variants(content.clone()).unwrap(),
+            vec![Variant::UserMessage(content)]
+        );
+    }
+
+    #[test]
+    fn chunks_are_reassembled_in_any_order() {
+        let mut rng = rng::new();
+        let content: Vec<u8> = (0..2 * MAX_CHUNK_SIZE + 1).map(|_| rng.gen()).collect();
+        let src = SrcLocation::Node(rng.gen());
+        let dst = DstLocation::Node(rng.gen::<XorName>());
+
+        let mut variants = user_message_variants(content.clone()).unwrap();
+        assert_eq!(variants.len(), 3);
+        variants.shuffle(&mut rng);
+
+        let mut assembler = ChunkAssembler::default();
+        let mut results: Vec<_> = variants
+            .into_iter()
+            .map(|variant| match variant {
+                Variant::UserMessageChunk {
+                    hash,
+                    index,
+                    count,
+                    data,
+                } => assembler.add(&src, &dst, hash, index, count, data),
+                _ => panic!("unexpected variant"),
+            })
+            .collect();
+
+        assert_eq!(results.pop(), Some(Some(content)));
+        assert!(results.into_iter().all(|result| result.is_none()));
+    }
+
+    #[test]
+    fn tampered_chunk_is_rejected() {
+        let mut rng = rng::new();
+        let content = vec![1; MAX_CHUNK_SIZE + 1];
+        let src = SrcLocation::Node(rng.gen());
+        let dst = DstLocation::Node(rng.gen::<XorName>());
+
+        let mut assembler = ChunkAssembler::default();
+        let mut last = None;
+        for variant in user_message_variants(content).unwrap() {
+            if let Variant::UserMessageChunk {
+                hash,
+                index,
+                count,
+                mut data,
+            } = variant
+            {
+                data[0] = 2;
+                last = assembler.add(&src, &dst, hash, index, count, data);
+            }
+        }
+
+        assert_eq!(last, None);
+    }
+
+    #[test]
+    fn too_large_payload_is_rejected() {
+        let content = vec![0; MAX_PAYLOAD_SIZE + 1];
+        assert!(user_message_variants(content).is_err());
+    }
+
+    #[test]
+    fn source_cannot_evict_payloads_of_others() {
+        let mut rng = rng::new();
+        let victim = SrcLocation::Node(rng.gen());
+        let attacker = SrcLocation::Node(rng.gen());
+        let dst = DstLocation::Node(rng.gen::<XorName>());
+
+        let content = vec![1; MAX_CHUNK_SIZE + 1];
+        let mut chunks = chunks(content.clone());
+        let last = chunks.pop().unwrap();
+
+        let mut assembler = ChunkAssembler::default();
+        for (hash, index, count, data) in chunks {
+            assert_eq!(assembler.add(&victim, &dst, hash, index, count, data), None);
+        }
+
+        // The attacker fills up all its own slots and bytes with maximum size payloads.
+        for fill in 0..=MAX_INCOMPLETE_PAYLOADS_PER_SOURCE {
+            let content = vec![fill as u8; MAX_PAYLOAD_SIZE];
+            for (hash, index, count, data) in chunks(content).into_iter().skip(1) {
+                assert_eq!(
+                    assembler.add(&attacker, &dst, hash, index, count, data),
+                    None
+                );
+            }
+        }
+
+        let (hash, index, count, data) = last;
+        assert_eq!(
+            assembler.add(&victim, &dst, hash, index, count, data),
+            Some(content)
+        );
+    }
+
+    #[test]
+    fn bytes_per_source_are_capped() {
+        let mut rng = rng::new();
+        let src = SrcLocation::Node(rng.gen());
+        let dst = DstLocation::Node(rng.gen::<XorName>());
+
+        let mut assembler = ChunkAssembler::default();
+        for fill in 0..MAX_INCOMPLETE_PAYLOADS_PER_SOURCE {
+            let content = vec![fill as u8; MAX_PAYLOAD_SIZE];
+            for (hash, index, count, data) in chunks(content).into_iter().skip(1) {
+                let _ = assembler.add(&src, &dst, hash, index, count, data);
+            }
+        }
+
+        let source_key = crypto::sha3_256(&bincode::serialize(&src).unwrap());
+        let source = assembler.sources.get(&source_key).unwrap();
+        assert!(source.bytes() <= MAX_INCOMPLETE_BYTES_PER_SOURCE);
+    }
+
+    fn chunks(content: Vec<u8>) -> Vec<(Digest256, u32, u32, Vec<u8>)> {
+        user_message_variants(content)
+            .unwrap()
+            .into_iter()
+            .map(|variant| match variant {
+                Variant::UserMessageChunk {
+                    hash,
+                    index,
+                    count,
+                    data,
+                } => (hash, index, count, data),
+                _ => panic!("unexpected variant"),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Wire envelope of the messages: a header declaring the protocol version and features of the
+//! sender and the codec the body is compressed with, followed by the (possibly compressed)
+//! serialized message:
+//!
+//! `[MARKER: u8][version: u16][features: u32][codec: u8][body]` (integers are big-endian)
+//!
+//! Nodes of protocol version 1 send the bare serialized message, without any envelope and never
+//! compressed. It starts with the tag of the message destination, a little-endian `u32` between 0
+//! and 2, so its first byte is never `MARKER`. Such messages are recognised only to be rejected,
+//! as the serialized message changed since.
+//!
+//! Messages are always created, hashed and verified in their uncompressed form. Compression is
+//! applied only just before sending, so relaying nodes can recompress with their own codec. The
+//! envelope describes only the last hop: a relayed message carries the relaying node's header.
+//!
+//! Senders declaring `FEATURE_ROUTE` append a route trailer to the serialized message, inside the
+//! possibly compressed part:
+//!
+//! `[names: n * 32 bytes][n: u8][hops: u8]`
+//!
+//! It counts the hops the message took and lists the names of the nodes that recorded themselves
+//! on its way. It is not signed and not part of the message hash, so relaying nodes can update it.
+//! Our uncompressed envelopes always carry a trailer, an empty one if the sender didn't provide
+//! any. It is stripped from the messages sent to peers not declaring `FEATURE_ROUTE`, so they can
+//! decode what we send them.
+
+use crate::{
+    network_params::{Compression, NetworkParams},
+    protocol::{Protocol, FEATURE_ROUTE},
+};
+use bytes::Bytes;
+use err_derive::Error;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use xor_name::{XorName, XOR_NAME_LEN};
+
+const MARKER: u8 = 0xff;
+
+// Values of the codec byte.
+const CODEC_NONE: u8 = 0;
+const CODEC_DEFLATE: u8 = 1;
+const CODEC_SNAPPY: u8 = 2;
+
+const HEADER_LEN: usize = 8;
+// Length of the route trailer without the names.
+const TRAILER_LEN: usize = 2;
+
+// Maximum size of a decompressed body. Larger ones are rejected without being fully decompressed,
+// so a small malicious message can't make us allocate arbitrary amounts of memory.
+const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Serializes `value` into an uncompressed envelope.
+pub(crate) fn serialize<T: Serialize>(value: &T) -> Result<Bytes, bincode::Error> {
+    let mut bytes = header(Protocol::OURS.features, CODEC_NONE);
+    bincode::serialize_into(&mut bytes, value)?;
+    append_route(&mut bytes, &Route::default());
+    Ok(bytes.into())
+}
+
+/// Returns the serialized message of an uncompressed envelope of ours, without the route trailer.
+pub(crate) fn body(uncompressed: &[u8]) -> &[u8] {
+    &uncompressed[HEADER_LEN..uncompressed.len() - trailer_len(uncompressed)]
+}
+
+/// Returns the route recorded in an uncompressed envelope of ours. Messages embedded in other
+/// messages have no envelope and so an empty route.
+pub(crate) fn route(uncompressed: &[u8]) -> Route {
+    if uncompressed.len() < HEADER_LEN + TRAILER_LEN {
+        return Route::default();
+    }
+
+    let names_end = uncompressed.len() - TRAILER_LEN;
+    let names = uncompressed[uncompressed.len() - trailer_len(uncompressed)..names_end]
+        .chunks(XOR_NAME_LEN)
+        .map(|chunk| {
+            let mut name = [0; XOR_NAME_LEN];
+            name.copy_from_slice(chunk);
+            XorName(name)
+        })
+        .collect();
+
+    Route {
+        hops: uncompressed[names_end + 1],
+        names,
+    }
+}
+
+/// Replaces the route trailer of an uncompressed envelope of ours.
+pub(crate) fn with_route(uncompressed: &[u8], route: &Route) -> Bytes {
+    let body_end = uncompressed.len() - trailer_len(uncompressed);
+    let mut bytes = Vec::with_capacity(body_end + TRAILER_LEN + route.names.len() * XOR_NAME_LEN);
+    bytes.extend_from_slice(&uncompressed[..body_end]);
+    append_route(&mut bytes, route);
+    bytes.into()
+}
+
+/// Converts an uncompressed envelope of ours into one a peer speaking `protocol` can decode: without
+/// the route trailer if the peer doesn't declare `FEATURE_ROUTE`.
+pub(crate) fn for_peer(uncompressed: Bytes, protocol: &Protocol) -> Bytes {
+    if protocol.features & FEATURE_ROUTE != FEATURE_ROUTE {
+        let mut bytes = header(Protocol::OURS.features & !FEATURE_ROUTE, CODEC_NONE);
+        bytes.extend_from_slice(body(&uncompressed));
+        bytes.into()
+    } else {
+        uncompressed
+    }
+}
+
+/// Compresses an uncompressed envelope according to `params`. Returns it unchanged if compression
+/// is disabled, it is below the threshold or compressing doesn't make it smaller.
+pub(crate) fn compress(bytes: Bytes, params: &NetworkParams) -> Bytes {
+    let codec = match params.compression {
+        Some(codec) => codec,
+        None => return bytes,
+    };
+
+    if bytes.len() < params.compression_threshold {
+        return bytes;
+    }
+
+    let (features, body) = match parse(&bytes) {
+        Ok((protocol, CODEC_NONE, body)) => (protocol.features, body),
+        _ => return bytes,
+    };
+
+    let result = match codec {
+        Compression::Deflate => deflate(body).map(|body| (CODEC_DEFLATE, body)),
+        Compression::Snappy => snap::raw::Encoder::new()
+            .compress_vec(body)
+            .map(|body| (CODEC_SNAPPY, body))
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error)),
+    };
+
+    match result {
+        Ok((codec_byte, compressed_body)) if HEADER_LEN + compressed_body.len() < bytes.len() => {
+            let mut compressed = header(features, codec_byte);
+            compressed.extend_from_slice(&compressed_body);
+            compressed.into()
+        }
+        Ok(_) => bytes,
+        Err(error) => {
+            warn!("Failed to compress message with {:?}: {}", codec, error);
+            bytes
+        }
+    }
+}
+
+/// Opens the envelope received from the wire. Returns the protocol declared by the sender and the
+/// message in an uncompressed envelope of ours.
+pub(crate) fn decompress(bytes: &Bytes) -> Result<(Protocol, Bytes), EnvelopeError> {
+    let (protocol, codec, body) = parse(bytes)?;
+
+    if codec == CODEC_NONE && protocol == Protocol::OURS && bytes[0] == MARKER {
+        check_trailer(body)?;
+        return Ok((protocol, bytes.clone()));
+    }
+
+    let body = match codec {
+        CODEC_NONE => body.to_vec(),
+        CODEC_DEFLATE => inflate(body)?,
+        CODEC_SNAPPY => {
+            if snap::raw::decompress_len(body)? > MAX_DECOMPRESSED_SIZE {
+                return Err(EnvelopeError::TooLarge);
+            }
+            snap::raw::Decoder::new().decompress_vec(body)?
+        }
+        _ => return Err(EnvelopeError::UnknownCodec(codec)),
+    };
+
+    let mut uncompressed = header(Protocol::OURS.features, CODEC_NONE);
+    uncompressed.extend_from_slice(&body);
+
+    if protocol.features & FEATURE_ROUTE == FEATURE_ROUTE {
+        check_trailer(&body)?;
+    } else {
+        append_route(&mut uncompressed, &Route::default());
+    }
+
+    Ok((protocol, uncompressed.into()))
+}
+
+/// Returns the size of the given envelope of ours once decompressed.
+pub(crate) fn uncompressed_len(bytes: &Bytes) -> usize {
+    decompress(bytes)
+        .map(|(_, uncompressed)| uncompressed.len())
+        .unwrap_or_else(|_| bytes.len())
+}
+
+/// Number of hops a message took so far and the names of the nodes that recorded themselves on
+/// its way, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Route {
+    pub hops: u8,
+    pub names: Vec<XorName>,
+}
+
+fn append_route(bytes: &mut Vec<u8>, route: &Route) {
+    for name in &route.names {
+        bytes.extend_from_slice(&name.0);
+    }
+    bytes.push(route.names.len() as u8);
+    bytes.push(route.hops);
+}
+
+// Length of the route trailer at the end of `bytes`, which must have been checked already.
+fn trailer_len(bytes: &[u8]) -> usize {
+    TRAILER_LEN + usize::from(bytes[bytes.len() - TRAILER_LEN]) * XOR_NAME_LEN
+}
+
+fn check_trailer(body: &[u8]) -> Result<(), EnvelopeError> {
+    if body.len() < TRAILER_LEN || body.len() < trailer_len(body) {
+        Err(EnvelopeError::Truncated)
+    } else {
+        Ok(())
+    }
+}
+
+fn header(features: u32, codec: u8) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.push(MARKER);
+    bytes.extend_from_slice(&Protocol::OURS.version.to_be_bytes());
+    bytes.extend_from_slice(&features.to_be_bytes());
+    bytes.push(codec);
+    bytes
+}
+
+// Returns the protocol, the codec and the body of the envelope.
+fn parse(bytes: &[u8]) -> Result<(Protocol, u8, &[u8]), EnvelopeError> {
+    match bytes.first() {
+        None => Err(EnvelopeError::Truncated),
+        Some(&MARKER) => {
+            if bytes.len() < HEADER_LEN {
+                return Err(EnvelopeError::Truncated);
+            }
+
+            let version = u16::from_be_bytes([bytes[1], bytes[2]]);
+            let features = u32::from_be_bytes([bytes[3], bytes[4], bytes[5], bytes[6]]);
+            let protocol = Protocol { version, features };
+
+            Ok((protocol, bytes[7], &bytes[HEADER_LEN..]))
+        }
+        // Bare message of a protocol version 1 node.
+        Some(_) => Ok((Protocol::LEGACY, CODEC_NONE, bytes)),
+    }
+}
+
+fn deflate(input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(input)?;
+    encoder.finish()
+}
+
+fn inflate(input: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    let mut output = Vec::new();
+    let _ = flate2::read::DeflateDecoder::new(input)
+        .take(MAX_DECOMPRESSED_SIZE as u64 + 1)
+        .read_to_end(&mut output)?;
+
+    if output.len() > MAX_DECOMPRESSED_SIZE {
+        Err(EnvelopeError::TooLarge)
+    } else {
+        Ok(output)
+    }
+}
+
+/// Number of bytes of the messages sent by a node, before and after compression. Messages resent
+/// after failing to reach their target are counted again.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CompressionStats {
+    /// Bytes that would have been sent without compression.
+    pub uncompressed: u64,
+    /// Bytes actually sent.
+    pub sent: u64,
+}
+
+/// Error returned when a received envelope can't be opened.
+#[derive(Debug, Error)]
+pub enum EnvelopeError {
+    #[error(display = "truncated envelope")]
+    Truncated,
+    #[error(display = "unknown compression codec: {}", _0)]
+    UnknownCodec(u8),
+    #[error(display = "decompressed message too large")]
+    TooLarge,
+    #[error(display = "deflate error: {}", _0)]
+    Deflate(#[error(source)] io::Error),
+    #[error(display = "snappy error: {}", _0)]
+    Snappy(#[error(source)] snap::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{location::DstLocation, rng};
+    use rand::Rng;
+
+    #[test]
+    fn roundtrip() {
+        let mut rng = rng::new();
+        // Compressible but not trivially so.
+        let value: Vec<u8> = (0..4096).map(|_| rng.gen_range(0, 4)).collect();
+        let uncompressed = serialize(&value).unwrap();
+
+        for codec in &[Compression::Deflate, Compression::Snappy] {
+            let params = NetworkParams {
+                compression: Some(*codec),
+                ..Default::default()
+            };
+            let compressed = compress(uncompressed.clone(), &params);
+            assert!(compressed.len() < uncompressed.len());
+            assert_eq!(
+                decompress(&compressed).unwrap(),
+                (Protocol::OURS, uncompressed.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn small_message_is_not_compressed() {
+        let uncompressed = serialize(&vec![0u8; 16]).unwrap();
+        let params = NetworkParams {
+            compression: Some(Compression::Deflate),
+            ..Default::default()
+        };
+        assert_eq!(compress(uncompressed.clone(), &params), uncompressed);
+    }
+
+    #[test]
+    fn legacy_message() {
+        // Version 1 messages start with their bincode-serialized destination.
+        let mut rng = rng::new();
+        for dst in &[
+            DstLocation::Node(rng.gen()),
+            DstLocation::Section(rng.gen()),
+            DstLocation::Direct,
+        ] {
+            let mut legacy = bincode::serialize(dst).unwrap();
+            legacy.extend_from_slice(&[1, 2, 3]);
+
+            let (protocol, uncompressed) = decompress(&legacy.clone().into()).unwrap();
+            assert_eq!(protocol, Protocol::LEGACY);
+            assert_eq!(body(&uncompressed), &legacy[..]);
+            assert_eq!(route(&uncompressed), Route::default());
+        }
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut bytes = serialize(&vec![1u8, 2, 3]).unwrap().to_vec();
+        bytes[1..3].copy_from_slice(&(Protocol::OURS.version + 1).to_be_bytes());
+
+        let (protocol, _) = decompress(&bytes.into()).unwrap();
+        assert_eq!(protocol.version, Protocol::OURS.version + 1);
+    }
+
+    #[test]
+    fn route_is_not_part_of_body() {
+        let value = vec![1u8, 2, 3];
+        let uncompressed = serialize(&value).unwrap();
+        assert_eq!(route(&uncompressed), Route::default());
+
+        let mut rng = rng::new();
+        let new_route = Route {
+            hops: 2,
+            names: vec![rng.gen(), rng.gen()],
+        };
+        let relayed = with_route(&uncompressed, &new_route);
+        assert_eq!(body(&relayed), body(&uncompressed));
+
+        let params = NetworkParams {
+            compression: Some(Compression::Deflate),
+            compression_threshold: 0,
+            ..Default::default()
+        };
+        let (_, received) = decompress(&compress(relayed, &params)).unwrap();
+        assert_eq!(route(&received), new_route);
+        assert_eq!(
+            bincode::deserialize::<Vec<u8>>(body(&received)).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn route_is_stripped_for_peers_not_supporting_it() {
+        let value = vec![1u8, 2, 3];
+        let mut rng = rng::new();
+        let route_recorded = Route {
+            hops: 1,
+            names: vec![rng.gen()],
+        };
+        let uncompressed = with_route(&serialize(&value).unwrap(), &route_recorded);
+        assert_eq!(
+            for_peer(uncompressed.clone(), &Protocol::OURS),
+            uncompressed
+        );
+
+        let params = NetworkParams {
+            compression: Some(Compression::Deflate),
+            compression_threshold: 0,
+            ..Default::default()
+        };
+        let no_route = Protocol {
+            features: Protocol::OURS.features & !FEATURE_ROUTE,
+            ..Protocol::OURS
+        };
+        let stripped = for_peer(uncompressed.clone(), &no_route);
+        assert_eq!(&stripped[HEADER_LEN..], body(&uncompressed));
+
+        for bytes in &[stripped.clone(), compress(stripped, &params)] {
+            let (protocol, received) = decompress(bytes).unwrap();
+            assert_eq!(protocol, no_route);
+            assert_eq!(body(&received), body(&uncompressed));
+            assert_eq!(route(&received), Route::default());
+        }
+    }
+
+    #[test]
+    fn unknown_codec_is_rejected() {
+        let mut bytes = header(Protocol::OURS.features, 7);
+        bytes.extend_from_slice(&[1, 2, 3]);
+        match decompress(&bytes.into()) {
+            Err(EnvelopeError::UnknownCodec(7)) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
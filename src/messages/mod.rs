@@ -7,15 +7,21 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod accumulating_message;
+mod chunking;
+mod envelope;
 mod hash;
 mod message_accumulator;
+mod ordering;
 mod src_authority;
 mod variant;
 
 pub use self::{
     accumulating_message::{AccumulatingMessage, PlainMessage},
+    chunking::{user_message_variants, ChunkAssembler, MAX_CHUNK_SIZE, MAX_PAYLOAD_SIZE},
+    envelope::{compress, for_peer, uncompressed_len, CompressionStats, EnvelopeError, Route},
     hash::MessageHash,
     message_accumulator::MessageAccumulator,
+    ordering::{OrderedInbox, OrderedOutbox},
     src_authority::SrcAuthority,
     variant::{BootstrapResponse, JoinRequest, Variant},
 };
@@ -23,7 +29,9 @@ use crate::{
     error::{Result, RoutingError},
     id::FullId,
     location::DstLocation,
-    section::SectionProofChain,
+    network_params::NetworkParams,
+    protocol::{Protocol, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION},
+    section::{CompactProofChain, SectionProofChain, SkipLinks},
 };
 
 use bytes::Bytes;
@@ -33,7 +41,7 @@ use std::{
     fmt::{self, Debug, Formatter},
     net::SocketAddr,
 };
-use xor_name::Prefix;
+use xor_name::{Prefix, XorName};
 
 /// Message sent over the network.
 #[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -57,12 +65,35 @@ pub struct Message {
     serialized: Bytes,
     #[serde(skip)]
     hash: MessageHash,
+    /// Protocol declared by the node we received the message from. Ours for messages we created.
+    #[serde(skip)]
+    protocol: Protocol,
 }
 
 impl Message {
     /// Deserialize the message. Only called on message receipt.
     pub(crate) fn from_bytes(bytes: &Bytes) -> Result<Self, CreateError> {
-        let mut msg: Message = bincode::deserialize(&bytes[..])?;
+        let (protocol, bytes) = envelope::decompress(bytes)?;
+        // Messages of older versions may decode into the wrong variant, so don't even try.
+        if protocol.version < MIN_PROTOCOL_VERSION {
+            return Err(CreateError::Unsupported {
+                hash: MessageHash::from_bytes(envelope::body(&bytes)),
+                protocol,
+            });
+        }
+
+        let mut msg: Message = match bincode::deserialize(envelope::body(&bytes)) {
+            Ok(msg) => msg,
+            // The message is likely of a variant or format introduced after our protocol version.
+            Err(_) if protocol.version > PROTOCOL_VERSION => {
+                return Err(CreateError::Unsupported {
+                    hash: MessageHash::from_bytes(envelope::body(&bytes)),
+                    protocol,
+                })
+            }
+            Err(error) => return Err(error.into()),
+        };
+        msg.protocol = protocol;
 
         let signed_bytes = bincode::serialize(&SignableView {
             dst: &msg.dst,
@@ -76,22 +107,23 @@ impl Message {
                 signature,
             } => {
                 if public_id.verify(&signed_bytes, &signature) {
-                    msg.serialized = bytes.clone();
-                    msg.hash = MessageHash::from_bytes(bytes);
+                    msg.hash = MessageHash::from_bytes(envelope::body(&bytes));
+                    msg.serialized = bytes;
                     Ok(msg)
                 } else {
                     Err(CreateError::FailedSignature)
                 }
             }
-            SrcAuthority::Section {
-                signature,
-                proof_chain,
-                ..
-            } => {
+            SrcAuthority::Section { signature, .. }
+            | SrcAuthority::CompactSection { signature, .. } => {
+                let last_key = msg
+                    .src
+                    .as_section_key()
+                    .map_err(|_| CreateError::FailedSignature)?;
                 // FIXME Assumes the nodes proof last key is the one signing this message
-                if proof_chain.last_key().verify(&signature, &signed_bytes) {
-                    msg.serialized = bytes.clone();
-                    msg.hash = MessageHash::from_bytes(bytes);
+                if last_key.verify(&signature, &signed_bytes) {
+                    msg.hash = MessageHash::from_bytes(envelope::body(&bytes));
+                    msg.serialized = bytes;
                     Ok(msg)
                 } else {
                     Err(CreateError::FailedSignature)
@@ -100,11 +132,36 @@ impl Message {
         }
     }
 
-    /// send across wire
+    /// send across wire. The bytes are uncompressed; use `compress` to compress them.
     pub(crate) fn to_bytes(&self) -> Bytes {
         self.serialized.clone()
     }
 
+    /// Hops the message took so far and the nodes recorded on its way.
+    pub(crate) fn route(&self) -> Route {
+        envelope::route(&self.serialized)
+    }
+
+    /// Bytes to relay the message with: its route extended by one hop and, if `params` ask for it,
+    /// by our name. Returns `None` if the message already took `params.max_hops` hops.
+    pub(crate) fn to_relay_bytes(
+        &self,
+        our_name: &XorName,
+        params: &NetworkParams,
+    ) -> Option<Bytes> {
+        let mut route = self.route();
+        if route.hops >= params.max_hops {
+            return None;
+        }
+
+        route.hops += 1;
+        if params.record_route && route.names.len() < usize::from(u8::MAX) {
+            route.names.push(*our_name);
+        }
+
+        Some(envelope::with_route(&self.serialized, &route))
+    }
+
     /// Creates a signed message where signature is assumed valid.
     fn new_signed(
         src: SrcAuthority,
@@ -119,10 +176,11 @@ impl Message {
             dst_key,
             serialized: Default::default(),
             hash: Default::default(),
+            protocol: Protocol::OURS,
         };
 
-        msg.serialized = bincode::serialize(&msg)?.into();
-        msg.hash = MessageHash::from_bytes(&msg.serialized);
+        msg.serialized = envelope::serialize(&msg)?;
+        msg.hash = MessageHash::from_bytes(envelope::body(&msg.serialized));
 
         Ok(msg)
     }
@@ -199,20 +257,32 @@ impl Message {
         &self.hash
     }
 
+    /// Protocol of the node we received the message from.
+    pub fn protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
     // Extend the current message proof so it starts at `new_first_key` while keeping the last key
-    // (and therefore the signature) intact.
+    // (and therefore the signature) intact. A compact proof stays compact, using the given skip
+    // links.
     #[cfg_attr(feature = "mock_base", allow(clippy::trivially_copy_pass_by_ref))]
     pub(crate) fn extend_proof_chain(
         mut self,
         new_first_key: &bls::PublicKey,
         section_proof_chain: &SectionProofChain,
+        skip_links: &SkipLinks,
     ) -> Result<Self, ExtendProofChainError> {
-        let proof_chain = match &mut self.src {
-            SrcAuthority::Section { proof_chain, .. } => proof_chain,
+        let (has_first_key, last_key) = match &self.src {
+            SrcAuthority::Section { proof_chain, .. } => {
+                (proof_chain.has_key(new_first_key), proof_chain.last_key())
+            }
+            SrcAuthority::CompactSection { proof_chain, .. } => {
+                (proof_chain.has_key(new_first_key), proof_chain.last_key())
+            }
             SrcAuthority::Node { .. } => return Err(ExtendProofChainError::MustBeSection),
         };
 
-        if proof_chain.has_key(new_first_key) {
+        if has_first_key {
             return Err(ExtendProofChainError::AlreadySufficient);
         }
 
@@ -222,13 +292,22 @@ impl Message {
             return Err(ExtendProofChainError::InvalidFirstKey);
         };
 
-        let index_to = if let Some(index) = section_proof_chain.index_of(proof_chain.last_key()) {
+        let index_to = if let Some(index) = section_proof_chain.index_of(last_key) {
             index
         } else {
             return Err(ExtendProofChainError::InvalidLastKey);
         };
 
-        *proof_chain = section_proof_chain.slice(index_from..=index_to);
+        let new_proof_chain = section_proof_chain.slice(..=index_to);
+        match &mut self.src {
+            SrcAuthority::Section { proof_chain, .. } => {
+                *proof_chain = new_proof_chain.slice(index_from..)
+            }
+            SrcAuthority::CompactSection { proof_chain, .. } => {
+                *proof_chain = CompactProofChain::new(&new_proof_chain, index_from, skip_links)
+            }
+            SrcAuthority::Node { .. } => unreachable!(),
+        }
 
         Ok(Self::new_signed(
             self.src,
@@ -237,16 +316,39 @@ impl Message {
             self.variant,
         )?)
     }
+
+    // Replaces the full proof chain of this section message with its compact form. `proof_chain`
+    // must be the compact form of the current one.
+    pub(crate) fn into_compact(self, proof_chain: CompactProofChain) -> Result<Self, CreateError> {
+        let src = match self.src {
+            SrcAuthority::Section {
+                prefix, signature, ..
+            } => SrcAuthority::CompactSection {
+                prefix,
+                signature,
+                proof_chain,
+            },
+            src => src,
+        };
+
+        Self::new_signed(src, self.dst, self.dst_key, self.variant)
+    }
 }
 
 impl Debug for Message {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
-        formatter
-            .debug_struct("Message")
+        let mut debug = formatter.debug_struct("Message");
+        let _ = debug
             .field("src", &self.src.src_location())
             .field("dst", &self.dst)
-            .field("variant", &self.variant)
-            .finish()
+            .field("variant", &self.variant);
+
+        let route = self.route();
+        if !route.names.is_empty() {
+            let _ = debug.field("route", &route.names);
+        }
+
+        debug.finish()
     }
 }
 
@@ -307,6 +409,13 @@ pub enum CreateError {
     Bincode(#[error(source)] bincode::Error),
     #[error(display = "signature check failed")]
     FailedSignature,
+    #[error(display = "envelope error: {}", _0)]
+    Envelope(#[error(source)] EnvelopeError),
+    #[error(display = "unsupported message {:?} of protocol {}", hash, protocol)]
+    Unsupported {
+        hash: MessageHash,
+        protocol: Protocol,
+    },
 }
 
 impl From<CreateError> for RoutingError {
@@ -314,6 +423,8 @@ impl From<CreateError> for RoutingError {
         match src {
             CreateError::Bincode(inner) => Self::Bincode(inner),
             CreateError::FailedSignature => Self::FailedSignature,
+            CreateError::Envelope(_) => Self::InvalidMessage,
+            CreateError::Unsupported { .. } => Self::ProtocolMismatch,
         }
     }
 }
@@ -341,3 +452,115 @@ pub(crate) struct SignableView<'a> {
     dst_key: Option<&'a bls::PublicKey>,
     variant: &'a Variant,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::{
+            test_utils::{gen_secret_key, proven},
+            GenesisPrefixInfo,
+        },
+        id::P2pNode,
+        relocation::RelocatePayload,
+        rng,
+        section::EldersInfo,
+    };
+    use rand::Rng;
+    use serde::Serialize;
+    use std::iter;
+
+    // Message as serialized by the nodes of protocol version 1, which send it bare.
+    #[derive(Serialize)]
+    struct LegacyMessage {
+        dst: DstLocation,
+        src: SrcAuthority,
+        variant: LegacyVariant,
+        dst_key: Option<bls::PublicKey>,
+    }
+
+    // Variants of protocol version 1. Only the variants used here keep their fields, the others
+    // just hold their position, which determines the serialized tag.
+    #[derive(Serialize)]
+    enum LegacyVariant {
+        _NeighbourInfo,
+        _UserMessage,
+        NodeApproval(GenesisPrefixInfo),
+        _GenesisUpdate,
+        _Relocate,
+        _MessageSignature,
+        BootstrapRequest(XorName),
+        _BootstrapResponse,
+        JoinRequest(Box<LegacyJoinRequest>),
+    }
+
+    #[derive(Serialize)]
+    struct LegacyJoinRequest {
+        section_key: bls::PublicKey,
+        relocate_payload: Option<RelocatePayload>,
+    }
+
+    #[derive(Serialize)]
+    struct LegacySignableView<'a> {
+        dst: &'a DstLocation,
+        dst_key: Option<&'a bls::PublicKey>,
+        variant: &'a LegacyVariant,
+    }
+
+    fn serialize_legacy(full_id: &FullId, dst: DstLocation, variant: LegacyVariant) -> Bytes {
+        let signable = bincode::serialize(&LegacySignableView {
+            dst: &dst,
+            dst_key: None,
+            variant: &variant,
+        })
+        .unwrap();
+        let msg = LegacyMessage {
+            dst,
+            src: SrcAuthority::Node {
+                public_id: *full_id.public_id(),
+                signature: full_id.sign(&signable),
+            },
+            variant,
+            dst_key: None,
+        };
+
+        bincode::serialize(&msg).unwrap().into()
+    }
+
+    #[test]
+    fn legacy_messages_are_rejected() {
+        let mut rng = rng::new();
+        let full_id = FullId::gen(&mut rng);
+        let secret_key = gen_secret_key(&mut rng);
+
+        let p2p_node = P2pNode::new(*full_id.public_id(), ([127, 0, 0, 1], 12000).into());
+        let elders_info = EldersInfo::new(
+            iter::once((*p2p_node.name(), p2p_node)).collect(),
+            Prefix::default(),
+        );
+        let node_approval = LegacyVariant::NodeApproval(GenesisPrefixInfo {
+            elders_info: proven(&secret_key, elders_info),
+            parsec_version: 0,
+        });
+        let bootstrap_request = LegacyVariant::BootstrapRequest(rng.gen());
+        let join_request = LegacyVariant::JoinRequest(Box::new(LegacyJoinRequest {
+            section_key: secret_key.public_key(),
+            relocate_payload: None,
+        }));
+
+        for (dst, variant) in vec![
+            (DstLocation::Node(rng.gen()), node_approval),
+            (DstLocation::Direct, bootstrap_request),
+            (DstLocation::Section(rng.gen()), join_request),
+        ] {
+            let bytes = serialize_legacy(&full_id, dst, variant);
+            match Message::from_bytes(&bytes) {
+                Err(CreateError::Unsupported { hash, protocol }) => {
+                    assert_eq!(protocol, Protocol::LEGACY);
+                    assert_eq!(hash, MessageHash::from_bytes(&bytes));
+                }
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+    }
+}
@@ -0,0 +1,547 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Ordered channels of user messages from a node to a destination node or section.
+//!
+//! The position of a message in its channel travels in the `OrderedUserMessage` variant rather
+//! than in a message header: it only concerns these messages, and as part of the signed content
+//! it can't be altered by the nodes relaying the message.
+
+use super::MAX_CHUNK_SIZE;
+use crate::{
+    location::DstLocation,
+    rng::MainRng,
+    time::{Duration, Instant},
+};
+use rand::Rng;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use xor_name::XorName;
+
+// Number of the latest messages of each outgoing channel kept to be resent on request.
+const RESEND_BUFFER_SIZE: usize = 256;
+
+// How long an outgoing channel can stay idle before we close it. The next message to the same
+// destination opens a new channel, with a greater epoch.
+const CHANNEL_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+// How long we keep the state of an idle incoming channel. Twice `CHANNEL_EXPIRY`, so the source
+// always closes the channel first and we never take a later message of it for the first one of a
+// new channel.
+const INCOMING_CHANNEL_EXPIRY: Duration = Duration::from_secs(20 * 60);
+
+// Maximum number of sources whose incoming channels are kept at the same time. When exceeded, the
+// least recently active source is dropped.
+const MAX_SOURCES: usize = 64;
+
+// Maximum number of incoming channels kept per source. When exceeded, the least recently active
+// channel of that source is dropped, so no source can evict the channels of the others.
+const MAX_CHANNELS_PER_SOURCE: usize = 4;
+
+// Maximum number of out of order messages buffered per incoming channel. Further ones are dropped
+// and have to be requested again once the gap before them is filled.
+const MAX_BUFFERED: usize = 1024;
+
+// Maximum total size of the out of order messages buffered per source. Further ones are dropped
+// like when exceeding `MAX_BUFFERED`. Bounds the buffered messages of all the sources together to
+// `MAX_SOURCES * MAX_BUFFERED_BYTES_PER_SOURCE`.
+const MAX_BUFFERED_BYTES_PER_SOURCE: usize = 2 * MAX_CHUNK_SIZE;
+
+// Number of times we ask for the missing messages of an incoming channel before giving up on them
+// and delivering the messages after them.
+const MAX_RESEND_REQUESTS: u32 = 3;
+
+/// Ordered channels from our node, one per destination. Assigns the sequence numbers and keeps the
+/// latest messages of each channel to resend them to the destinations that missed them.
+#[derive(Default)]
+pub struct OrderedOutbox {
+    channels: HashMap<DstLocation, OutChannel>,
+    // Epoch of the channel opened last.
+    last_epoch: u64,
+}
+
+impl OrderedOutbox {
+    /// Adds `content` to the channel to `dst`, opening a new channel if there is none or it was
+    /// idle for too long. Returns the epoch of the channel and the sequence number of the message.
+    pub fn push(&mut self, dst: DstLocation, content: Vec<u8>, rng: &mut MainRng) -> (u64, u64) {
+        let now = Instant::now();
+        self.channels
+            .retain(|_, channel| channel.last_sent.elapsed() < CHANNEL_EXPIRY);
+
+        if !self.channels.contains_key(&dst) {
+            let epoch = self.next_epoch(rng);
+            let _ = self.channels.insert(dst, OutChannel::new(epoch, now));
+        }
+
+        let channel = self
+            .channels
+            .get_mut(&dst)
+            .expect("ordered channel just inserted");
+        let seq = channel.next_seq;
+        channel.next_seq += 1;
+        channel.last_sent = now;
+
+        channel.sent.push_back((seq, content));
+        if channel.sent.len() > RESEND_BUFFER_SIZE {
+            let _ = channel.sent.pop_front();
+        }
+
+        (channel.epoch, seq)
+    }
+
+    /// Returns the messages of the channel to `dst` with the given epoch and sequence numbers in
+    /// `from..to` that are still kept.
+    pub fn get(
+        &self,
+        dst: &DstLocation,
+        epoch: u64,
+        from: u64,
+        to: u64,
+    ) -> impl Iterator<Item = (u64, &Vec<u8>)> {
+        self.channels
+            .get(dst)
+            .filter(|channel| channel.epoch == epoch)
+            .into_iter()
+            .flat_map(|channel| channel.sent.iter())
+            .filter(move |(seq, _)| *seq >= from && *seq < to)
+            .map(|(seq, content)| (*seq, content))
+    }
+
+    // Returns the epoch for a new channel: the current time in milliseconds in the high bits, so
+    // the channels we open later, even after a restart, have greater epochs, and random low bits,
+    // so the channels opened by different runs of our node in the same millisecond still differ.
+    fn next_epoch(&mut self, rng: &mut MainRng) -> u64 {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+        let epoch = (millis << 22 | u64::from(rng.gen::<u32>() >> 10))
+            .max(self.last_epoch.saturating_add(1));
+        self.last_epoch = epoch;
+        epoch
+    }
+}
+
+struct OutChannel {
+    epoch: u64,
+    next_seq: u64,
+    sent: VecDeque<(u64, Vec<u8>)>,
+    last_sent: Instant,
+}
+
+impl OutChannel {
+    fn new(epoch: u64, now: Instant) -> Self {
+        Self {
+            epoch,
+            next_seq: 0,
+            sent: VecDeque::new(),
+            last_sent: now,
+        }
+    }
+}
+
+/// Ordered channels to our node or section. Buffers the messages received out of order until the
+/// ones before them arrive.
+///
+/// The number of channels and the size of the buffered messages are limited. A channel dropped to
+/// make room for others starts over from the first message, so the messages still kept by the
+/// source might be requested and delivered again.
+#[derive(Default)]
+pub struct OrderedInbox {
+    // Incoming channels by the name of their source node.
+    sources: HashMap<XorName, Source>,
+}
+
+impl OrderedInbox {
+    /// Adds the message with the given sequence number of the channel from `src` to `dst`.
+    /// Returns the messages of the channel that can now be delivered, in order.
+    pub fn add(
+        &mut self,
+        src: XorName,
+        dst: DstLocation,
+        epoch: u64,
+        seq: u64,
+        content: Vec<u8>,
+    ) -> Vec<Vec<u8>> {
+        let now = Instant::now();
+
+        if !self.sources.contains_key(&src) && self.sources.len() >= MAX_SOURCES {
+            self.remove_expired();
+
+            if self.sources.len() >= MAX_SOURCES {
+                if let Some(oldest) = self
+                    .sources
+                    .iter()
+                    .min_by_key(|(_, source)| source.last_active())
+                    .map(|(name, _)| *name)
+                {
+                    let _ = self.sources.remove(&oldest);
+                }
+            }
+        }
+
+        self.sources
+            .entry(src)
+            .or_default()
+            .add(dst, epoch, seq, content, now)
+    }
+
+    /// Checks the channels waiting for missing messages and drops the idle ones. Called
+    /// periodically, so a gap found by two consecutive calls is requested to be filled. Returns the
+    /// gaps to request and, for the gaps requested too many times already, the messages after them
+    /// which are delivered without waiting any longer.
+    pub fn check_gaps(&mut self) -> (Vec<Gap>, Vec<(XorName, DstLocation, Vec<u8>)>) {
+        self.remove_expired();
+
+        let mut gaps = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (&src, source) in &mut self.sources {
+            for (dst, channel) in &mut source.channels {
+                let dst = *dst;
+                let first_buffered = if let Some(seq) = channel.buffered.keys().next() {
+                    *seq
+                } else {
+                    continue;
+                };
+
+                channel.stalled_checks += 1;
+
+                // The gap is new - the missing messages might still be on their way.
+                if channel.stalled_checks == 1 {
+                    continue;
+                }
+
+                let attempt = channel.stalled_checks - 1;
+                if attempt <= MAX_RESEND_REQUESTS {
+                    gaps.push(Gap {
+                        src,
+                        dst,
+                        epoch: channel.epoch,
+                        from: channel.next_seq,
+                        to: first_buffered,
+                        attempt,
+                    });
+                } else {
+                    channel.next_seq = first_buffered;
+                    skipped.extend(
+                        channel
+                            .pop_ready()
+                            .into_iter()
+                            .map(|content| (src, dst, content)),
+                    );
+                }
+            }
+        }
+
+        (gaps, skipped)
+    }
+
+    fn remove_expired(&mut self) {
+        for source in self.sources.values_mut() {
+            source
+                .channels
+                .retain(|(_, channel)| channel.last_active.elapsed() < INCOMING_CHANNEL_EXPIRY);
+        }
+
+        self.sources.retain(|_, source| !source.channels.is_empty());
+    }
+}
+
+/// Messages missing from an incoming channel.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Gap {
+    pub src: XorName,
+    pub dst: DstLocation,
+    pub epoch: u64,
+    pub from: u64,
+    pub to: u64,
+    // Number of times the gap was requested, including this one.
+    pub attempt: u32,
+}
+
+// Incoming channels of a single source, least recently active first.
+#[derive(Default)]
+struct Source {
+    channels: Vec<(DstLocation, InChannel)>,
+}
+
+impl Source {
+    fn add(
+        &mut self,
+        dst: DstLocation,
+        epoch: u64,
+        seq: u64,
+        content: Vec<u8>,
+        now: Instant,
+    ) -> Vec<Vec<u8>> {
+        let mut channel = if let Some(index) = self.channels.iter().position(|(key, _)| *key == dst)
+        {
+            self.channels.remove(index).1
+        } else {
+            if self.channels.len() >= MAX_CHANNELS_PER_SOURCE {
+                let _ = self.channels.remove(0);
+            }
+            InChannel::new(epoch, now)
+        };
+
+        let ready = if epoch < channel.epoch {
+            // Message of a channel the source closed since.
+            Vec::new()
+        } else {
+            if epoch > channel.epoch {
+                // The source opened a new channel.
+                channel = InChannel::new(epoch, now);
+            }
+
+            let other_bytes: usize = self.channels.iter().map(|(_, other)| other.bytes).sum();
+            channel.add(seq, content, other_bytes, now)
+        };
+
+        self.channels.push((dst, channel));
+        ready
+    }
+
+    fn last_active(&self) -> Option<Instant> {
+        self.channels
+            .iter()
+            .map(|(_, channel)| channel.last_active)
+            .max()
+    }
+}
+
+struct InChannel {
+    epoch: u64,
+    next_seq: u64,
+    buffered: BTreeMap<u64, Vec<u8>>,
+    // Total size of the buffered messages.
+    bytes: usize,
+    // Number of consecutive gap checks that found the channel stuck at `next_seq`.
+    stalled_checks: u32,
+    last_active: Instant,
+}
+
+impl InChannel {
+    fn new(epoch: u64, now: Instant) -> Self {
+        Self {
+            epoch,
+            next_seq: 0,
+            buffered: BTreeMap::new(),
+            bytes: 0,
+            stalled_checks: 0,
+            last_active: now,
+        }
+    }
+
+    // Adds the message, unless it's a duplicate or buffering it would take more than the limits
+    // allow, given the other channels of the source buffer `other_bytes`. Returns the messages
+    // that can now be delivered.
+    fn add(
+        &mut self,
+        seq: u64,
+        content: Vec<u8>,
+        other_bytes: usize,
+        now: Instant,
+    ) -> Vec<Vec<u8>> {
+        self.last_active = now;
+
+        if seq < self.next_seq || self.buffered.contains_key(&seq) {
+            return Vec::new();
+        }
+
+        if seq > self.next_seq
+            && (self.buffered.len() >= MAX_BUFFERED
+                || other_bytes + self.bytes + content.len() > MAX_BUFFERED_BYTES_PER_SOURCE)
+        {
+            return Vec::new();
+        }
+
+        self.bytes += content.len();
+        let _ = self.buffered.insert(seq, content);
+        self.pop_ready()
+    }
+
+    fn pop_ready(&mut self) -> Vec<Vec<u8>> {
+        let mut ready = Vec::new();
+        while let Some(content) = self.buffered.remove(&self.next_seq) {
+            self.bytes -= content.len();
+            ready.push(content);
+            self.next_seq += 1;
+        }
+
+        if !ready.is_empty() {
+            self.stalled_checks = 0;
+        }
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    #[test]
+    fn out_of_order_messages_are_delivered_in_order() {
+        let mut rng = rng::new();
+        let src = rng.gen();
+        let dst = DstLocation::Section(rng.gen());
+        let mut inbox = OrderedInbox::default();
+
+        assert!(inbox.add(src, dst, 1, 1, vec![1]).is_empty());
+        assert!(inbox.add(src, dst, 1, 2, vec![2]).is_empty());
+        assert_eq!(
+            inbox.add(src, dst, 1, 0, vec![0]),
+            vec![vec![0], vec![1], vec![2]]
+        );
+
+        // Duplicates are dropped.
+        assert!(inbox.add(src, dst, 1, 1, vec![1]).is_empty());
+
+        // A greater epoch restarts the channel and a lesser one is dropped.
+        assert_eq!(inbox.add(src, dst, 2, 0, vec![0]), vec![vec![0]]);
+        assert!(inbox.add(src, dst, 1, 3, vec![3]).is_empty());
+        assert_eq!(inbox.add(src, dst, 2, 1, vec![1]), vec![vec![1]]);
+    }
+
+    #[test]
+    fn persistent_gap_is_requested_then_skipped() {
+        let mut rng = rng::new();
+        let src = rng.gen();
+        let dst = DstLocation::Node(rng.gen());
+        let mut inbox = OrderedInbox::default();
+
+        assert!(inbox.add(src, dst, 1, 2, vec![2]).is_empty());
+
+        // Not requested the first time, as the missing messages might be still on their way.
+        assert_eq!(inbox.check_gaps(), (vec![], vec![]));
+
+        for attempt in 1..=MAX_RESEND_REQUESTS {
+            let expected_gap = Gap {
+                src,
+                dst,
+                epoch: 1,
+                from: 0,
+                to: 2,
+                attempt,
+            };
+            assert_eq!(inbox.check_gaps(), (vec![expected_gap], vec![]));
+        }
+
+        assert_eq!(inbox.check_gaps(), (vec![], vec![(src, dst, vec![2])]));
+        assert_eq!(inbox.add(src, dst, 1, 3, vec![3]), vec![vec![3]]);
+    }
+
+    #[test]
+    fn buffered_bytes_are_limited_per_source() {
+        let mut rng = rng::new();
+        let src = rng.gen();
+        let other_src = rng.gen();
+        let dsts: Vec<_> = (0..2).map(|_| DstLocation::Section(rng.gen())).collect();
+        let mut inbox = OrderedInbox::default();
+
+        // Fill the buffer of the source through both of its channels.
+        let content = vec![0; MAX_CHUNK_SIZE];
+        for &dst in &dsts {
+            assert!(inbox.add(src, dst, 1, 1, content.clone()).is_empty());
+        }
+
+        // Out of order messages of the source no longer fit, but the next ones are delivered.
+        assert!(inbox.add(src, dsts[0], 1, 2, vec![2]).is_empty());
+        assert_eq!(
+            inbox.add(src, dsts[0], 1, 0, vec![0]),
+            vec![vec![0], content.clone()]
+        );
+
+        // Other sources have their own limit.
+        assert!(inbox.add(other_src, dsts[0], 1, 1, content).is_empty());
+        assert_eq!(inbox.sources[&other_src].channels[0].1.buffered.len(), 1);
+    }
+
+    #[test]
+    fn channels_are_limited_per_source() {
+        let mut rng = rng::new();
+        let src = rng.gen();
+        let mut inbox = OrderedInbox::default();
+
+        let dsts: Vec<_> = (0..=MAX_CHANNELS_PER_SOURCE)
+            .map(|_| DstLocation::Section(rng.gen()))
+            .collect();
+        for &dst in &dsts {
+            assert_eq!(inbox.add(src, dst, 1, 0, vec![0]), vec![vec![0]]);
+        }
+
+        // The least recently active channel was dropped, so it starts over.
+        assert_eq!(inbox.sources[&src].channels.len(), MAX_CHANNELS_PER_SOURCE);
+        assert_eq!(inbox.add(src, dsts[0], 1, 0, vec![0]), vec![vec![0]]);
+        assert!(inbox.add(src, dsts[2], 1, 0, vec![0]).is_empty());
+    }
+
+    #[test]
+    fn outbox_keeps_latest_messages() {
+        let mut rng = rng::new();
+        let dst = DstLocation::Node(rng.gen());
+        let mut outbox = OrderedOutbox::default();
+
+        let mut epoch = 0;
+        for index in 0..RESEND_BUFFER_SIZE + 2 {
+            let (channel_epoch, seq) = outbox.push(dst, vec![index as u8], &mut rng);
+            epoch = channel_epoch;
+            assert_eq!(seq, index as u64);
+        }
+
+        let kept: Vec<_> = outbox.get(&dst, epoch, 0, 4).map(|(seq, _)| seq).collect();
+        assert_eq!(kept, vec![2, 3]);
+        assert_eq!(outbox.get(&dst, epoch - 1, 0, 4).count(), 0);
+    }
+
+    #[test]
+    fn outbox_epochs_increase() {
+        let mut rng = rng::new();
+        let mut outbox = OrderedOutbox::default();
+
+        let epochs: Vec<_> = (0..3)
+            .map(|_| {
+                outbox
+                    .push(DstLocation::Node(rng.gen()), vec![], &mut rng)
+                    .0
+            })
+            .collect();
+        assert!(epochs[0] < epochs[1] && epochs[1] < epochs[2]);
+    }
+
+    #[cfg(feature = "mock_base")]
+    #[test]
+    fn idle_channels_expire() {
+        use fake_clock::FakeClock;
+
+        let mut rng = rng::new();
+        let src = rng.gen();
+        let dst = DstLocation::Node(rng.gen());
+        let mut outbox = OrderedOutbox::default();
+        let mut inbox = OrderedInbox::default();
+
+        let (epoch, seq) = outbox.push(dst, vec![0], &mut rng);
+        assert_eq!(inbox.add(src, dst, epoch, seq, vec![0]), vec![vec![0]]);
+
+        FakeClock::advance_time(CHANNEL_EXPIRY.as_secs() * 1000);
+
+        // The source opens a new channel, which the destination follows.
+        let (new_epoch, seq) = outbox.push(dst, vec![1], &mut rng);
+        assert!(new_epoch > epoch);
+        assert_eq!(seq, 0);
+        assert_eq!(inbox.add(src, dst, new_epoch, seq, vec![1]), vec![vec![1]]);
+
+        FakeClock::advance_time(INCOMING_CHANNEL_EXPIRY.as_secs() * 1000);
+        let _ = inbox.check_gaps();
+        assert!(inbox.sources.is_empty());
+    }
+}
@@ -12,7 +12,7 @@ use crate::{
     error::{Result, RoutingError},
     id::{P2pNode, PublicId},
     location::{DstLocation, SrcLocation},
-    section::{SectionProofChain, TrustStatus},
+    section::{CompactProofChain, SectionProofChain, TrustStatus},
 };
 
 use std::net::SocketAddr;
@@ -41,13 +41,25 @@ pub enum SrcAuthority {
         /// Proof chain whole last key is the section public key corresponding to the signature.
         proof_chain: SectionProofChain,
     },
+    /// Authority of a whole section, proven with a compact proof chain. Used instead of `Section`
+    /// once all the elders of the source section support it.
+    CompactSection {
+        /// Prefix of the source section.
+        prefix: Prefix,
+        /// BLS signature of the message corresponding to the source section public key.
+        signature: bls::Signature,
+        /// Proof chain whole last key is the section public key corresponding to the signature.
+        proof_chain: CompactProofChain,
+    },
 }
 
 impl SrcAuthority {
     pub(crate) fn src_location(&self) -> SrcLocation {
         match self {
             Self::Node { public_id, .. } => SrcLocation::Node(*public_id.name()),
-            Self::Section { prefix, .. } => SrcLocation::Section(*prefix),
+            Self::Section { prefix, .. } | Self::CompactSection { prefix, .. } => {
+                SrcLocation::Section(*prefix)
+            }
         }
     }
 
@@ -60,13 +72,13 @@ impl SrcAuthority {
     }
 
     pub(crate) fn is_section(&self) -> bool {
-        matches!(self, Self::Section { .. })
+        matches!(self, Self::Section { .. } | Self::CompactSection { .. })
     }
 
     pub(crate) fn as_node(&self) -> Result<&PublicId> {
         match self {
             Self::Node { public_id, .. } => Ok(public_id),
-            Self::Section { .. } => Err(RoutingError::BadLocation),
+            Self::Section { .. } | Self::CompactSection { .. } => Err(RoutingError::BadLocation),
         }
     }
 
@@ -74,6 +86,7 @@ impl SrcAuthority {
     pub(crate) fn as_section_key(&self) -> Result<&bls::PublicKey> {
         match self {
             Self::Section { proof_chain, .. } => Ok(proof_chain.last_key()),
+            Self::CompactSection { proof_chain, .. } => Ok(proof_chain.last_key()),
             Self::Node { .. } => Err(RoutingError::BadLocation),
         }
     }
@@ -86,6 +99,11 @@ impl SrcAuthority {
                 proof_chain,
                 ..
             } => Ok((prefix, proof_chain.last_key())),
+            Self::CompactSection {
+                prefix,
+                proof_chain,
+                ..
+            } => Ok((prefix, proof_chain.last_key())),
             Self::Node { .. } => Err(RoutingError::BadLocation),
         }
     }
@@ -112,7 +130,7 @@ impl SrcAuthority {
             variant,
         })?;
 
-        match self {
+        let (signature, last_key, status) = match self {
             Self::Node {
                 public_id,
                 signature,
@@ -120,35 +138,53 @@ impl SrcAuthority {
                 if !public_id.verify(&bytes, signature) {
                     return Err(RoutingError::FailedSignature);
                 }
+
+                return Ok(VerifyStatus::Full);
             }
             Self::Section {
                 prefix,
                 signature,
                 proof_chain,
-            } => {
-                let trusted_key_infos = trusted_key_infos
-                    .into_iter()
-                    .filter(|(known_prefix, _)| prefix.is_compatible(known_prefix))
-                    .map(|(_, key_info)| key_info);
-
-                match proof_chain.check_trust(trusted_key_infos) {
-                    TrustStatus::Trusted => (),
-                    TrustStatus::Unknown => return Ok(VerifyStatus::Unknown),
-                    TrustStatus::Invalid => return Err(RoutingError::UntrustedMessage),
-                };
-
-                let bytes = bincode::serialize(&SignableView {
-                    dst,
-                    dst_key,
-                    variant,
-                })?;
-
-                if !proof_chain.last_key().verify(signature, &bytes) {
-                    return Err(RoutingError::FailedSignature);
-                }
-            }
+            } => (
+                signature,
+                proof_chain.last_key(),
+                proof_chain.check_trust(trusted_keys(prefix, trusted_key_infos)),
+            ),
+            Self::CompactSection {
+                prefix,
+                signature,
+                proof_chain,
+            } => (
+                signature,
+                proof_chain.last_key(),
+                proof_chain.check_trust(trusted_keys(prefix, trusted_key_infos)),
+            ),
+        };
+
+        match status {
+            TrustStatus::Trusted => (),
+            TrustStatus::Unknown => return Ok(VerifyStatus::Unknown),
+            TrustStatus::Invalid => return Err(RoutingError::UntrustedMessage),
+        };
+
+        if !last_key.verify(signature, &bytes) {
+            return Err(RoutingError::FailedSignature);
         }
 
         Ok(VerifyStatus::Full)
     }
 }
+
+// Returns the keys of the given trusted keys that are of sections compatible with `prefix`.
+fn trusted_keys<'a, I>(
+    prefix: &'a Prefix,
+    trusted_key_infos: I,
+) -> impl Iterator<Item = &'a bls::PublicKey>
+where
+    I: IntoIterator<Item = (&'a Prefix, &'a bls::PublicKey)>,
+{
+    trusted_key_infos
+        .into_iter()
+        .filter(move |(known_prefix, _)| prefix.is_compatible(known_prefix))
+        .map(|(_, key)| key)
+}
@@ -8,10 +8,16 @@
 
 use super::{AccumulatingMessage, Message, MessageHash};
 use crate::{
-    consensus::{GenesisPrefixInfo, ParsecRequest, ParsecResponse},
+    consensus::{GenesisPrefixInfo, ParsecRequest, ParsecResponse, ProofShare, Proven},
+    crypto::{signing::Signature, Digest256},
     id::PublicId,
+    location::DstLocation,
+    network_id::NetworkId,
     relocation::{RelocateDetails, RelocatePayload},
-    section::EldersInfo,
+    section::{
+        CompactProofChain, EldersInfo, LocatedSection, MembershipStatement, RecoveryCertificate,
+    },
+    transport::Priority,
 };
 use bytes::Bytes;
 use hex_fmt::HexFmt;
@@ -38,6 +44,18 @@ pub enum Variant {
     },
     /// User-facing message
     UserMessage(Vec<u8>),
+    /// Chunk of a user-facing message too large to be sent as a single `UserMessage`. The
+    /// destination reassembles the payload once it receives all the chunks.
+    UserMessageChunk {
+        /// Hash of the whole payload.
+        hash: Digest256,
+        /// Index of this chunk.
+        index: u32,
+        /// Total number of chunks of the payload.
+        count: u32,
+        /// The chunk.
+        data: Vec<u8>,
+    },
     /// Approves the joining node as a routing node.
     /// Section X -> Node joining X
     NodeApproval(GenesisPrefixInfo),
@@ -50,7 +68,12 @@ pub enum Variant {
     MessageSignature(Box<AccumulatingMessage>),
     /// Sent from a newly connected peer to the bootstrap node to request connection infos of
     /// members of the section matching the given name.
-    BootstrapRequest(XorName),
+    BootstrapRequest {
+        /// Name the section to join should match.
+        name: XorName,
+        /// Id of the network the peer wants to join, if known.
+        network_id: Option<NetworkId>,
+    },
     /// Sent from the bootstrap node to a peer in response to `BootstrapRequest`. It can either
     /// accept the peer into the section, or redirect it to another set of bootstrap peers
     BootstrapResponse(BootstrapResponse),
@@ -97,6 +120,197 @@ pub enum Variant {
         /// Public key set that got consensused
         public_key_set: bls::PublicKeySet,
     },
+    /// Sent on connection setup to challenge the peer to prove it owns the identity it claims at
+    /// its address.
+    AddressChallenge {
+        /// Random nonce the peer has to send back.
+        nonce: u64,
+        /// Id of the network of the challenger, if known.
+        network_id: Option<NetworkId>,
+    },
+    /// Response to `AddressChallenge`, echoing its nonce. Signed by the responding node.
+    AddressChallengeResponse {
+        /// Nonce of the challenge.
+        nonce: u64,
+        /// Id of the network of the responder, if known.
+        network_id: Option<NetworkId>,
+    },
+    /// Message exchanged between the genesis nodes for the DKG of the genesis section key.
+    GenesisDkgMessage {
+        /// The genesis nodes taking part in the DKG.
+        participants: BTreeSet<PublicId>,
+        /// The serialized DKG message.
+        message: Bytes,
+    },
+    /// Signature shares of the genesis section state, sent by every genesis node to the others
+    /// once the genesis DKG completes.
+    GenesisSignatureShares {
+        /// The genesis section key the shares were created with.
+        public_key: bls::PublicKey,
+        /// Shares of the signatures of the genesis section state: the genesis `EldersInfo`,
+        /// the joins of the members, then the neighbour `EldersInfo`s and keys, if any.
+        shares: Vec<bls::SignatureShare>,
+        /// When recovering a section, signature of the link between the key of the recovery
+        /// snapshot and the new section key.
+        recovery_signature: Option<Signature>,
+    },
+    /// Sent by the elders of a section recovered after losing all its elders to the rest of the
+    /// section and to the neighbours, to link the new section key to the previous one.
+    RecoveryCertificate(Box<RecoveryCertificate>),
+    /// Decryption share of a ciphertext encrypted with our section key, sent by an elder to the
+    /// other elders once the section agreed to decrypt it.
+    DecryptionShare {
+        /// The ciphertext being decrypted.
+        ciphertext: Box<bls::Ciphertext>,
+        /// Index of the key share of the sender.
+        index: usize,
+        /// The decryption share.
+        share: bls::DecryptionShare,
+    },
+    /// Signature share of the skip link to our new section key, created by an elder with its share
+    /// of the older key the link is from and sent to the other elders.
+    SkipLinkShare {
+        /// Index of the new key in our section proof chain.
+        index: u64,
+        /// The new key.
+        key: bls::PublicKey,
+        /// Signature share of the new key by the older key.
+        proof_share: ProofShare,
+    },
+    /// Sent by a light client to the elders of the section it follows, to learn the current
+    /// elders and section key.
+    SectionProofRequest {
+        /// The latest key of the section the client trusts.
+        known_key: bls::PublicKey,
+    },
+    /// Response to `SectionProofRequest`.
+    SectionProofResponse {
+        /// The current elders of the section, signed by its current key.
+        elders_info: Proven<EldersInfo>,
+        /// Proof of the current section key, starting from the key the client trusts (or from the
+        /// first key if the section doesn't know it).
+        proof_chain: Box<CompactProofChain>,
+    },
+    /// Sent by a node to the section its destination name belongs to, to learn the elders of that
+    /// section.
+    LocateRequest,
+    /// Response to `LocateRequest`, sent by one of the elders of the located section.
+    LocateResponse(Box<LocatedSection>),
+    /// Sent by a member of a section to its elders, to get its current membership certified.
+    MembershipCertificateRequest,
+    /// Response to `MembershipCertificateRequest`: an elder's signature share of the statement
+    /// about the requesting member.
+    MembershipCertificateShare {
+        /// The statement about the member.
+        statement: MembershipStatement,
+        /// Signature share of the statement by the section key.
+        proof_share: ProofShare,
+    },
+    /// Sent to a peer whose message we couldn't decode because it was created by a newer protocol
+    /// version than ours.
+    ///
+    /// New variants must always be added at the end of this enum, so that nodes of the previous
+    /// protocol version can still decode the others.
+    MessageRejected {
+        /// Hash of the rejected message.
+        hash: MessageHash,
+        /// Our protocol version.
+        protocol_version: u16,
+    },
+    /// User-facing message whose destination is asked to acknowledge it with an `Ack`.
+    AckedUserMessage(Vec<u8>),
+    /// Acknowledges the delivery of the `AckedUserMessage` with the given hash. Sent by the
+    /// destination node or, with the signatures of a quorum of its elders, by the destination
+    /// section.
+    Ack(MessageHash),
+    /// User-facing message of an ordered channel from its source node to a destination. The
+    /// destination delivers the messages of the channel in the order of their sequence numbers.
+    OrderedUserMessage {
+        /// Destination of the channel. Differs from the destination of the message only when it
+        /// is resent to a single elder of the destination section.
+        channel: DstLocation,
+        /// Identifies the instance of the channel. Channels the source opens later have greater
+        /// epochs, so the destination follows the latest one and drops the messages of the others.
+        epoch: u64,
+        /// Position of the message in the channel, starting from 0.
+        seq: u64,
+        /// Number of the resend request this message answers, or 0 if sent for the first time.
+        /// Makes every resend a distinct message, so it isn't filtered out as a duplicate of the
+        /// copy sent before.
+        resend: u32,
+        /// The payload.
+        content: Vec<u8>,
+    },
+    /// Sent by a destination of an ordered channel to its source, to ask for the messages it is
+    /// missing.
+    OrderedResendRequest {
+        /// Destination of the channel.
+        channel: DstLocation,
+        /// Epoch of the channel.
+        epoch: u64,
+        /// Sequence number of the first missing message.
+        from: u64,
+        /// Sequence number of the first message received after the missing ones.
+        to: u64,
+        /// Number of times the messages were requested, including this one.
+        attempt: u32,
+    },
+}
+
+impl Variant {
+    /// Returns whether this variant carries the content of a message sent by the upper layers.
+    pub(crate) fn is_user_message(&self) -> bool {
+        match self {
+            Self::UserMessage(_)
+            | Self::UserMessageChunk { .. }
+            | Self::AckedUserMessage(_)
+            | Self::OrderedUserMessage { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Priority class to send this message with. Messages carrying another message inherit its
+    /// class.
+    pub(crate) fn priority(&self) -> Priority {
+        match self {
+            Self::ParsecPoke(_) | Self::ParsecRequest(..) | Self::ParsecResponse(..) => {
+                Priority::Consensus
+            }
+            Self::DKGMessage { .. }
+            | Self::DKGOldElders { .. }
+            | Self::GenesisDkgMessage { .. }
+            | Self::GenesisSignatureShares { .. }
+            | Self::SkipLinkShare { .. } => Priority::Dkg,
+            Self::UserMessage(_)
+            | Self::UserMessageChunk { .. }
+            | Self::AckedUserMessage(_)
+            | Self::OrderedUserMessage { .. } => Priority::Bulk,
+            Self::MessageSignature(msg) => msg.content.variant.priority(),
+            Self::BouncedUntrustedMessage(msg) => msg.variant().priority(),
+            Self::NeighbourInfo { .. }
+            | Self::NodeApproval(_)
+            | Self::GenesisUpdate(_)
+            | Self::Relocate(_)
+            | Self::BootstrapRequest { .. }
+            | Self::BootstrapResponse(_)
+            | Self::JoinRequest(_)
+            | Self::Ping
+            | Self::BouncedUnknownMessage { .. }
+            | Self::AddressChallenge { .. }
+            | Self::AddressChallengeResponse { .. }
+            | Self::RecoveryCertificate(_)
+            | Self::DecryptionShare { .. }
+            | Self::SectionProofRequest { .. }
+            | Self::SectionProofResponse { .. }
+            | Self::LocateRequest
+            | Self::LocateResponse(_)
+            | Self::MembershipCertificateRequest
+            | Self::MembershipCertificateShare { .. }
+            | Self::MessageRejected { .. }
+            | Self::Ack(_)
+            | Self::OrderedResendRequest { .. } => Priority::Control,
+        }
+    }
 }
 
 impl Debug for Variant {
@@ -108,11 +322,24 @@ impl Debug for Variant {
                 .field("nonce", nonce)
                 .finish(),
             Self::UserMessage(payload) => write!(f, "UserMessage({:10})", HexFmt(payload)),
+            Self::UserMessageChunk {
+                hash, index, count, ..
+            } => write!(
+                f,
+                "UserMessageChunk({:10}, {}/{})",
+                HexFmt(hash),
+                index,
+                count
+            ),
             Self::NodeApproval(payload) => write!(f, "NodeApproval({:?})", payload),
             Self::GenesisUpdate(payload) => write!(f, "GenesisUpdate({:?})", payload),
             Self::Relocate(payload) => write!(f, "Relocate({:?})", payload),
             Self::MessageSignature(payload) => write!(f, "MessageSignature({:?})", payload.content),
-            Self::BootstrapRequest(payload) => write!(f, "BootstrapRequest({})", payload),
+            Self::BootstrapRequest { name, network_id } => f
+                .debug_struct("BootstrapRequest")
+                .field("name", name)
+                .field("network_id", network_id)
+                .finish(),
             Self::BootstrapResponse(payload) => write!(f, "BootstrapResponse({:?})", payload),
             Self::JoinRequest(payload) => write!(f, "JoinRequest({:?})", payload),
             Self::ParsecPoke(version) => write!(f, "ParsecPoke({})", version),
@@ -151,6 +378,118 @@ impl Debug for Variant {
                 .field("section_key_index", section_key_index)
                 .field("public_key_set", public_key_set)
                 .finish(),
+            Self::AddressChallenge { nonce, network_id } => f
+                .debug_struct("AddressChallenge")
+                .field("nonce", nonce)
+                .field("network_id", network_id)
+                .finish(),
+            Self::AddressChallengeResponse { nonce, network_id } => f
+                .debug_struct("AddressChallengeResponse")
+                .field("nonce", nonce)
+                .field("network_id", network_id)
+                .finish(),
+            Self::GenesisDkgMessage {
+                participants,
+                message,
+            } => f
+                .debug_struct("GenesisDkgMessage")
+                .field("participants", participants)
+                .field("message_hash", &MessageHash::from_bytes(message))
+                .finish(),
+            Self::GenesisSignatureShares {
+                public_key,
+                shares,
+                recovery_signature,
+            } => f
+                .debug_struct("GenesisSignatureShares")
+                .field("public_key", public_key)
+                .field("shares", &shares.len())
+                .field("recovery_signature", &recovery_signature.is_some())
+                .finish(),
+            Self::RecoveryCertificate(certificate) => f
+                .debug_struct("RecoveryCertificate")
+                .field("snapshot_index", &certificate.snapshot.value.index)
+                .field("elders_info", &certificate.elders_info.value)
+                .field("new_key", certificate.new_key())
+                .finish(),
+            Self::DecryptionShare { index, .. } => f
+                .debug_struct("DecryptionShare")
+                .field("index", index)
+                .finish(),
+            Self::SkipLinkShare {
+                index, proof_share, ..
+            } => f
+                .debug_struct("SkipLinkShare")
+                .field("index", index)
+                .field("proof_share", proof_share)
+                .finish(),
+            Self::SectionProofRequest { known_key } => f
+                .debug_struct("SectionProofRequest")
+                .field("known_key", known_key)
+                .finish(),
+            Self::SectionProofResponse {
+                elders_info,
+                proof_chain,
+            } => f
+                .debug_struct("SectionProofResponse")
+                .field("elders_info", &elders_info.value)
+                .field("last_key_index", &proof_chain.last_key_index())
+                .finish(),
+            Self::LocateRequest => write!(f, "LocateRequest"),
+            Self::LocateResponse(located) => f
+                .debug_tuple("LocateResponse")
+                .field(&located.elders_info.value)
+                .finish(),
+            Self::MembershipCertificateRequest => write!(f, "MembershipCertificateRequest"),
+            Self::MembershipCertificateShare {
+                statement,
+                proof_share,
+            } => f
+                .debug_struct("MembershipCertificateShare")
+                .field("statement", statement)
+                .field("proof_share", proof_share)
+                .finish(),
+            Self::MessageRejected {
+                hash,
+                protocol_version,
+            } => f
+                .debug_struct("MessageRejected")
+                .field("hash", hash)
+                .field("protocol_version", protocol_version)
+                .finish(),
+            Self::AckedUserMessage(payload) => {
+                write!(f, "AckedUserMessage({:10})", HexFmt(payload))
+            }
+            Self::Ack(hash) => write!(f, "Ack({:?})", hash),
+            Self::OrderedUserMessage {
+                channel,
+                epoch,
+                seq,
+                resend,
+                content,
+            } => write!(
+                f,
+                "OrderedUserMessage({:?}, {}, {}, {}, {:10})",
+                channel,
+                epoch,
+                seq,
+                resend,
+                HexFmt(content)
+            ),
+            Self::OrderedResendRequest {
+                channel,
+                epoch,
+                from,
+                to,
+                attempt,
+            } => f
+                .debug_struct("OrderedResendRequest")
+                .field("channel", channel)
+                .field("epoch", epoch)
+                .field("from", from)
+                .field("to", to)
+                .field("attempt", attempt)
+                .finish(),
         }
     }
 }
@@ -163,6 +502,7 @@ pub enum BootstrapResponse {
     Join {
         elders_info: EldersInfo,
         section_key: bls::PublicKey,
+        network_id: Option<NetworkId>,
     },
     /// The new peer should retry bootstrapping with another section. The set of connection infos
     /// of the members of that section is provided.
@@ -174,6 +514,8 @@ pub enum BootstrapResponse {
 pub struct JoinRequest {
     /// The public key of the section to join
     pub section_key: bls::PublicKey,
+    /// Id of the network the peer wants to join, if known.
+    pub network_id: Option<NetworkId>,
     /// If the peer is being relocated, contains `RelocatePayload`. Otherwise contains `None`.
     pub relocate_payload: Option<RelocatePayload>,
 }
@@ -183,6 +525,7 @@ impl Debug for JoinRequest {
         formatter
             .debug_struct("JoinRequest")
             .field("section_key", &self.section_key)
+            .field("network_id", &self.network_id)
             .field(
                 "relocate_payload",
                 &self
@@ -0,0 +1,39 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::crypto::{self, Digest256};
+use hex_fmt::HexFmt;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// Identifier of a network. Nodes refuse to talk to peers that belong to a different network, so
+/// that for example a node configured with the contacts of a testnet can't join the production
+/// network by accident.
+///
+/// Unless configured explicitly via `NodeConfig`, the id is derived from the genesis section key
+/// by the first node and learned from the section a node bootstraps against by the others.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct NetworkId(pub Digest256);
+
+impl NetworkId {
+    /// Derives the network id from the genesis section key.
+    pub fn from_genesis_key(key: &bls::PublicKey) -> Self {
+        Self(crypto::sha3_256(&key.to_bytes()))
+    }
+}
+
+impl Debug for NetworkId {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "NetworkId({:10})", HexFmt(&self.0))
+    }
+}
+
+impl Display for NetworkId {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{:10}", HexFmt(&self.0))
+    }
+}
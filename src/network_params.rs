@@ -6,15 +6,44 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{ELDER_SIZE, RECOMMENDED_SECTION_SIZE};
+use crate::{
+    protocol::{FEATURE_DEFLATE, FEATURE_SNAPPY},
+    ELDER_SIZE, RECOMMENDED_SECTION_SIZE,
+};
 
-/// Network parameters: number of elders, recommended section size
+/// Default minimum size, in bytes, of a serialized message for it to be compressed.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Default maximum number of infants a section admits, unless more nodes are requested: no limit.
+pub const MAX_INFANTS: usize = usize::MAX;
+
+/// Default maximum number of hops a message can take before it is dropped.
+pub const MAX_HOPS: u8 = 32;
+
+/// Network parameters: number of elders, recommended section size, message compression, relaying
 #[derive(Clone, Copy, Debug)]
 pub struct NetworkParams {
     /// The number of elders per section
     pub elder_size: usize,
     /// Recommended number of nodes in a section.
     pub recommended_section_size: usize,
+    /// Number of infants (members not yet aged past `MIN_AGE`, other than elders) a section stops
+    /// admitting new infants at. More are admitted if the upper layers request them with
+    /// `Node::request_more_nodes`. Unlimited by default.
+    pub max_infants: usize,
+    /// Codec to compress the messages we send with, or `None` (the default) to send them
+    /// uncompressed. Messages carry the codec they were compressed with, so nodes can use
+    /// different codecs. Takes effect only once every elder of our section runs a protocol version
+    /// supporting compression.
+    pub compression: Option<Compression>,
+    /// Messages whose serialized size is below this many bytes are sent uncompressed.
+    pub compression_threshold: usize,
+    /// Messages that already took this many hops are dropped instead of being relayed further,
+    /// so a routing loop can't keep them circulating.
+    pub max_hops: u8,
+    /// Whether to add our name to the route recorded in the messages we relay, for debugging the
+    /// paths messages take. Makes every relayed message longer by the size of a name.
+    pub record_route: bool,
 }
 
 impl Default for NetworkParams {
@@ -22,6 +51,30 @@ impl Default for NetworkParams {
         Self {
             elder_size: ELDER_SIZE,
             recommended_section_size: RECOMMENDED_SECTION_SIZE,
+            max_infants: MAX_INFANTS,
+            compression: None,
+            compression_threshold: COMPRESSION_THRESHOLD,
+            max_hops: MAX_HOPS,
+            record_route: false,
+        }
+    }
+}
+
+/// Codec for compressing messages on the wire.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Compression {
+    /// Deflate: better ratio, slower.
+    Deflate,
+    /// Snappy: faster, lower ratio.
+    Snappy,
+}
+
+impl Compression {
+    /// Feature bit a peer needs to decode messages compressed with this codec.
+    pub fn feature(self) -> u32 {
+        match self {
+            Self::Deflate => FEATURE_DEFLATE,
+            Self::Snappy => FEATURE_SNAPPY,
         }
     }
 }
@@ -10,27 +10,38 @@ mod stage;
 #[cfg(all(test, feature = "mock"))]
 mod tests;
 
+pub use self::stage::ACK_TIMEOUT;
 #[cfg(feature = "mock_base")]
 pub use self::stage::{BOOTSTRAP_TIMEOUT, JOIN_TIMEOUT};
 
-use self::stage::{Approved, Bootstrapping, JoinParams, Joining, RelocateParams, Stage};
+use self::stage::{
+    Approved, Bootstrapping, Genesis, GenesisParams, JoinParams, Joining, RelocateParams, Stage,
+};
 use crate::{
-    consensus::GenesisPrefixInfo,
+    consensus::{GenesisPrefixInfo, Proven},
     core::Core,
     error::{Result, RoutingError},
     event::{Connected, Event},
     id::{FullId, P2pNode, PublicId},
     location::{DstLocation, SrcLocation},
     log_utils,
-    messages::{BootstrapResponse, Message, MessageStatus, QueuedMessage, Variant},
+    messages::{
+        self, BootstrapResponse, CompressionStats, CreateError, Message, MessageHash,
+        MessageStatus, QueuedMessage, Variant,
+    },
+    network_id::NetworkId,
     network_params::NetworkParams,
     pause::PausedState,
+    protocol::PROTOCOL_VERSION,
     quic_p2p::{EventSenders, Peer, Token},
+    rate_limit::{Admission, RateLimitStats, RateLimits},
     relocation::SignedRelocateDetails,
     rng::{self, MainRng},
-    section::SectionProofChain,
-    section::SharedState,
-    transport::PeerStatus,
+    section::{
+        CompactProofChain, LocatedSection, MembershipCertificate, NetworkMap, SectionProofChain,
+    },
+    section::{RecoverySnapshot, SharedState},
+    transport::{PeerStatus, QueueDepths, ResendPolicy},
     TransportConfig, TransportEvent,
 };
 
@@ -49,25 +60,40 @@ use {crate::section::EldersInfo, std::collections::BTreeSet};
 pub struct NodeConfig {
     /// If true, configures the node to start a new network instead of joining an existing one.
     pub first: bool,
+    /// If set, configures the node to start a new network together with the given nodes, which
+    /// must include this node. The genesis section key is generated by DKG between them, so no
+    /// single node ever knows the whole secret key. Takes precedence over `first`.
+    pub genesis: Option<Vec<P2pNode>>,
     /// The ID of the node or `None` for randomly generated one.
     pub full_id: Option<FullId>,
     /// Configuration for the underlying network transport.
     pub transport_config: TransportConfig,
     /// Global network parameters. Must be identical for all nodes in the network.
     pub network_params: NetworkParams,
+    /// The id of the network to join or start. If `None`, the first node derives it from the
+    /// genesis section key and the other nodes learn it from the section they bootstrap against.
+    pub network_id: Option<NetworkId>,
     /// Random number generator to be used by the node. Can be used to achieve repeatable tests by
     /// providing a pre-seeded RNG. By default uses a random seed provided by the OS.
     pub rng: MainRng,
+    /// Limits on the incoming traffic from individual peers. No limits by default.
+    pub rate_limits: RateLimits,
+    /// Policy for resending the messages that failed to be sent.
+    pub resend_policy: ResendPolicy,
 }
 
 impl Default for NodeConfig {
     fn default() -> Self {
         Self {
             first: false,
+            genesis: None,
             full_id: None,
             transport_config: TransportConfig::default(),
             network_params: NetworkParams::default(),
+            network_id: None,
             rng: rng::new(),
+            rate_limits: RateLimits::default(),
+            resend_policy: ResendPolicy::default(),
         }
     }
 }
@@ -98,15 +124,31 @@ impl Node {
     ///
     /// Returns the node itself, the user event receiver and the client network
     /// event receiver.
-    pub fn new(config: NodeConfig) -> (Self, Receiver<Event>, Receiver<TransportEvent>) {
+    pub fn new(mut config: NodeConfig) -> (Self, Receiver<Event>, Receiver<TransportEvent>) {
         let (timer_tx, timer_rx) = crossbeam_channel::unbounded();
         let (transport_tx, transport_node_rx, transport_client_rx) = transport_channels();
         let (user_event_tx, user_event_rx) = crossbeam_channel::unbounded();
 
         let first = config.first;
+        let genesis = config.genesis.take();
         let mut core = Core::new(config, timer_tx, transport_tx, user_event_tx);
 
-        let stage = if first {
+        let stage = if let Some(participants) = genesis {
+            match Genesis::new(&mut core, participants) {
+                Ok(stage) => {
+                    info!(
+                        "{} Starting a new network with {} genesis nodes.",
+                        core.name(),
+                        stage.elders_info().elders.len()
+                    );
+                    Stage::Genesis(stage)
+                }
+                Err(error) => {
+                    error!("{} Failed to start the genesis: {:?}", core.name(), error);
+                    Stage::Terminated
+                }
+            }
+        } else if first {
             match Approved::first(&mut core) {
                 Ok(stage) => {
                     info!("{} Started a new network as a seed node.", core.name());
@@ -317,7 +359,7 @@ impl Node {
     /// Checks whether the given location represents self.
     pub fn in_dst_location(&self, dst: &DstLocation) -> bool {
         match &self.stage {
-            Stage::Bootstrapping(_) | Stage::Joining(_) => match dst {
+            Stage::Genesis(_) | Stage::Bootstrapping(_) | Stage::Joining(_) => match dst {
                 DstLocation::Node(name) => name == self.core.name(),
                 DstLocation::Section(_) => false,
                 DstLocation::Direct => true,
@@ -345,7 +387,127 @@ impl Node {
         }
     }
 
-    /// Send a message.
+    /// Vote for admitting `count` more nodes into our section, for example when the upper layers
+    /// are running out of resources. Our section admits as many infants beyond
+    /// `NetworkParams::max_infants` as a quorum of our elders requested, so the elders don't need
+    /// to ask for the same number of them.
+    /// Returns `InvalidState` error if we are not an elder.
+    pub fn request_more_nodes(&mut self, count: usize) -> Result<()> {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+        {
+            stage.vote_for_more_nodes(count);
+            Ok(())
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
+    /// Vote for evicting the member with the given name from our section, for example when it
+    /// misbehaves in a way only the upper layers can detect. Once accumulated, the member is
+    /// removed the same way as if it went offline. `reason` is only logged.
+    /// Returns `InvalidState` error if we are not an elder.
+    pub fn propose_eviction(&mut self, name: XorName, reason: &str) -> Result<()> {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+        {
+            stage.vote_for_eviction(name, reason);
+            Ok(())
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
+    /// Vote for notarising the given document hash. The hashes agreed on by our elders are
+    /// periodically collected into a batch whose Merkle root is signed by our section, and each
+    /// elder then raises `Event::Notarised` with a receipt for every hash in it. The receipt
+    /// proves that the hash existed at the given time and can be verified offline.
+    /// Returns `InvalidState` error if we are not an elder.
+    pub fn notarise(&mut self, hash: [u8; 32]) -> Result<()> {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+        {
+            stage.vote_for_notarisation(hash);
+            Ok(())
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
+    /// Vote for upgrading our section to the given protocol version. Once accumulated, our elders
+    /// restart one at a time, each raising `Event::RestartForUpgrade` when its turn comes, so that
+    /// a quorum of them stays running. In sections with too few elders for that, the upgrade
+    /// stalls unless `allow_quorum_loss` is set, in which case consensus pauses while an elder
+    /// restarts. A stalled upgrade goes on once proposed again with `allow_quorum_loss` set. Once
+    /// every elder runs the new version, `Event::UpgradeCompleted` is raised.
+    /// Returns `InvalidState` error if we are not an elder.
+    pub fn propose_upgrade(&mut self, version: u16, allow_quorum_loss: bool) -> Result<()> {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+        {
+            stage.vote_for_upgrade(version, allow_quorum_loss);
+            Ok(())
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
+    /// Vote for decrypting the given ciphertext, encrypted with our current section key (see
+    /// `public_key_set`). Once a quorum of our elders votes for the same ciphertext, the elders
+    /// exchange their decryption shares and each raises `Event::SectionDecrypted` with the
+    /// plaintext, so no single elder can decrypt it alone.
+    /// Only our current key can be used: if it changes before the elders agree on the request, the
+    /// request is dropped without `Event::SectionDecrypted`, and has to be made again with a
+    /// ciphertext encrypted with the new key.
+    /// Returns `InvalidState` error if we are not an elder.
+    pub fn request_section_decryption(&mut self, ciphertext: bls::Ciphertext) -> Result<()> {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+        {
+            stage.vote_for_decryption(ciphertext);
+            Ok(())
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
+    /// Vote for replacing our section key with a new one generated by the current elders, without
+    /// waiting for the periodic refresh. The new key is linked to the current one in our section
+    /// proof chain.
+    /// Returns `InvalidState` error if we are not an elder.
+    pub fn refresh_section_key(&mut self) -> Result<()> {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+        {
+            stage.vote_for_key_refresh(our_id);
+            Ok(())
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
+    /// Send a message. Payloads larger than `MAX_CHUNK_SIZE` are transparently sent in chunks,
+    /// each signed and relayed separately, and reassembled by the destination before raising
+    /// `Event::MessageReceived`.
+    /// Returns `PayloadTooLarge` error if the content exceeds `MAX_PAYLOAD_SIZE`.
     pub fn send_message(
         &mut self,
         src: SrcLocation,
@@ -359,16 +521,65 @@ impl Node {
         let _log_ident = self.set_log_ident();
 
         match &mut self.stage {
-            Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => {
+            Stage::Genesis(_) | Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => {
                 Err(RoutingError::InvalidState)
             }
-            Stage::Approved(stage) => stage.send_routing_message(
-                &mut self.core,
-                src,
-                dst,
-                Variant::UserMessage(content),
-                None,
-            ),
+            Stage::Approved(stage) => {
+                for variant in messages::user_message_variants(content)? {
+                    stage.send_routing_message(&mut self.core, src, dst, variant, None)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Send a message from this node through the ordered channel to `dst`. The destination
+    /// delivers the messages of the channel in the order they were sent in, asking this node to
+    /// resend the ones it misses. Messages missing for too long, or sent too long ago for this node
+    /// to still have them, are eventually skipped.
+    ///
+    /// Unlike `send_message`, the content is not chunked, so it must not exceed `MAX_CHUNK_SIZE`.
+    pub fn send_ordered_message(
+        &mut self,
+        dst: DstLocation,
+        content: Vec<u8>,
+    ) -> Result<(), RoutingError> {
+        if let DstLocation::Direct = dst {
+            return Err(RoutingError::BadLocation);
+        }
+
+        let _log_ident = self.set_log_ident();
+
+        match &mut self.stage {
+            Stage::Genesis(_) | Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => {
+                Err(RoutingError::InvalidState)
+            }
+            Stage::Approved(stage) => stage.send_ordered_message(&mut self.core, dst, content),
+        }
+    }
+
+    /// Send a message from this node and ask its destination to acknowledge it. Raises
+    /// `Event::Delivered` once acknowledged, or `Event::DeliveryTimeout` if not acknowledged
+    /// within `ACK_TIMEOUT`. A section destination acknowledges only once a quorum of its elders
+    /// received the message. Returns the hash identifying the message in those events.
+    ///
+    /// Unlike `send_message`, the content is not chunked, so it must not exceed `MAX_CHUNK_SIZE`.
+    pub fn send_message_with_ack(
+        &mut self,
+        dst: DstLocation,
+        content: Vec<u8>,
+    ) -> Result<MessageHash, RoutingError> {
+        if let DstLocation::Direct = dst {
+            return Err(RoutingError::BadLocation);
+        }
+
+        let _log_ident = self.set_log_ident();
+
+        match &mut self.stage {
+            Stage::Genesis(_) | Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => {
+                Err(RoutingError::InvalidState)
+            }
+            Stage::Approved(stage) => stage.send_message_with_ack(&mut self.core, dst, content),
         }
     }
 
@@ -401,6 +612,92 @@ impl Node {
             .ok_or(RoutingError::InvalidState)
     }
 
+    /// Returns the value of the given round of our section randomness beacon, or `None` if the
+    /// round didn't happen yet or is too old to be still kept. The value is the signature of the
+    /// round number and the value of the previous round by our section key at the time.
+    pub fn beacon(&self, round: u64) -> Option<&bls::Signature> {
+        self.stage
+            .approved()
+            .and_then(|stage| stage.shared_state.beacon.get(round))
+    }
+
+    /// Returns the protocol version every elder of our section runs. Features introduced by later
+    /// versions must stay switched off until it is raised by a completed upgrade, even if this node
+    /// already supports them. Returns `None` if we are not a member of any section yet.
+    pub fn active_protocol_version(&self) -> Option<u16> {
+        self.stage
+            .approved()
+            .map(|stage| stage.shared_state.upgrade.active_version())
+    }
+
+    /// Returns the number of incoming messages dropped so far for exceeding the rate limits set in
+    /// `NodeConfig::rate_limits`.
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.core.rate_limiter.stats
+    }
+
+    /// Returns the number of bytes of the messages sent so far, before and after compression.
+    pub fn compression_stats(&self) -> CompressionStats {
+        self.core.compression_stats
+    }
+
+    /// Returns the number of outgoing messages waiting in the per-peer queues, by priority class,
+    /// for monitoring. Growing queues mean our peers can't keep up with the traffic we send them.
+    pub fn queue_depths(&self) -> QueueDepths {
+        self.core.transport.queue_depths()
+    }
+
+    /// Returns the number of messages we dropped instead of relaying because they already took
+    /// `NetworkParams::max_hops` hops. A growing count points to a routing loop.
+    pub fn hop_limit_drops(&self) -> u64 {
+        self.core.hop_limit_drops
+    }
+
+    /// Returns the compact proof of our current section key, starting from the key at the given
+    /// index in our section proof chain. The proof uses the skip links our section signed to stay
+    /// short, so it is suitable for clients that were offline for a long time. Returns `None` if
+    /// we are not a member of any section yet.
+    pub fn compact_proof(&self, from: u64) -> Option<CompactProofChain> {
+        self.stage
+            .approved()
+            .map(|stage| stage.shared_state.prove_compact(from))
+    }
+
+    /// Returns the certificate of our membership in our section: our name, age, membership state
+    /// and section prefix signed by our section, with the proof of the signing key. Anyone trusting
+    /// our genesis key can verify it with `MembershipCertificate::verify`, without connecting to
+    /// the network. Returns `None` if we are not a member of any section yet or our elders haven't
+    /// certified our current membership yet. Certificates are requested automatically whenever our
+    /// membership changes.
+    pub fn membership_certificate(&self) -> Option<&MembershipCertificate> {
+        self.stage
+            .approved()
+            .and_then(|stage| stage.membership_certificate(self.core.name()))
+    }
+
+    /// Returns every section we know (ours and our neighbours) with its prefix, elders and latest
+    /// key, together with our section proof chain that all of them are signed against. Returns
+    /// `None` if we are not a member of any section yet.
+    pub fn network_map(&self) -> Option<NetworkMap> {
+        self.stage
+            .approved()
+            .map(|stage| stage.shared_state.network_map())
+    }
+
+    /// Returns the section responsible for `name` with the proof of its elders, if it is our
+    /// section or one of our neighbours. Otherwise queries that section and returns `None`; the
+    /// answer is raised as `Event::Located` once it arrives and its proof is verified.
+    /// Returns `InvalidState` error if we are not a member of any section yet.
+    pub fn locate(&mut self, name: &XorName) -> Result<Option<LocatedSection>> {
+        let _log_ident = self.set_log_ident();
+
+        if let Some(stage) = self.stage.approved_mut() {
+            stage.locate(&mut self.core, *name)
+        } else {
+            Err(RoutingError::InvalidState)
+        }
+    }
+
     /// Returns the current BLS secret key share or `RoutingError::InvalidState` if we are not
     /// elder.
     pub fn secret_key_share(&self) -> Result<&bls::SecretKeyShare> {
@@ -438,7 +735,10 @@ impl Node {
         match event {
             BootstrappedTo { node } => self.handle_bootstrapped_to(node),
             BootstrapFailure => self.handle_bootstrap_failure(),
-            ConnectedTo { .. } => (),
+            ConnectedTo { peer } => match peer {
+                Peer::Client(_) => (),
+                Peer::Node(peer_addr) => self.core.challenge_peer(peer_addr),
+            },
             ConnectionFailure { peer, .. } => match peer {
                 Peer::Client(_) => (),
                 Peer::Node(peer_addr) => self.handle_connection_failure(peer_addr),
@@ -464,7 +764,7 @@ impl Node {
     fn handle_bootstrapped_to(&mut self, addr: SocketAddr) {
         match &mut self.stage {
             Stage::Bootstrapping(stage) => stage.send_bootstrap_request(&mut self.core, addr),
-            Stage::Joining(_) | Stage::Approved(_) => {
+            Stage::Genesis(_) | Stage::Joining(_) | Stage::Approved(_) => {
                 // A bootstrapped node doesn't need another bootstrap connection
                 self.core.transport.disconnect(addr);
             }
@@ -489,19 +789,67 @@ impl Node {
     }
 
     fn handle_new_message(&mut self, sender: SocketAddr, bytes: Bytes) {
+        let pub_id = self.core.peer_bindings.bound_id(&sender).copied();
+        match self.core.rate_limiter.admit(sender, pub_id.as_ref()) {
+            Admission::Accept => (),
+            Admission::Reject => {
+                trace!("Dropping message from {} - rate limit exceeded", sender);
+                return;
+            }
+            Admission::RejectAndEvict(pub_id) => {
+                self.handle_flooding(pub_id);
+                return;
+            }
+        }
+
         let msg = match Message::from_bytes(&bytes) {
             Ok(msg) => msg,
+            Err(CreateError::Unsupported { hash, protocol })
+                if protocol.version > PROTOCOL_VERSION =>
+            {
+                debug!(
+                    "Rejecting message {:?} from {} speaking newer protocol {}",
+                    hash, sender, protocol
+                );
+                self.core.send_direct_message(
+                    &sender,
+                    Variant::MessageRejected {
+                        hash,
+                        protocol_version: PROTOCOL_VERSION,
+                    },
+                );
+                return;
+            }
             Err(error) => {
                 debug!("Failed to deserialize message: {:?}", error);
                 return;
             }
         };
 
+        self.core.record_protocol(sender, *msg.protocol());
+
         if let Err(error) = self.try_handle_message(sender, msg) {
             debug!("Failed to handle message: {:?}", error);
         }
     }
 
+    // Proposes to evict a member of our section that keeps exceeding its rate limit.
+    fn handle_flooding(&mut self, pub_id: PublicId) {
+        let our_id = self.core.id();
+        if let Some(stage) = self
+            .stage
+            .approved_mut()
+            .filter(|stage| stage.is_our_elder(our_id))
+            .filter(|stage| stage.shared_state.our_members.contains(pub_id.name()))
+        {
+            warn!(
+                "Proposing to evict {} for flooding us with messages",
+                pub_id
+            );
+            stage.vote_for_eviction(*pub_id.name(), "message flooding");
+        }
+    }
+
     fn handle_unsent_message(&mut self, addr: SocketAddr, msg: Bytes, msg_token: Token) {
         match self.core.handle_unsent_message(addr, msg, msg_token) {
             PeerStatus::Normal => (),
@@ -520,9 +868,22 @@ impl Node {
         }
 
         match &mut self.stage {
+            Stage::Genesis(stage) => {
+                if let Some(params) = stage.handle_timeout(&mut self.core, token) {
+                    if let Err(error) = self.complete_genesis(params) {
+                        error!("Failed to complete genesis: {:?}", error);
+                    }
+                }
+            }
             Stage::Bootstrapping(stage) => stage.handle_timeout(&mut self.core, token),
             Stage::Joining(stage) => stage.handle_timeout(&mut self.core, token),
-            Stage::Approved(stage) => stage.handle_timeout(&mut self.core, token),
+            Stage::Approved(stage) => {
+                if let Some(snapshot) = stage.handle_timeout(&mut self.core, token) {
+                    if let Err(error) = self.recover(snapshot) {
+                        error!("Failed to start the recovery: {:?}", error);
+                    }
+                }
+            }
             Stage::Terminated => {}
         }
     }
@@ -531,6 +892,8 @@ impl Node {
         if let Stage::Approved(stage) = &mut self.stage {
             stage.handle_peer_lost(&self.core, peer_addr);
         }
+
+        self.core.peer_bindings.remove(&peer_addr);
     }
 
     ////////////////////////////////////////////////////////////////////////////
@@ -583,12 +946,15 @@ impl Node {
     fn relay_message(&mut self, msg: &Message) -> Result<()> {
         match &mut self.stage {
             Stage::Approved(stage) => stage.relay_message(&mut self.core, msg),
-            Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => Ok(()),
+            Stage::Genesis(_) | Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => {
+                Ok(())
+            }
         }
     }
 
     fn decide_message_status(&self, msg: &Message) -> Result<MessageStatus> {
         match &self.stage {
+            Stage::Genesis(stage) => stage.decide_message_status(msg),
             Stage::Bootstrapping(stage) => stage.decide_message_status(msg),
             Stage::Joining(stage) => stage.decide_message_status(msg),
             Stage::Approved(stage) => stage.decide_message_status(self.core.id(), msg),
@@ -599,6 +965,10 @@ impl Node {
     fn handle_message(&mut self, sender: SocketAddr, msg: Message) -> Result<()> {
         if let Stage::Approved(stage) = &mut self.stage {
             stage.update_section_knowledge(self.core.name(), &msg);
+
+            if let Ok(pub_id) = msg.src().as_node() {
+                stage.handle_peer_reachable(pub_id.name());
+            }
         }
 
         self.core.msg_queue.push_back(msg.into_queued(Some(sender)));
@@ -620,13 +990,68 @@ impl Node {
     fn dispatch_message(&mut self, sender: Option<SocketAddr>, msg: Message) -> Result<()> {
         trace!("Got {:?}", msg);
 
+        // The address binding handshake is the same in every stage.
+        match msg.variant() {
+            Variant::AddressChallenge { nonce, network_id } => {
+                let sender = sender.ok_or(RoutingError::InvalidSource)?;
+                self.core.check_network_id(&sender, *network_id)?;
+                let variant = Variant::AddressChallengeResponse {
+                    nonce: *nonce,
+                    network_id: self.core.network_id,
+                };
+                self.core.send_direct_message(&sender, variant);
+                return Ok(());
+            }
+            Variant::AddressChallengeResponse { nonce, network_id } => {
+                let p2p_node = msg.src().to_sender_node(sender)?;
+                self.core
+                    .check_network_id(p2p_node.peer_addr(), *network_id)?;
+                return self.handle_address_challenge_response(p2p_node, *nonce);
+            }
+            Variant::MessageRejected {
+                hash,
+                protocol_version,
+            } => {
+                let sender = sender.ok_or(RoutingError::InvalidSource)?;
+                warn!(
+                    "Message {:?} rejected by {} speaking protocol version {}",
+                    hash, sender, protocol_version
+                );
+                return Ok(());
+            }
+            _ => (),
+        }
+
         match &mut self.stage {
+            Stage::Genesis(stage) => {
+                let params = match msg.variant() {
+                    Variant::GenesisDkgMessage { message, .. } => {
+                        stage.handle_dkg_message(&mut self.core, message, *msg.src().as_node()?)?
+                    }
+                    Variant::GenesisSignatureShares {
+                        public_key,
+                        shares,
+                        recovery_signature,
+                    } => stage.handle_signature_shares(
+                        *public_key,
+                        shares.clone(),
+                        *recovery_signature,
+                        *msg.src().as_node()?,
+                    )?,
+                    _ => unreachable!(),
+                };
+
+                if let Some(params) = params {
+                    self.complete_genesis(params)?;
+                }
+            }
             Stage::Bootstrapping(stage) => match msg.variant() {
                 Variant::BootstrapResponse(response) => {
                     if let Some(params) = stage.handle_bootstrap_response(
                         &mut self.core,
                         msg.src().to_sender_node(sender)?,
                         response.clone(),
+                        msg.protocol(),
                     )? {
                         self.join(params);
                     }
@@ -637,11 +1062,13 @@ impl Node {
                 Variant::BootstrapResponse(BootstrapResponse::Join {
                     elders_info,
                     section_key,
+                    network_id,
                 }) => stage.handle_bootstrap_response(
                     &mut self.core,
                     msg.src().to_sender_node(sender)?,
                     elders_info.clone(),
                     *section_key,
+                    *network_id,
                 )?,
                 Variant::NodeApproval(genesis_prefix_info) => {
                     let section_key = *msg.src().as_section_key()?;
@@ -680,10 +1107,12 @@ impl Node {
                         *msg.src().as_node()?,
                     )?;
                 }
-                Variant::BootstrapRequest(name) => stage.handle_bootstrap_request(
+                Variant::BootstrapRequest { name, network_id } => stage.handle_bootstrap_request(
                     &mut self.core,
                     msg.src().to_sender_node(sender)?,
                     *name,
+                    *network_id,
+                    msg.protocol(),
                 ),
                 Variant::JoinRequest(join_request) => stage.handle_join_request(
                     &mut self.core,
@@ -718,6 +1147,56 @@ impl Node {
                         dst: *msg.dst(),
                     });
                 }
+                Variant::AckedUserMessage(content) => {
+                    stage.handle_acked_user_message(&mut self.core, &msg, content.clone())?
+                }
+                Variant::Ack(msg_hash) => {
+                    stage.handle_ack(&self.core, msg.src().src_location(), *msg_hash)
+                }
+                Variant::OrderedUserMessage {
+                    channel,
+                    epoch,
+                    seq,
+                    resend,
+                    content,
+                } => stage.handle_ordered_user_message(
+                    &self.core,
+                    &msg,
+                    *msg.src().as_node()?.name(),
+                    *channel,
+                    *epoch,
+                    *seq,
+                    *resend,
+                    content.clone(),
+                ),
+                Variant::OrderedResendRequest {
+                    channel,
+                    epoch,
+                    from,
+                    to,
+                    attempt,
+                } => stage.handle_ordered_resend_request(
+                    &mut self.core,
+                    *msg.src().as_node()?.name(),
+                    *channel,
+                    *epoch,
+                    *from,
+                    *to,
+                    *attempt,
+                )?,
+                Variant::UserMessageChunk {
+                    hash,
+                    index,
+                    count,
+                    data,
+                } => stage.handle_user_message_chunk(
+                    &self.core,
+                    &msg,
+                    *hash,
+                    *index,
+                    *count,
+                    data.clone(),
+                ),
                 Variant::BouncedUntrustedMessage(message) => stage
                     .handle_bounced_untrusted_message(
                         &mut self.core,
@@ -759,9 +1238,72 @@ impl Node {
                         *msg.src().as_node()?,
                     )?;
                 }
-                Variant::NodeApproval(_) | Variant::BootstrapResponse(_) | Variant::Ping => {
-                    unreachable!()
+                Variant::RecoveryCertificate(certificate) => {
+                    stage.handle_recovery_certificate(&mut self.core, *certificate.clone())?
                 }
+                Variant::DecryptionShare {
+                    ciphertext,
+                    index,
+                    share,
+                } => stage.handle_decryption_share(
+                    &mut self.core,
+                    *ciphertext.clone(),
+                    *index,
+                    share.clone(),
+                ),
+                Variant::SkipLinkShare {
+                    index,
+                    key,
+                    proof_share,
+                } => stage.handle_skip_link_share(*index, *key, proof_share.clone()),
+                Variant::SectionProofRequest { known_key } => stage.handle_section_proof_request(
+                    &mut self.core,
+                    msg.src().to_sender_node(sender)?,
+                    *known_key,
+                ),
+                Variant::LocateRequest => {
+                    let requester = *msg.src().as_node()?.name();
+                    stage.handle_locate_request(&mut self.core, requester)?
+                }
+                Variant::LocateResponse(located) => {
+                    stage.handle_locate_response(&self.core, msg.src().as_node()?, *located.clone())
+                }
+                Variant::MembershipCertificateRequest => stage
+                    .handle_membership_certificate_request(
+                        &mut self.core,
+                        msg.src().to_sender_node(sender)?,
+                    )?,
+                Variant::MembershipCertificateShare {
+                    statement,
+                    proof_share,
+                } => stage.handle_membership_certificate_share(
+                    &self.core,
+                    statement.clone(),
+                    proof_share.clone(),
+                ),
+                Variant::GenesisDkgMessage { participants, .. } => {
+                    // Another recovery elder started the recovery of our section before us. Join
+                    // it and handle the message in the genesis stage. Otherwise this is a message
+                    // we sent to ourselves during the genesis, still queued after it completed.
+                    if let Some(snapshot) = stage
+                        .recovery_snapshot_for(self.core.id(), participants)
+                        .cloned()
+                    {
+                        self.recover(snapshot)?;
+                        self.core
+                            .msg_queue
+                            .push_front(msg.clone().into_queued(sender));
+                    }
+                }
+                Variant::NodeApproval(_)
+                | Variant::BootstrapResponse(_)
+                | Variant::Ping
+                | Variant::AddressChallenge { .. }
+                | Variant::AddressChallengeResponse { .. }
+                | Variant::MessageRejected { .. } => unreachable!(),
+                // Messages we sent to ourselves during the genesis might still be queued after it
+                // completed.
+                Variant::GenesisSignatureShares { .. } => (),
             },
             Stage::Terminated => unreachable!(),
         }
@@ -769,17 +1311,42 @@ impl Node {
         Ok(())
     }
 
+    fn handle_address_challenge_response(&mut self, p2p_node: P2pNode, nonce: u64) -> Result<()> {
+        if !self.core.peer_bindings.handle_response(
+            *p2p_node.peer_addr(),
+            *p2p_node.public_id(),
+            nonce,
+        ) {
+            debug!(
+                "Ignoring unexpected AddressChallengeResponse from {}",
+                p2p_node
+            );
+            return Ok(());
+        }
+
+        trace!("Verified address of {}", p2p_node);
+
+        if let Stage::Approved(stage) = &mut self.stage {
+            stage.handle_peer_bound(&mut self.core, p2p_node);
+        }
+
+        Ok(())
+    }
+
     fn handle_untrusted_message(&mut self, sender: SocketAddr, msg: Message) -> Result<()> {
         match &self.stage {
             Stage::Approved(stage) => {
                 stage.handle_untrusted_message(&mut self.core, Some(sender), msg)
             }
-            Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => unreachable!(),
+            Stage::Genesis(_) | Stage::Bootstrapping(_) | Stage::Joining(_) | Stage::Terminated => {
+                unreachable!()
+            }
         }
     }
 
     fn handle_unknown_message(&mut self, sender: SocketAddr, msg: Message) -> Result<()> {
         match &mut self.stage {
+            Stage::Genesis(stage) => stage.handle_unknown_message(sender, msg),
             Stage::Bootstrapping(stage) => stage.handle_unknown_message(sender, msg),
             Stage::Joining(stage) => stage.handle_unknown_message(sender, msg),
             Stage::Approved(stage) => {
@@ -795,6 +1362,58 @@ impl Node {
     // Transitions
     ////////////////////////////////////////////////////////////////////////////
 
+    // Transition from Genesis to Approved
+    fn complete_genesis(&mut self, params: GenesisParams) -> Result<()> {
+        let GenesisParams {
+            shared_state,
+            section_key_share,
+            parsec_version,
+            recovery,
+            msg_backlog,
+        } = params;
+
+        let stage = Approved::genesis(
+            &mut self.core,
+            shared_state,
+            parsec_version,
+            section_key_share,
+        )?;
+
+        if let Some(certificate) = recovery {
+            info!(
+                "{} Recovered our section as one of its new elders.",
+                self.core.name()
+            );
+            stage.send_recovery_certificate(&mut self.core, certificate);
+        } else {
+            info!(
+                "{} Started a new network as a genesis node.",
+                self.core.name()
+            );
+            self.core.send_event(Event::Connected(Connected::First));
+        }
+
+        self.stage = Stage::Approved(stage);
+        self.core.msg_queue.extend(msg_backlog);
+        self.core.send_event(Event::Promoted);
+
+        Ok(())
+    }
+
+    // Transition from Approved to Genesis to recover our section after losing its elders.
+    fn recover(&mut self, snapshot: Proven<RecoverySnapshot>) -> Result<()> {
+        info!(
+            "{} Recovering our section from snapshot {}.",
+            self.core.name(),
+            snapshot.value.index
+        );
+
+        let stage = Genesis::recover(&mut self.core, snapshot)?;
+        self.stage = Stage::Genesis(stage);
+
+        Ok(())
+    }
+
     // Transition from Bootstrapping to Joining
     fn join(&mut self, params: JoinParams) {
         let JoinParams {
@@ -861,6 +1480,7 @@ impl Node {
     fn set_log_ident(&self) -> log_utils::Guard {
         use std::fmt::Write;
         log_utils::set_ident(|buffer| match &self.stage {
+            Stage::Genesis(_) => write!(buffer, "{}(genesis) ", self.name()),
             Stage::Bootstrapping(_) => write!(buffer, "{}(?) ", self.name()),
             Stage::Joining(stage) => write!(
                 buffer,
@@ -921,9 +1541,10 @@ impl Node {
         delivery_group_size: usize,
         message: Message,
     ) -> Result<(), RoutingError> {
+        let priority = message.variant().priority();
         let message = message.to_bytes();
         self.core
-            .send_message_to_targets(dst_targets, delivery_group_size, message);
+            .send_message_to_targets(dst_targets, delivery_group_size, message, priority);
         Ok(())
     }
 
@@ -1051,6 +1672,14 @@ impl Node {
             .map(|stage| stage.shared_state.our_history.last_key())
     }
 
+    /// Returns the index of the latest recovery snapshot of our section, if any.
+    pub fn recovery_snapshot_index(&self) -> Option<u64> {
+        self.shared_state()?
+            .recovery_snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.value.index)
+    }
+
     pub(crate) fn shared_state(&self) -> Option<&SharedState> {
         self.stage.approved().map(|stage| &stage.shared_state)
     }
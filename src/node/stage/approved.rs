@@ -8,42 +8,105 @@
 
 use crate::{
     consensus::{
-        self, threshold_count, AccumulatingEvent, ConsensusEngine, DkgResult, DkgVoter,
-        GenesisPrefixInfo, ParsecRequest, ParsecResponse, Proof, Proven,
+        self, threshold_count, AccumulatingEvent, AccumulationError, ConsensusEngine,
+        DecryptionAccumulator, DkgKey, DkgResult, DkgVoter, GenesisPrefixInfo, ParsecRequest,
+        ParsecResponse, Proof, ProofShare, Proven, SignatureAccumulator,
     },
     core::Core,
+    crypto::Digest256,
     delivery_group,
     error::{Result, RoutingError},
     event::Event,
     id::{P2pNode, PublicId},
     location::{DstLocation, SrcLocation},
     messages::{
-        self, AccumulatingMessage, BootstrapResponse, JoinRequest, Message, MessageAccumulator,
-        MessageHash, MessageStatus, PlainMessage, Variant, VerifyStatus,
+        self, AccumulatingMessage, BootstrapResponse, ChunkAssembler, JoinRequest, Message,
+        MessageAccumulator, MessageHash, MessageStatus, OrderedInbox, OrderedOutbox, PlainMessage,
+        SrcAuthority, Variant, VerifyStatus,
     },
+    network_id::NetworkId,
+    notary::{self, NotaryBatch},
     pause::PausedState,
+    protocol::{Protocol, COMPACT_PROOF_VERSION, PROTOCOL_VERSION},
     relocation::{RelocateDetails, SignedRelocateDetails},
     rng::MainRng,
     section::{
-        member_info, EldersInfo, MemberState, NeighbourEldersRemoved, SectionKeyShare,
-        SectionKeysProvider, SectionUpdateBarrier, SectionUpdateDetails, SharedState, MIN_AGE,
+        member_info, quorum_count, skip_link_source, BeaconInput, EldersInfo, LocatedSection,
+        MemberState, MembershipCertificate, MembershipStatement, NeighbourEldersRemoved,
+        RecoveryCertificate, RecoverySnapshot, SectionKeyShare, SectionKeysProvider,
+        SectionUpdateBarrier, SectionUpdateDetails, SharedState, TrustStatus, Upgrade, MIN_AGE,
     },
     time::Duration,
+    transport::Priority,
 };
 use bls_dkg::key_gen::message::Message as DkgMessage;
 use bytes::Bytes;
 use crossbeam_channel::Sender;
 use itertools::Itertools;
+use lru_time_cache::LruCache;
 use rand::Rng;
 use serde::Serialize;
-use std::{cmp::Ordering, collections::BTreeSet, iter, net::SocketAddr};
+use std::{
+    cmp::{self, Ordering},
+    collections::{BTreeSet, HashMap},
+    iter,
+    net::SocketAddr,
+};
 use xor_name::{Prefix, XorName};
 
 // Send our knowledge in a similar speed as GOSSIP_TIMEOUT
 const KNOWLEDGE_TIMEOUT: Duration = Duration::from_secs(2);
 
 // Interval to progress DKG timed phase
-const DKG_PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+pub(super) const DKG_PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+// How long to wait for a DKG to complete before trying again without the participants that didn't
+// contribute to it.
+const DKG_DEADLINE: Duration = Duration::from_secs(5 * 60);
+
+// Interval between the rounds of our section randomness beacon.
+const BEACON_INTERVAL: Duration = Duration::from_secs(60);
+
+// Interval at which the elders advance the notary time and put the pending notarised hashes into
+// a new batch.
+const NOTARY_BATCH_INTERVAL: Duration = Duration::from_secs(notary::NOTARY_TIMESTAMP_GRANULARITY);
+
+// How long to keep a join request whose sender hasn't yet proven to own its address.
+const PENDING_JOIN_REQUEST_EXPIRY: Duration = Duration::from_secs(60);
+
+// How long to keep delivering messages to a light client that stopped sending us
+// `SectionProofRequest`s.
+const LIGHT_CLIENT_EXPIRY: Duration = Duration::from_secs(3 * 60);
+
+// How long to wait for the response to a `LocateRequest` before ignoring it.
+const LOCATE_REQUEST_EXPIRY: Duration = Duration::from_secs(60);
+
+// Interval at which we check that our membership certificate matches our current membership and
+// ask our elders for a new one if not.
+const MEMBERSHIP_CERTIFICATE_INTERVAL: Duration = Duration::from_secs(10);
+
+// Upper bound of the interval at which we ask our elders for a membership certificate while they
+// don't provide one.
+const MAX_MEMBERSHIP_CERTIFICATE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// Interval at which the elders take a new recovery snapshot of our section.
+const RECOVERY_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+// How long to wait after losing contact with a quorum of our elders before starting the recovery
+// of our section, to give the elders a chance to come back.
+const RECOVERY_DELAY: Duration = Duration::from_secs(60);
+
+// Interval at which the elders refresh the section key even if the elders didn't change, to limit
+// how long a leaked key share stays useful.
+const KEY_REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Interval at which we check the incoming ordered channels for missing messages. A gap found by two
+// consecutive checks is requested to be filled by the source.
+pub(crate) const ORDERED_GAP_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long to wait for the acknowledgement of a message sent with `Node::send_message_with_ack`
+/// before raising `Event::DeliveryTimeout`.
+pub const ACK_TIMEOUT: Duration = Duration::from_secs(60);
 
 // The approved stage - node is a full member of a section and is performing its duties according
 // to its persona (infant, adult or elder).
@@ -61,6 +124,52 @@ pub struct Approved {
     members_changed: bool,
     // Voter for DKG
     dkg_voter: DkgVoter,
+    // Join requests waiting for the sender to respond to our `AddressChallenge`.
+    pending_join_requests: LruCache<SocketAddr, (P2pNode, JoinRequest)>,
+    // Token for the timer to take the next recovery snapshot.
+    recovery_snapshot_timer_token: u64,
+    // Elders of our section we lost connection to.
+    lost_elders: BTreeSet<XorName>,
+    // Token for the timer to start the recovery of our section after losing its elders.
+    recovery_timer_token: Option<u64>,
+    // Token for the timer to refresh our section key.
+    key_refresh_timer_token: u64,
+    // Accumulator for the decryption shares of the ciphertexts our section agreed to decrypt.
+    decryption_accumulator: DecryptionAccumulator,
+    // Accumulator for the signature shares of the skip links to our new section keys.
+    skip_link_accumulator: SignatureAccumulator<(u64, bls::PublicKey)>,
+    // Token for the timer to start the next round of our section randomness beacon.
+    beacon_timer_token: u64,
+    // Token for the timer to start the next notary batch.
+    notary_timer_token: u64,
+    // Light clients following our section, to deliver the messages addressed to them.
+    light_clients: LruCache<XorName, P2pNode>,
+    // Names we sent a `LocateRequest` for and haven't got the response yet.
+    pending_locates: LruCache<XorName, ()>,
+    // Token for the timer to refresh our membership certificate.
+    membership_timer_token: u64,
+    // Accumulator for the signature shares of our membership statement.
+    membership_accumulator: SignatureAccumulator<MembershipStatement>,
+    // Our latest membership certificate.
+    membership_certificate: Option<MembershipCertificate>,
+    // Current interval of our membership certificate requests.
+    membership_request_interval: Duration,
+    // Reassembles the user messages received in chunks.
+    chunk_assembler: ChunkAssembler,
+    // Protocol version we voted to be running, to not report it twice.
+    reported_version: Option<u16>,
+    // Protocol version we were asked to restart into, to not ask twice.
+    restart_version: Option<u16>,
+    // Index of our next `RequestMoreNodes` vote, to not vote for the same request twice.
+    next_more_nodes_request: u64,
+    // Messages we sent and wait to be acknowledged by their destination.
+    pending_acks: HashMap<MessageHash, PendingAck>,
+    // Ordered channels from our node.
+    ordered_outbox: OrderedOutbox,
+    // Ordered channels to our node or section.
+    ordered_inbox: OrderedInbox,
+    // Token for the timer to check the incoming ordered channels for missing messages.
+    ordered_gap_timer_token: u64,
 }
 
 impl Approved {
@@ -85,7 +194,24 @@ impl Approved {
             secret_key_share,
         };
 
-        Self::new(core, shared_state, 0, Some(section_key_share))
+        Self::genesis(core, shared_state, 0, section_key_share)
+    }
+
+    // Create the approved stage for a node that started the network, either alone or as one of
+    // the participants of the genesis ceremony, or that recovered its section.
+    pub fn genesis(
+        core: &mut Core,
+        shared_state: SharedState,
+        parsec_version: u64,
+        section_key_share: SectionKeyShare,
+    ) -> Result<Self> {
+        if core.network_id.is_none() {
+            let network_id = NetworkId::from_genesis_key(shared_state.our_history.first_key());
+            info!("Starting network {}", network_id);
+            core.network_id = Some(network_id);
+        }
+
+        Self::new(core, shared_state, parsec_version, Some(section_key_share))
     }
 
     // Create the approved stage for a regular node.
@@ -106,6 +232,13 @@ impl Approved {
 
         let section_keys_provider = SectionKeysProvider::new(section_key_share);
         let gossip_timer_token = core.timer.schedule(KNOWLEDGE_TIMEOUT);
+        let recovery_snapshot_timer_token = core.timer.schedule(RECOVERY_SNAPSHOT_INTERVAL);
+        let key_refresh_timer_token = core.timer.schedule(KEY_REFRESH_INTERVAL);
+        let beacon_timer_token = core.timer.schedule(BEACON_INTERVAL);
+        let notary_timer_token = core.timer.schedule(NOTARY_BATCH_INTERVAL);
+        let membership_timer_token = core.timer.schedule(MEMBERSHIP_CERTIFICATE_INTERVAL);
+        let ordered_gap_timer_token = core.timer.schedule(ORDERED_GAP_CHECK_INTERVAL);
+        core.active_version = shared_state.upgrade.active_version();
 
         Ok(Self {
             consensus_engine,
@@ -117,18 +250,44 @@ impl Approved {
             churn_in_progress: false,
             members_changed: false,
             dkg_voter: Default::default(),
+            pending_join_requests: LruCache::with_expiry_duration(PENDING_JOIN_REQUEST_EXPIRY),
+            recovery_snapshot_timer_token,
+            lost_elders: BTreeSet::new(),
+            recovery_timer_token: None,
+            key_refresh_timer_token,
+            decryption_accumulator: Default::default(),
+            skip_link_accumulator: Default::default(),
+            beacon_timer_token,
+            notary_timer_token,
+            light_clients: LruCache::with_expiry_duration(LIGHT_CLIENT_EXPIRY),
+            pending_locates: LruCache::with_expiry_duration(LOCATE_REQUEST_EXPIRY),
+            membership_timer_token,
+            membership_accumulator: Default::default(),
+            membership_certificate: None,
+            membership_request_interval: MEMBERSHIP_CERTIFICATE_INTERVAL,
+            chunk_assembler: Default::default(),
+            reported_version: None,
+            restart_version: None,
+            next_more_nodes_request: 0,
+            pending_acks: HashMap::new(),
+            ordered_outbox: Default::default(),
+            ordered_inbox: Default::default(),
+            ordered_gap_timer_token,
         })
     }
 
     pub fn pause(self, core: Core) -> PausedState {
         PausedState {
             network_params: core.network_params,
+            network_id: core.network_id,
             consensus_engine: self.consensus_engine,
             shared_state: self.shared_state,
             section_keys_provider: self.section_keys_provider,
             full_id: core.full_id,
             msg_filter: core.msg_filter,
             msg_queue: core.msg_queue,
+            peer_bindings: core.peer_bindings,
+            rate_limiter: core.rate_limiter,
             transport: core.transport,
             transport_rx: None,
             msg_accumulator: self.message_accumulator,
@@ -142,15 +301,19 @@ impl Approved {
         timer_tx: Sender<u64>,
         user_event_tx: Sender<Event>,
     ) -> (Self, Core) {
-        let core = Core::resume(
+        let mut core = Core::resume(
             state.network_params,
+            state.network_id,
             state.full_id,
             state.transport,
             state.msg_filter,
             state.msg_queue,
+            state.peer_bindings,
+            state.rate_limiter,
             timer_tx,
             user_event_tx,
         );
+        core.active_version = state.shared_state.upgrade.active_version();
 
         let is_self_elder = state
             .shared_state
@@ -175,6 +338,29 @@ impl Approved {
             churn_in_progress: false,
             members_changed: false,
             dkg_voter: Default::default(),
+            pending_join_requests: LruCache::with_expiry_duration(PENDING_JOIN_REQUEST_EXPIRY),
+            recovery_snapshot_timer_token: core.timer.schedule(RECOVERY_SNAPSHOT_INTERVAL),
+            lost_elders: BTreeSet::new(),
+            recovery_timer_token: None,
+            key_refresh_timer_token: core.timer.schedule(KEY_REFRESH_INTERVAL),
+            decryption_accumulator: Default::default(),
+            skip_link_accumulator: Default::default(),
+            beacon_timer_token: core.timer.schedule(BEACON_INTERVAL),
+            notary_timer_token: core.timer.schedule(NOTARY_BATCH_INTERVAL),
+            light_clients: LruCache::with_expiry_duration(LIGHT_CLIENT_EXPIRY),
+            pending_locates: LruCache::with_expiry_duration(LOCATE_REQUEST_EXPIRY),
+            membership_timer_token: core.timer.schedule(MEMBERSHIP_CERTIFICATE_INTERVAL),
+            membership_accumulator: Default::default(),
+            membership_certificate: None,
+            membership_request_interval: MEMBERSHIP_CERTIFICATE_INTERVAL,
+            chunk_assembler: Default::default(),
+            reported_version: None,
+            restart_version: None,
+            next_more_nodes_request: 0,
+            pending_acks: HashMap::new(),
+            ordered_outbox: Default::default(),
+            ordered_inbox: Default::default(),
+            ordered_gap_timer_token: core.timer.schedule(ORDERED_GAP_CHECK_INTERVAL),
         };
 
         (stage, core)
@@ -216,20 +402,47 @@ impl Approved {
     }
 
     pub fn handle_peer_lost(&mut self, core: &Core, peer_addr: SocketAddr) {
-        let name = if let Some(node) = self.shared_state.find_p2p_node_from_addr(&peer_addr) {
-            debug!("Lost known peer {}", node);
-            *node.name()
+        // Only blame the identity the peer proved to own at this address. The address we know for
+        // a member might be spoofed or already reused by someone else.
+        let pub_id = if let Some(pub_id) = core.peer_bindings.bound_id(&peer_addr) {
+            pub_id
         } else {
-            trace!("Lost unknown peer {}", peer_addr);
+            trace!("Lost unverified peer {}", peer_addr);
             return;
         };
 
+        let name = match self.shared_state.find_p2p_node_from_addr(&peer_addr) {
+            Some(node) if node.public_id() == pub_id => {
+                debug!("Lost known peer {}", node);
+                *node.name()
+            }
+            _ => {
+                trace!("Lost unknown peer {} at {}", pub_id, peer_addr);
+                return;
+            }
+        };
+
         if self.is_our_elder(core.id()) && self.shared_state.our_members.contains(&name) {
             self.vote_for_event(AccumulatingEvent::Offline(name))
+        } else if self.shared_state.our_info().elders.contains_key(&name) {
+            self.handle_elder_lost(core, name)
+        }
+    }
+
+    // Handle a message from the given peer, which proves it is still reachable.
+    pub fn handle_peer_reachable(&mut self, name: &XorName) {
+        if self.lost_elders.remove(name) && !self.has_lost_elder_quorum() {
+            self.recovery_timer_token = None;
         }
     }
 
-    pub fn handle_timeout(&mut self, core: &mut Core, token: u64) {
+    // Returns the snapshot to recover our section from when the timer to start the recovery
+    // expires.
+    pub fn handle_timeout(
+        &mut self,
+        core: &mut Core,
+        token: u64,
+    ) -> Option<Proven<RecoverySnapshot>> {
         if self.gossip_timer_token == token {
             if self.is_our_elder(core.id()) {
                 self.gossip_timer_token =
@@ -244,6 +457,85 @@ impl Approved {
             self.dkg_voter
                 .set_timer_token(core.timer.schedule(DKG_PROGRESS_INTERVAL));
             self.progress_dkg(core);
+        } else if self.recovery_snapshot_timer_token == token {
+            self.recovery_snapshot_timer_token = core.timer.schedule(RECOVERY_SNAPSHOT_INTERVAL);
+            self.vote_for_recovery_snapshot(core);
+        } else if self.recovery_timer_token == Some(token) {
+            self.recovery_timer_token = None;
+            return self.recovery_snapshot(core.id()).cloned();
+        } else if self.key_refresh_timer_token == token {
+            self.key_refresh_timer_token = core.timer.schedule(KEY_REFRESH_INTERVAL);
+            self.vote_for_key_refresh(core.id());
+        } else if self.beacon_timer_token == token {
+            self.beacon_timer_token = core.timer.schedule(BEACON_INTERVAL);
+            self.vote_for_beacon_round(core);
+        } else if self.notary_timer_token == token {
+            self.notary_timer_token = core.timer.schedule(NOTARY_BATCH_INTERVAL);
+            self.vote_for_notary_tick(core);
+        } else if self.membership_timer_token == token {
+            self.request_membership_certificate(core);
+            self.membership_timer_token = core.timer.schedule(self.membership_request_interval);
+        } else if self.ordered_gap_timer_token == token {
+            self.ordered_gap_timer_token = core.timer.schedule(ORDERED_GAP_CHECK_INTERVAL);
+            self.check_ordered_gaps(core);
+        } else {
+            self.handle_ack_timeout(core, token);
+        }
+
+        None
+    }
+
+    fn handle_ack_timeout(&mut self, core: &Core, token: u64) {
+        let msg_hash = if let Some((msg_hash, _)) = self
+            .pending_acks
+            .iter()
+            .find(|(_, pending)| pending.timer_token == token)
+        {
+            *msg_hash
+        } else {
+            return;
+        };
+
+        if let Some(pending) = self.pending_acks.remove(&msg_hash) {
+            debug!("No ack for message {:?} to {:?}", msg_hash, pending.dst);
+            core.send_event(Event::DeliveryTimeout {
+                dst: pending.dst,
+                msg_hash,
+            });
+        }
+    }
+
+    fn check_ordered_gaps(&mut self, core: &mut Core) {
+        let (gaps, skipped) = self.ordered_inbox.check_gaps();
+
+        for (src, dst, content) in skipped {
+            debug!(
+                "Giving up on missing ordered messages from {} to {:?}",
+                src, dst
+            );
+            core.send_event(Event::MessageReceived {
+                content,
+                src: SrcLocation::Node(src),
+                dst,
+            });
+        }
+
+        for gap in gaps {
+            trace!("Requesting missing ordered messages {:?}", gap);
+
+            let variant = Variant::OrderedResendRequest {
+                channel: gap.dst,
+                epoch: gap.epoch,
+                from: gap.from,
+                to: gap.to,
+                attempt: gap.attempt,
+            };
+            let src = SrcLocation::Node(*core.name());
+            if let Err(error) =
+                self.send_routing_message(core, src, DstLocation::Node(gap.src), variant, None)
+            {
+                debug!("Failed to request missing ordered messages: {:?}", error);
+            }
         }
     }
 
@@ -252,6 +544,10 @@ impl Approved {
 
         for (dkg_key, dkg_result) in completed {
             debug!("Completed DKG {:?}", dkg_key);
+            core.send_event(Event::DkgCompleted {
+                elders: dkg_key.0.iter().map(|pub_id| *pub_id.name()).collect(),
+                key: dkg_result.public_key_set.public_key(),
+            });
             self.notify_old_elders(
                 core,
                 &dkg_key.0,
@@ -284,6 +580,46 @@ impl Approved {
         }
 
         self.check_dkg(core);
+
+        for (dkg_key, non_contributors) in self.dkg_voter.take_expired(DKG_DEADLINE) {
+            self.handle_dkg_expired(core, dkg_key, non_contributors);
+        }
+    }
+
+    fn handle_dkg_expired(
+        &mut self,
+        core: &mut Core,
+        dkg_key: DkgKey,
+        non_contributors: BTreeSet<PublicId>,
+    ) {
+        warn!(
+            "DKG {:?} didn't complete in time (non-contributors: {:?})",
+            dkg_key, non_contributors
+        );
+
+        core.send_event(Event::DkgFailed {
+            elders: dkg_key.0.iter().map(|pub_id| *pub_id.name()).collect(),
+            non_contributors: non_contributors
+                .iter()
+                .map(|pub_id| *pub_id.name())
+                .collect(),
+        });
+
+        // Without knowing who to blame, all we can do is to keep waiting.
+        if non_contributors.is_empty()
+            || dkg_key.1 != self.shared_state.our_history.last_key_index()
+            || !self.is_our_elder(core.id())
+        {
+            return;
+        }
+
+        for non_contributor in non_contributors {
+            self.vote_for_event(AccumulatingEvent::DkgFailure {
+                participants: dkg_key.0.clone(),
+                section_key_index: dkg_key.1,
+                non_contributor,
+            });
+        }
     }
 
     pub fn finish_handle_input(&mut self, core: &mut Core) {
@@ -309,6 +645,85 @@ impl Approved {
         self.vote_for_event(AccumulatingEvent::User(event));
     }
 
+    /// Vote for admitting `count` additional members into our section, one vote per member. The
+    /// votes continue from the requests already agreed on or voted for by us, whichever is later.
+    pub fn vote_for_more_nodes(&mut self, count: usize) {
+        let start = self
+            .next_more_nodes_request
+            .max(self.shared_state.more_nodes_requests);
+        let end = start.saturating_add(count as u64);
+
+        for index in start..end {
+            self.vote_for_event(AccumulatingEvent::RequestMoreNodes(index));
+        }
+
+        self.next_more_nodes_request = end;
+    }
+
+    /// Vote for removing the member with the given name from our section. The reason is only
+    /// logged, so votes for the same member given for different reasons accumulate together.
+    pub fn vote_for_eviction(&mut self, name: XorName, reason: &str) {
+        info!("Voting to evict {} (reason: {})", name, reason);
+        self.vote_for_event(AccumulatingEvent::Evict(name));
+    }
+
+    /// Vote for replacing our section key with a new one generated by the current elders.
+    pub fn vote_for_key_refresh(&mut self, our_id: &PublicId) {
+        if self.section_keys_provider.key_share().is_ok() && self.is_our_elder(our_id) {
+            let index = self.shared_state.our_history.last_key_index();
+            self.vote_for_event(AccumulatingEvent::RefreshKey(index));
+        }
+    }
+
+    /// Vote for decrypting the given ciphertext encrypted with our current section key.
+    pub fn vote_for_decryption(&mut self, ciphertext: bls::Ciphertext) {
+        self.vote_for_event(AccumulatingEvent::Decrypt {
+            key_index: self.shared_state.our_history.last_key_index(),
+            ciphertext: Box::new(ciphertext),
+        });
+    }
+
+    /// Vote for notarising the given document hash.
+    pub fn vote_for_notarisation(&mut self, hash: Digest256) {
+        self.vote_for_event(AccumulatingEvent::Notarise(hash));
+    }
+
+    /// Vote for upgrading our section to the given protocol version.
+    pub fn vote_for_upgrade(&mut self, version: u16, allow_quorum_loss: bool) {
+        self.vote_for_event(AccumulatingEvent::ProposeUpgrade {
+            version,
+            allow_quorum_loss,
+        });
+    }
+
+    // Returns our membership certificate, if it matches our current membership.
+    pub fn membership_certificate(&self, our_name: &XorName) -> Option<&MembershipCertificate> {
+        let statement = self.shared_state.membership_statement(our_name)?;
+        self.membership_certificate
+            .as_ref()
+            .filter(|certificate| certificate.statement.value == statement)
+    }
+
+    // Returns the section `name` belongs to if we know it. Otherwise asks that section and returns
+    // `None`; the answer is raised as `Event::Located`.
+    pub fn locate(&mut self, core: &mut Core, name: XorName) -> Result<Option<LocatedSection>> {
+        if let Some(located) = self.shared_state.locate(&name) {
+            return Ok(Some(located));
+        }
+
+        if self.pending_locates.insert(name, ()).is_none() {
+            self.send_routing_message(
+                core,
+                SrcLocation::Node(*core.name()),
+                DstLocation::Section(name),
+                Variant::LocateRequest,
+                None,
+            )?;
+        }
+
+        Ok(None)
+    }
+
     /// Is the node with the given id an elder in our section?
     pub fn is_our_elder(&self, id: &PublicId) -> bool {
         self.shared_state
@@ -323,6 +738,137 @@ impl Approved {
         self.section_keys_provider.key_share().ok()
     }
 
+    // Returns the snapshot to recover from if the participants of the genesis DKG we received are
+    // the recovery elders designated by it.
+    pub fn recovery_snapshot_for(
+        &self,
+        our_id: &PublicId,
+        participants: &BTreeSet<PublicId>,
+    ) -> Option<&Proven<RecoverySnapshot>> {
+        let snapshot = self.recovery_snapshot(our_id)?;
+        let recovery_elders: BTreeSet<_> = snapshot
+            .value
+            .recovery_elders()
+            .values()
+            .map(P2pNode::public_id)
+            .copied()
+            .collect();
+
+        if recovery_elders == *participants {
+            Some(snapshot)
+        } else {
+            None
+        }
+    }
+
+    // Returns the snapshot to recover our section from, if we lost contact with a quorum of our
+    // elders and the snapshot designates us as one of the recovery elders.
+    fn recovery_snapshot(&self, our_id: &PublicId) -> Option<&Proven<RecoverySnapshot>> {
+        if self.is_our_elder(our_id) || !self.has_lost_elder_quorum() {
+            return None;
+        }
+
+        let snapshot = if let Some(snapshot) = &self.shared_state.recovery_snapshot {
+            snapshot
+        } else {
+            warn!("Lost our elders but there is no snapshot to recover from");
+            return None;
+        };
+
+        // A snapshot taken before the elders last changed would recover a different section.
+        if snapshot.value.elders_info != *self.shared_state.our_info() {
+            warn!("Lost our elders but the latest snapshot is outdated");
+            return None;
+        }
+
+        if snapshot
+            .value
+            .recovery_elders()
+            .get(our_id.name())
+            .map(P2pNode::public_id)
+            == Some(our_id)
+        {
+            Some(snapshot)
+        } else {
+            None
+        }
+    }
+
+    fn handle_elder_lost(&mut self, core: &Core, name: XorName) {
+        let _ = self.lost_elders.insert(name);
+
+        if self.recovery_timer_token.is_none() && self.has_lost_elder_quorum() {
+            warn!("Lost contact with a quorum of our elders");
+            self.recovery_timer_token = Some(core.timer.schedule(RECOVERY_DELAY));
+        }
+    }
+
+    fn has_lost_elder_quorum(&self) -> bool {
+        let elders = &self.shared_state.our_info().elders;
+        let reachable = elders
+            .keys()
+            .filter(|name| !self.lost_elders.contains(name))
+            .count();
+        reachable < quorum_count(elders.len())
+    }
+
+    fn vote_for_recovery_snapshot(&mut self, core: &Core) {
+        if self.section_keys_provider.key_share().is_ok() && self.is_our_elder(core.id()) {
+            let index = self.next_recovery_snapshot_index();
+            self.vote_for_event(AccumulatingEvent::StartRecoverySnapshot(index));
+        }
+    }
+
+    fn vote_for_beacon_round(&mut self, core: &Core) {
+        if self.section_keys_provider.key_share().is_ok() && self.is_our_elder(core.id()) {
+            let input = self.shared_state.beacon.next_input();
+            self.vote_for_event(AccumulatingEvent::BeaconRound(input));
+        }
+    }
+
+    fn vote_for_notary_tick(&mut self, core: &Core) {
+        let now = notary::timestamp_now();
+        let timestamp = match self.shared_state.notary.next_timestamp(now) {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+
+        if self.section_keys_provider.key_share().is_ok() && self.is_our_elder(core.id()) {
+            self.vote_for_event(AccumulatingEvent::NotaryTick(timestamp));
+        }
+    }
+
+    fn next_recovery_snapshot_index(&self) -> u64 {
+        self.shared_state
+            .recovery_snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.value.index + 1)
+            .unwrap_or(0)
+    }
+
+    // Send the certificate of our recovered section to the rest of our section and to our
+    // neighbours, so they can trust our new key.
+    pub fn send_recovery_certificate(&self, core: &mut Core, certificate: RecoveryCertificate) {
+        let snapshot = &certificate.snapshot.value;
+        let recipients: Vec<_> = snapshot
+            .survivors()
+            .map(|(p2p_node, _)| p2p_node)
+            .filter(|p2p_node| !self.is_our_elder(p2p_node.public_id()))
+            .chain(
+                snapshot
+                    .neighbours
+                    .iter()
+                    .flat_map(|info| info.elders.values()),
+            )
+            .map(|p2p_node| *p2p_node.peer_addr())
+            .collect();
+
+        let variant = Variant::RecoveryCertificate(Box::new(certificate));
+        for addr in recipients {
+            core.send_direct_message(&addr, variant.clone());
+        }
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     // Message handling
     ////////////////////////////////////////////////////////////////////////////
@@ -334,7 +880,12 @@ impl Approved {
                     return Ok(MessageStatus::Unknown);
                 }
             }
-            Variant::UserMessage(_) => {
+            Variant::UserMessage(_)
+            | Variant::UserMessageChunk { .. }
+            | Variant::AckedUserMessage(_)
+            | Variant::Ack(_)
+            | Variant::OrderedUserMessage { .. }
+            | Variant::OrderedResendRequest { .. } => {
                 if !self.should_handle_user_message(our_id, msg.dst()) {
                     return Ok(MessageStatus::Unknown);
                 }
@@ -362,17 +913,43 @@ impl Approved {
                     return Ok(MessageStatus::Unknown);
                 }
             }
-            Variant::NodeApproval(_) | Variant::BootstrapResponse(_) | Variant::Ping => {
-                return Ok(MessageStatus::Useless)
+            Variant::GenesisDkgMessage { participants, .. } => {
+                if self.recovery_snapshot_for(our_id, participants).is_some() {
+                    return Ok(MessageStatus::Useful);
+                } else {
+                    return Ok(MessageStatus::Useless);
+                }
+            }
+            Variant::DecryptionShare { .. }
+            | Variant::SkipLinkShare { .. }
+            | Variant::SectionProofRequest { .. }
+            | Variant::LocateRequest
+            | Variant::MembershipCertificateRequest => {
+                if self.is_our_elder(our_id) {
+                    return Ok(MessageStatus::Useful);
+                } else {
+                    return Ok(MessageStatus::Useless);
+                }
             }
+            Variant::NodeApproval(_)
+            | Variant::BootstrapResponse(_)
+            | Variant::Ping
+            | Variant::GenesisSignatureShares { .. }
+            | Variant::SectionProofResponse { .. } => return Ok(MessageStatus::Useless),
             Variant::Relocate(_)
             | Variant::MessageSignature(_)
-            | Variant::BootstrapRequest(_)
+            | Variant::BootstrapRequest { .. }
             | Variant::ParsecPoke(_)
             | Variant::ParsecRequest(..)
             | Variant::ParsecResponse(..)
             | Variant::BouncedUntrustedMessage(_)
-            | Variant::BouncedUnknownMessage { .. } => {}
+            | Variant::BouncedUnknownMessage { .. }
+            | Variant::AddressChallenge { .. }
+            | Variant::AddressChallengeResponse { .. }
+            | Variant::MessageRejected { .. }
+            | Variant::RecoveryCertificate(_)
+            | Variant::LocateResponse(_)
+            | Variant::MembershipCertificateShare { .. } => {}
         }
 
         if self.verify_message(msg)? {
@@ -435,12 +1012,13 @@ impl Approved {
             Some(bounce_dst_key),
             Variant::BouncedUntrustedMessage(Box::new(msg)),
         )?;
+        let priority = bounce_msg.variant().priority();
         let bounce_msg = bounce_msg.to_bytes();
 
         if let Some(sender) = sender {
-            core.send_message_to_target(&sender, bounce_msg)
+            core.send_message_to_target(&sender, bounce_msg, priority)
         } else {
-            self.send_message_to_our_elders(core, bounce_msg)
+            self.send_message_to_our_elders(core, bounce_msg, priority)
         }
 
         Ok(())
@@ -464,6 +1042,7 @@ impl Approved {
                 parsec_version: self.consensus_engine.parsec_version(),
             },
         )?;
+        let priority = bounce_msg.variant().priority();
         let bounce_msg = bounce_msg.to_bytes();
 
         // If the message came from one of our elders then bounce it only to them to avoid message
@@ -475,9 +1054,9 @@ impl Approved {
                 .any(|p2p_node| p2p_node.peer_addr() == sender)
         });
         if let Some(sender) = our_elder_sender {
-            core.send_message_to_target(&sender, bounce_msg)
+            core.send_message_to_target(&sender, bounce_msg, priority)
         } else {
-            self.send_message_to_our_elders(core, bounce_msg)
+            self.send_message_to_our_elders(core, bounce_msg, priority)
         }
 
         Ok(())
@@ -498,14 +1077,17 @@ impl Approved {
             return Ok(());
         };
 
-        let resend_msg =
-            match bounced_msg.extend_proof_chain(&dst_key, &self.shared_state.our_history) {
-                Ok(msg) => msg,
-                Err(error) => {
-                    trace!("    ...extending proof failed, discarding: {:?}", error);
-                    return Ok(());
-                }
-            };
+        let resend_msg = match bounced_msg.extend_proof_chain(
+            &dst_key,
+            &self.shared_state.our_history,
+            &self.shared_state.skip_links,
+        ) {
+            Ok(msg) => msg,
+            Err(error) => {
+                trace!("    ...extending proof failed, discarding: {:?}", error);
+                return Ok(());
+            }
+        };
 
         trace!("    ...resending with extended proof");
 
@@ -541,8 +1123,10 @@ impl Approved {
         // they will then be able to handle the resent message. If not, the peer will bounce the
         // message again.
 
+        // The priority of the bounced message is unknown without deserialising it, so it is resent
+        // as a control one. The parsec gossip still overtakes it.
         self.send_parsec_gossip(core, Some((sender_parsec_version, sender.clone())));
-        core.send_message_to_target(sender.peer_addr(), bounced_msg_bytes)
+        core.send_message_to_target(sender.peer_addr(), bounced_msg_bytes, Priority::Control)
     }
 
     pub fn handle_neighbour_info(&mut self, elders_info: EldersInfo, src_key: bls::PublicKey) {
@@ -578,11 +1162,112 @@ impl Approved {
 
         core.msg_filter.reset();
 
+        let recovery_snapshot = self.shared_state.recovery_snapshot.take();
         self.shared_state = SharedState::new(genesis_prefix_info.elders_info, section_key);
+        self.shared_state.recovery_snapshot = recovery_snapshot;
+        core.active_version = self.shared_state.upgrade.active_version();
         self.section_keys_provider = SectionKeysProvider::new(None);
+        self.lost_elders.clear();
+        self.recovery_timer_token = None;
         self.reset_parsec(core, genesis_prefix_info.parsec_version)
     }
 
+    pub fn handle_recovery_certificate(
+        &mut self,
+        core: &mut Core,
+        certificate: RecoveryCertificate,
+    ) -> Result<()> {
+        let prefix = certificate.elders_info.value.prefix;
+        let new_key = *certificate.new_key();
+
+        if prefix == *self.shared_state.our_prefix() {
+            if self.is_our_elder(core.id()) || self.shared_state.our_history.has_key(&new_key) {
+                trace!("Ignore RecoveryCertificate of our section - not needed");
+                return Ok(());
+            }
+
+            // Only accept the recovery once we lost our elders too, to prevent the recovery
+            // elders from taking over a section that still works.
+            if !self.has_lost_elder_quorum() {
+                warn!("Ignore RecoveryCertificate of our section - our elders are still reachable");
+                return Ok(());
+            }
+
+            // The snapshot must capture our section as we know it now. Recovering from an older
+            // one would roll back the changes since.
+            if certificate.snapshot.proof.public_key != *self.shared_state.our_history.last_key()
+                || certificate.snapshot.value.elders_info != *self.shared_state.our_info()
+            {
+                warn!(
+                    "Ignore RecoveryCertificate of our section - snapshot {} is outdated",
+                    certificate.snapshot.value.index
+                );
+                return Ok(());
+            }
+
+            if !certificate.verify(self.shared_state.our_history.keys()) {
+                return Err(RoutingError::UntrustedMessage);
+            }
+
+            info!(
+                "Our section recovered from snapshot {} with new elders {:?}",
+                certificate.snapshot.value.index, certificate.elders_info.value
+            );
+
+            core.msg_filter.reset();
+
+            self.shared_state = SharedState::new(certificate.elders_info, new_key);
+            core.active_version = self.shared_state.upgrade.active_version();
+            self.section_keys_provider = SectionKeysProvider::new(None);
+            self.lost_elders.clear();
+            self.recovery_timer_token = None;
+
+            // The recovered elders start a fresh parsec instead of resetting the one of the lost
+            // elders, so do the same.
+            self.consensus_engine = ConsensusEngine::new(
+                &mut core.rng,
+                core.full_id.clone(),
+                self.shared_state.our_info(),
+                vec![],
+                certificate.snapshot.value.parsec_version + 1,
+            );
+        } else if self.is_our_elder(core.id())
+            && prefix.is_neighbour(self.shared_state.our_prefix())
+        {
+            if self.shared_state.sections.has_key(&new_key) {
+                trace!("Ignore RecoveryCertificate of {:?} - already known", prefix);
+                return Ok(());
+            }
+
+            let trusted_keys = self
+                .shared_state
+                .sections
+                .keys()
+                .filter(|(known_prefix, _)| **known_prefix == prefix)
+                .map(|(_, key)| key);
+            if !certificate.verify(trusted_keys) {
+                return Err(RoutingError::UntrustedMessage);
+            }
+
+            info!(
+                "Neighbour {:?} recovered with new elders {:?}",
+                prefix, certificate.elders_info.value
+            );
+
+            // Let the recovered section know our latest info too.
+            let nonce = MessageHash::from_bytes(&bincode::serialize(&certificate)?);
+            self.handle_neighbour_info(certificate.elders_info.value, new_key);
+            self.vote_for_event(AccumulatingEvent::SendNeighbourInfo {
+                dst: prefix.name(),
+                nonce,
+            });
+        } else {
+            trace!("Ignore RecoveryCertificate of {:?} - not relevant", prefix);
+        }
+
+        Ok(())
+    }
+
     pub fn handle_relocate(
         &mut self,
         core: &mut Core,
@@ -653,16 +1338,27 @@ impl Approved {
         core: &mut Core,
         p2p_node: P2pNode,
         destination: XorName,
+        network_id: Option<NetworkId>,
+        protocol: &Protocol,
     ) {
         debug!(
             "Received BootstrapRequest to section at {} from {:?}.",
             destination, p2p_node
         );
 
+        if core
+            .check_network_id(p2p_node.peer_addr(), network_id)
+            .is_err()
+            || core.check_protocol(p2p_node.peer_addr(), protocol).is_err()
+        {
+            return;
+        }
+
         let response = if self.shared_state.our_prefix().matches(&destination) {
             BootstrapResponse::Join {
                 elders_info: self.shared_state.our_info().clone(),
                 section_key: *self.shared_state.our_history.last_key(),
+                network_id: core.network_id,
             }
         } else {
             let conn_infos: Vec<_> = self
@@ -680,6 +1376,414 @@ impl Approved {
         core.send_direct_message(p2p_node.peer_addr(), Variant::BootstrapResponse(response));
     }
 
+    pub fn handle_section_proof_request(
+        &mut self,
+        core: &mut Core,
+        p2p_node: P2pNode,
+        known_key: bls::PublicKey,
+    ) {
+        trace!("Received SectionProofRequest from {}", p2p_node);
+
+        if self.shared_state.our_prefix().matches(p2p_node.name()) {
+            let _ = self
+                .light_clients
+                .insert(*p2p_node.name(), p2p_node.clone());
+        }
+
+        let from = self
+            .shared_state
+            .our_history
+            .index_of(&known_key)
+            .unwrap_or(0);
+        let variant = Variant::SectionProofResponse {
+            elders_info: self.shared_state.sections.proven_our().clone(),
+            proof_chain: Box::new(self.shared_state.prove_compact(from)),
+        };
+
+        core.send_direct_message(p2p_node.peer_addr(), variant);
+    }
+
+    pub fn handle_locate_request(&mut self, core: &mut Core, requester: XorName) -> Result<()> {
+        trace!("Received LocateRequest from {}", requester);
+
+        let located = LocatedSection {
+            elders_info: self.shared_state.sections.proven_our().clone(),
+            proof_chain: self.shared_state.prove_compact(0),
+        };
+
+        self.send_routing_message(
+            core,
+            SrcLocation::Node(*core.name()),
+            DstLocation::Node(requester),
+            Variant::LocateResponse(Box::new(located)),
+            None,
+        )
+    }
+
+    pub fn handle_locate_response(
+        &mut self,
+        core: &Core,
+        sender: &PublicId,
+        located: LocatedSection,
+    ) {
+        let prefix = located.elders_info.value.prefix;
+        if located
+            .elders_info
+            .value
+            .elders
+            .get(sender.name())
+            .map(P2pNode::public_id)
+            != Some(sender)
+        {
+            debug!(
+                "Ignoring LocateResponse for {:?} from non-elder {}",
+                prefix, sender
+            );
+            return;
+        }
+
+        // A section we descend from can only be known from before it split.
+        let our_prefix = self.shared_state.our_prefix();
+        if prefix == *our_prefix || our_prefix.is_extension_of(&prefix) {
+            debug!("Ignoring LocateResponse for stale {:?}", prefix);
+            return;
+        }
+
+        let names: Vec<_> = self
+            .pending_locates
+            .peek_iter()
+            .map(|(name, _)| *name)
+            .filter(|name| prefix.matches(name))
+            .collect();
+        if names.is_empty() {
+            trace!("Ignoring unexpected LocateResponse for {:?}", prefix);
+            return;
+        }
+
+        // The located section shares our history up to the split that separated us, so our keys
+        // are enough to establish its trust.
+        match located.check_trust(self.shared_state.our_history.keys()) {
+            TrustStatus::Trusted => (),
+            status => {
+                debug!(
+                    "Ignoring LocateResponse for {:?} with {:?} proof",
+                    prefix, status
+                );
+                return;
+            }
+        }
+
+        // A key we share with the located section was in use before the split that separated us,
+        // so the `EldersInfo` it signed is outdated.
+        if self
+            .shared_state
+            .our_history
+            .has_key(&located.elders_info.proof.public_key)
+        {
+            debug!(
+                "Ignoring LocateResponse for {:?} signed before our last common key",
+                prefix
+            );
+            return;
+        }
+
+        for name in names {
+            let _ = self.pending_locates.remove(&name);
+        }
+
+        core.send_event(Event::Located(Box::new(located)));
+    }
+
+    pub fn handle_membership_certificate_request(
+        &mut self,
+        core: &mut Core,
+        p2p_node: P2pNode,
+    ) -> Result<()> {
+        let statement =
+            if let Some(statement) = self.shared_state.membership_statement(p2p_node.name()) {
+                statement
+            } else {
+                trace!(
+                    "Ignoring MembershipCertificateRequest from non-member {}",
+                    p2p_node
+                );
+                return Ok(());
+            };
+
+        let proof_share = self.membership_proof_share(&statement)?;
+        core.send_direct_message(
+            p2p_node.peer_addr(),
+            Variant::MembershipCertificateShare {
+                statement,
+                proof_share,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn handle_membership_certificate_share(
+        &mut self,
+        core: &Core,
+        statement: MembershipStatement,
+        proof_share: ProofShare,
+    ) {
+        if self.shared_state.membership_statement(core.name()).as_ref() != Some(&statement) {
+            trace!(
+                "Ignoring outdated MembershipCertificateShare {:?}",
+                statement
+            );
+            return;
+        }
+
+        let public_key = proof_share.public_key_set.public_key();
+        if !self.shared_state.our_history.has_key(&public_key) {
+            trace!("Ignoring MembershipCertificateShare signed by unknown key");
+            return;
+        }
+
+        match self.membership_accumulator.add(statement, proof_share) {
+            Ok((statement, proof)) => {
+                debug!("Got membership certificate {:?}", statement);
+                let proof_chain = self
+                    .shared_state
+                    .prove_compact_including(0, &proof.public_key);
+                self.membership_certificate = Some(MembershipCertificate {
+                    statement: Proven::new(statement, proof),
+                    proof_chain,
+                });
+            }
+            Err(AccumulationError::NotEnoughShares)
+            | Err(AccumulationError::AlreadyAccumulated) => (),
+            Err(error) => debug!("Failed to add MembershipCertificateShare: {}", error),
+        }
+    }
+
+    pub fn handle_user_message_chunk(
+        &mut self,
+        core: &Core,
+        msg: &Message,
+        hash: Digest256,
+        index: u32,
+        count: u32,
+        data: Vec<u8>,
+    ) {
+        let src = msg.src().src_location();
+        if let Some(content) = self
+            .chunk_assembler
+            .add(&src, msg.dst(), hash, index, count, data)
+        {
+            core.send_event(Event::MessageReceived {
+                content,
+                src,
+                dst: *msg.dst(),
+            });
+        }
+    }
+
+    pub fn handle_acked_user_message(
+        &mut self,
+        core: &mut Core,
+        msg: &Message,
+        content: Vec<u8>,
+    ) -> Result<()> {
+        core.send_event(Event::MessageReceived {
+            content,
+            src: msg.src().src_location(),
+            dst: *msg.dst(),
+        });
+
+        // A section acknowledges with the signatures of its elders, so the ack only reaches the
+        // sender once a quorum of them received the message.
+        let src = if msg.dst().is_section() {
+            SrcLocation::Section(*self.shared_state.our_prefix())
+        } else {
+            SrcLocation::Node(*core.name())
+        };
+        let dst = msg.src().src_location().to_dst();
+
+        self.send_routing_message(core, src, dst, Variant::Ack(*msg.hash()), None)
+    }
+
+    pub fn handle_ack(&mut self, core: &Core, src: SrcLocation, msg_hash: MessageHash) {
+        let dst = if let Some(pending) = self.pending_acks.get(&msg_hash) {
+            pending.dst
+        } else {
+            trace!("Ignoring ack of message {:?} - not pending", msg_hash);
+            return;
+        };
+
+        // Only the destination of the message can acknowledge it.
+        let valid = match (dst, src) {
+            (DstLocation::Node(dst_name), SrcLocation::Node(src_name)) => dst_name == src_name,
+            (DstLocation::Section(dst_name), SrcLocation::Section(src_prefix)) => {
+                src_prefix.matches(&dst_name)
+            }
+            _ => false,
+        };
+        if !valid {
+            debug!(
+                "Ignoring ack of message {:?} to {:?} from {:?}",
+                msg_hash, dst, src
+            );
+            return;
+        }
+
+        let _ = self.pending_acks.remove(&msg_hash);
+        core.send_event(Event::Delivered { dst, msg_hash });
+    }
+
+    // Sends `content` from our node to `dst`, asking `dst` to acknowledge it. Returns the hash of
+    // the message.
+    pub fn send_message_with_ack(
+        &mut self,
+        core: &mut Core,
+        dst: DstLocation,
+        content: Vec<u8>,
+    ) -> Result<MessageHash> {
+        if content.len() > messages::MAX_CHUNK_SIZE {
+            return Err(RoutingError::InvalidMessage);
+        }
+
+        let msg =
+            Message::single_src(&core.full_id, dst, None, Variant::AckedUserMessage(content))?;
+        let msg_hash = *msg.hash();
+        let timer_token = core.timer.schedule(ACK_TIMEOUT);
+        let _ = self
+            .pending_acks
+            .insert(msg_hash, PendingAck { dst, timer_token });
+
+        self.handle_accumulated_message(core, msg)?;
+        Ok(msg_hash)
+    }
+
+    // Sends `content` from our node to `dst` through the ordered channel to `dst`.
+    pub fn send_ordered_message(
+        &mut self,
+        core: &mut Core,
+        dst: DstLocation,
+        content: Vec<u8>,
+    ) -> Result<()> {
+        if content.len() > messages::MAX_CHUNK_SIZE {
+            return Err(RoutingError::InvalidMessage);
+        }
+
+        let (epoch, seq) = self
+            .ordered_outbox
+            .push(dst, content.clone(), &mut core.rng);
+        let variant = Variant::OrderedUserMessage {
+            channel: dst,
+            epoch,
+            seq,
+            resend: 0,
+            content,
+        };
+
+        self.send_routing_message(core, SrcLocation::Node(*core.name()), dst, variant, None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_ordered_user_message(
+        &mut self,
+        core: &Core,
+        msg: &Message,
+        src: XorName,
+        channel: DstLocation,
+        epoch: u64,
+        seq: u64,
+        resend: u32,
+        content: Vec<u8>,
+    ) {
+        // A message resent to us alone must belong to a channel to us or our section.
+        if *msg.dst() != channel && !channel.contains(core.name(), self.shared_state.our_prefix()) {
+            debug!("Ignoring ordered message of foreign channel {:?}", channel);
+            return;
+        }
+
+        if resend > 0 {
+            trace!(
+                "Received ordered message {} of channel from {} to {:?} resent on request {}",
+                seq,
+                src,
+                channel,
+                resend
+            );
+        }
+
+        for content in self.ordered_inbox.add(src, channel, epoch, seq, content) {
+            core.send_event(Event::MessageReceived {
+                content,
+                src: SrcLocation::Node(src),
+                dst: channel,
+            });
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_ordered_resend_request(
+        &mut self,
+        core: &mut Core,
+        requester: XorName,
+        channel: DstLocation,
+        epoch: u64,
+        from: u64,
+        to: u64,
+        attempt: u32,
+    ) -> Result<()> {
+        // Only the destinations of the channel get its messages.
+        let is_destination = match channel {
+            DstLocation::Node(name) => name == requester,
+            DstLocation::Section(name) => self
+                .shared_state
+                .sections
+                .closest(&name)
+                .prefix
+                .matches(&requester),
+            DstLocation::Direct => false,
+        };
+        if !is_destination {
+            debug!(
+                "Ignoring resend request from {} for channel {:?}",
+                requester, channel
+            );
+            return Ok(());
+        }
+
+        let variants: Vec<_> = self
+            .ordered_outbox
+            .get(&channel, epoch, from, to)
+            .map(|(seq, content)| Variant::OrderedUserMessage {
+                channel,
+                epoch,
+                seq,
+                resend: attempt,
+                content: content.clone(),
+            })
+            .collect();
+
+        if variants.is_empty() {
+            trace!(
+                "No ordered messages {}..{} of channel {:?} with epoch {} left to resend",
+                from,
+                to,
+                channel,
+                epoch
+            );
+        }
+
+        for variant in variants {
+            self.send_routing_message(
+                core,
+                SrcLocation::Node(*core.name()),
+                DstLocation::Node(requester),
+                variant,
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub fn handle_join_request(
         &mut self,
         core: &mut Core,
@@ -688,10 +1792,18 @@ impl Approved {
     ) {
         debug!("Received {:?} from {}", join_request, p2p_node);
 
+        if core
+            .check_network_id(p2p_node.peer_addr(), join_request.network_id)
+            .is_err()
+        {
+            return;
+        }
+
         if join_request.section_key != *self.shared_state.our_history.last_key() {
             let response = BootstrapResponse::Join {
                 elders_info: self.shared_state.our_info().clone(),
                 section_key: *self.shared_state.our_history.last_key(),
+                network_id: core.network_id,
             };
             trace!("Resending BootstrapResponse {:?} to {}", response, p2p_node,);
             core.send_direct_message(p2p_node.peer_addr(), Variant::BootstrapResponse(response));
@@ -716,6 +1828,31 @@ impl Approved {
             return;
         }
 
+        if join_request.relocate_payload.is_none()
+            && !self
+                .shared_state
+                .is_accepting_infants(core.network_params.max_infants)
+        {
+            debug!(
+                "Ignoring JoinRequest from {} - not accepting more infants.",
+                pub_id
+            );
+            return;
+        }
+
+        if !core.peer_bindings.is_bound(&p2p_node) {
+            debug!(
+                "Deferring JoinRequest from {} - address not verified yet.",
+                p2p_node
+            );
+            let addr = *p2p_node.peer_addr();
+            let _ = self
+                .pending_join_requests
+                .insert(addr, (p2p_node, join_request));
+            core.challenge_peer(addr);
+            return;
+        }
+
         // This joining node is being relocated to us.
         let (age, previous_name, their_knowledge) =
             if let Some(payload) = join_request.relocate_payload {
@@ -768,6 +1905,34 @@ impl Approved {
         })
     }
 
+    // Called when the peer proved that it owns the `PublicId` of `p2p_node` at its address.
+    pub fn handle_peer_bound(&mut self, core: &mut Core, p2p_node: P2pNode) {
+        if let Some((pending_node, join_request)) =
+            self.pending_join_requests.remove(p2p_node.peer_addr())
+        {
+            if pending_node == p2p_node {
+                self.handle_join_request(core, p2p_node.clone(), join_request);
+            }
+        }
+
+        if !self.is_our_elder(core.id()) {
+            return;
+        }
+
+        // A member reachable at a different address than we know - it changed its IP.
+        let moved = self
+            .shared_state
+            .our_members
+            .get(p2p_node.name())
+            .filter(|info| info.state != MemberState::Left)
+            .map(|info| info.p2p_node.peer_addr() != p2p_node.peer_addr())
+            .unwrap_or(false);
+
+        if moved {
+            self.vote_for_event(AccumulatingEvent::AddressUpdate(p2p_node));
+        }
+    }
+
     pub fn handle_parsec_poke(&mut self, core: &mut Core, p2p_node: P2pNode, version: u64) {
         trace!("Received parsec poke v{} from {}", version, p2p_node);
 
@@ -889,6 +2054,7 @@ impl Approved {
         let responses = self.dkg_voter.process_dkg_message(
             &mut core.rng,
             &(participants.clone(), section_key_index),
+            pub_id,
             msg_parsed,
         );
 
@@ -919,9 +2085,32 @@ impl Approved {
         }
     }
 
+    // Replaces the full proof chain of a message accumulated by our section with the compact one,
+    // once all our elders support it.
+    fn compact_proof(&self, msg: Message) -> Result<Message> {
+        if self.shared_state.upgrade.active_version() < COMPACT_PROOF_VERSION {
+            return Ok(msg);
+        }
+
+        let proof_chain = match msg.src() {
+            SrcAuthority::Section { proof_chain, .. } => {
+                self.shared_state.compact_proof(proof_chain)
+            }
+            SrcAuthority::Node { .. } | SrcAuthority::CompactSection { .. } => None,
+        };
+
+        if let Some(proof_chain) = proof_chain {
+            Ok(msg.into_compact(proof_chain)?)
+        } else {
+            Ok(msg)
+        }
+    }
+
     fn handle_accumulated_message(&mut self, core: &mut Core, msg: Message) -> Result<()> {
         trace!("accumulated message {:?}", msg);
 
+        let msg = self.compact_proof(msg)?;
+
         // TODO: this is almost the same as `Node::try_handle_message` - find a way
         // to avoid the duplication.
         self.try_relay_message(core, &msg)?;
@@ -1245,9 +2434,62 @@ impl Approved {
                 payload,
                 proof.expect("missing proof for Relocate"),
             )?,
+            AccumulatingEvent::RequestMoreNodes(index) => {
+                self.handle_request_more_nodes_event(index)
+            }
+            AccumulatingEvent::Evict(name) => {
+                self.handle_evict_event(core, name, proof.expect("missing proof for Evict"))
+            }
+            AccumulatingEvent::AddressUpdate(p2p_node) => {
+                self.handle_address_update_event(p2p_node)
+            }
+            AccumulatingEvent::StartRecoverySnapshot(index) => {
+                self.handle_start_recovery_snapshot_event(core, index)
+            }
+            AccumulatingEvent::RecoverySnapshot(snapshot) => self.handle_recovery_snapshot_event(
+                *snapshot,
+                proof.expect("missing proof for RecoverySnapshot"),
+            ),
+            AccumulatingEvent::RefreshKey(index) => self.handle_refresh_key_event(core, index),
+            AccumulatingEvent::DkgFailure {
+                participants,
+                section_key_index,
+                non_contributor,
+            } => self.handle_dkg_failure_event(participants, section_key_index, non_contributor),
+            AccumulatingEvent::Decrypt {
+                key_index,
+                ciphertext,
+            } => self.handle_decrypt_event(core, key_index, *ciphertext),
+            AccumulatingEvent::Notarise(hash) => self.handle_notarise_event(hash),
+            AccumulatingEvent::NotaryTick(timestamp) => {
+                self.handle_notary_tick_event(core, timestamp)
+            }
+            AccumulatingEvent::NotaryBatch(batch) => self.handle_notary_batch_event(
+                core,
+                batch,
+                proof.expect("missing proof for NotaryBatch"),
+            ),
+            AccumulatingEvent::BeaconRound(input) => self.handle_beacon_round_event(
+                core,
+                input,
+                proof.expect("missing proof for BeaconRound"),
+            ),
+            AccumulatingEvent::ProposeUpgrade {
+                version,
+                allow_quorum_loss,
+            } => self.handle_propose_upgrade_event(core, version, allow_quorum_loss),
+            AccumulatingEvent::UpgradeReport { name, version } => {
+                self.handle_upgrade_report_event(core, name, version)
+            }
+            AccumulatingEvent::SkipLink { index, signature } => {
+                self.handle_skip_link_event(index, signature)
+            }
             AccumulatingEvent::User(payload) => self.handle_user_event(core, payload)?,
         }
 
+        // The genesis event or the completion of an upgrade might have changed it.
+        core.active_version = self.shared_state.upgrade.active_version();
+
         Ok(())
     }
 
@@ -1276,6 +2518,8 @@ impl Approved {
         their_knowledge: Option<bls::PublicKey>,
         proof: Proof,
     ) {
+        let over_infant_limit = self.shared_state.infant_count() >= core.network_params.max_infants;
+
         if self.shared_state.add_member(
             p2p_node.clone(),
             age,
@@ -1286,6 +2530,13 @@ impl Approved {
 
             self.members_changed = true;
 
+            if previous_name.is_none()
+                && over_infant_limit
+                && self.shared_state.requested_members > 0
+            {
+                self.shared_state.requested_members -= 1;
+            }
+
             if self.is_our_elder(core.id()) {
                 if let Some(previous_name) = previous_name {
                     core.send_event(Event::MemberJoined {
@@ -1300,35 +2551,174 @@ impl Approved {
                     });
                 }
 
-                self.send_node_approval(core, p2p_node, their_knowledge);
-                self.print_network_stats();
+                self.send_node_approval(core, p2p_node, their_knowledge);
+                self.print_network_stats(core);
+            }
+        } else {
+            info!("ignore Online: {}", p2p_node);
+        }
+    }
+
+    fn handle_offline_event(&mut self, core: &mut Core, name: XorName, proof: Proof) {
+        if let Some(info) = self.shared_state.remove_member(
+            &name,
+            proof,
+            core.network_params.recommended_section_size,
+        ) {
+            info!("handle Offline: {}", name);
+
+            self.members_changed = true;
+
+            core.transport.disconnect(*info.p2p_node.peer_addr());
+            core.peer_bindings.remove(info.p2p_node.peer_addr());
+
+            if self.is_our_elder(core.id()) {
+                core.send_event(Event::MemberLeft {
+                    name,
+                    age: info.age(),
+                });
+            }
+        } else {
+            info!("ignore Offline: {}", name);
+        }
+    }
+
+    fn handle_address_update_event(&mut self, p2p_node: P2pNode) {
+        if let Some(old_addr) = self
+            .shared_state
+            .our_members
+            .update_peer_addr(p2p_node.clone())
+        {
+            info!("handle AddressUpdate: {} (was at {})", p2p_node, old_addr);
+
+            // Elders are stored with their address in `EldersInfo` so this might cause an elders
+            // change.
+            self.members_changed = true;
+        } else {
+            info!("ignore AddressUpdate: {}", p2p_node);
+        }
+    }
+
+    fn handle_evict_event(&mut self, core: &mut Core, name: XorName, proof: Proof) {
+        info!("handle Evict: {}", name);
+        self.handle_offline_event(core, name, proof)
+    }
+
+    fn handle_start_recovery_snapshot_event(&mut self, core: &Core, index: u64) {
+        if index != self.next_recovery_snapshot_index() || !self.is_our_elder(core.id()) {
+            return;
+        }
+
+        // All the elders handle this event at the same point of the consensus, so the snapshots
+        // they vote for are identical.
+        let snapshot = RecoverySnapshot::new(
+            index,
+            self.consensus_engine.parsec_version(),
+            &self.shared_state,
+        );
+        self.vote_for_event(AccumulatingEvent::RecoverySnapshot(Box::new(snapshot)));
+    }
+
+    fn handle_recovery_snapshot_event(&mut self, snapshot: RecoverySnapshot, proof: Proof) {
+        if snapshot.index < self.next_recovery_snapshot_index() {
+            return;
+        }
+
+        debug!("Took recovery snapshot {}", snapshot.index);
+        self.shared_state.recovery_snapshot = Some(Proven::new(snapshot, proof));
+    }
+
+    fn handle_refresh_key_event(&mut self, core: &mut Core, index: u64) {
+        if index != self.shared_state.our_history.last_key_index() {
+            trace!("ignore RefreshKey({}) - stale", index);
+            return;
+        }
+
+        if !self.is_ready_to_churn() {
+            // The key is going to change anyway.
+            debug!("ignore RefreshKey({}) - churn in progress", index);
+            return;
+        }
+
+        info!("handle RefreshKey({})", index);
+
+        self.churn_in_progress = true;
+
+        if !self.is_our_elder(core.id()) {
+            return;
+        }
+
+        // Run DKG among the current elders. Once it completes we vote for the new key and for the
+        // unchanged `EldersInfo` the same way as for any other elder change.
+        let info = self.shared_state.our_info().clone();
+        let participants: BTreeSet<_> = info.elder_ids().copied().collect();
+        let dkg_key = (participants.clone(), index);
+
+        if let Some(dkg_result) = self.dkg_voter.push_info(&dkg_key, info) {
+            if let Err(err) = self.handle_dkg_result_event(core, &participants, index, &dkg_result)
+            {
+                debug!("Failed handle dkg_result {:?} - {:?}", dkg_key, err);
             }
         } else {
-            info!("ignore Online: {}", p2p_node);
+            self.init_dkg_gen(core, participants, index);
         }
     }
 
-    fn handle_offline_event(&mut self, core: &mut Core, name: XorName, proof: Proof) {
-        if let Some(info) = self.shared_state.remove_member(
-            &name,
-            proof,
-            core.network_params.recommended_section_size,
-        ) {
-            info!("handle Offline: {}", name);
+    fn handle_dkg_failure_event(
+        &mut self,
+        participants: BTreeSet<PublicId>,
+        section_key_index: u64,
+        non_contributor: PublicId,
+    ) {
+        if section_key_index != self.shared_state.our_history.last_key_index() {
+            trace!("ignore DkgFailure of {:?} - stale", participants);
+            return;
+        }
 
-            self.members_changed = true;
+        info!(
+            "handle DkgFailure of {:?} (non-contributor: {})",
+            participants, non_contributor
+        );
 
-            core.transport.disconnect(*info.p2p_node.peer_addr());
+        if self
+            .shared_state
+            .our_members
+            .is_active(non_contributor.name())
+        {
+            let _ = self
+                .shared_state
+                .dkg_excluded
+                .insert(*non_contributor.name());
+        }
 
-            if self.is_our_elder(core.id()) {
-                core.send_event(Event::MemberLeft {
-                    name,
-                    age: info.age(),
-                });
-            }
-        } else {
-            info!("ignore Offline: {}", name);
+        let dkg_key = (participants, section_key_index);
+        let _ = self.dkg_voter.take_info(&dkg_key);
+        self.dkg_voter.remove_voter(&dkg_key);
+
+        // Recalculate the elders, this time choosing the next best candidates instead of the
+        // excluded ones.
+        self.churn_in_progress = false;
+        self.members_changed = true;
+    }
+
+    fn handle_request_more_nodes_event(&mut self, index: u64) {
+        // Whoever voted for a request also voted for all the ones before it, so agreeing on it
+        // agrees on them too, even if their own votes accumulate later or not at all.
+        if index < self.shared_state.more_nodes_requests {
+            debug!("ignore RequestMoreNodes({}) - already agreed on", index);
+            return;
         }
+
+        let count = index + 1 - self.shared_state.more_nodes_requests;
+        self.shared_state.more_nodes_requests = index + 1;
+        self.shared_state.requested_members = self
+            .shared_state
+            .requested_members
+            .saturating_add(count as usize);
+        info!(
+            "handle RequestMoreNodes({}): {} more (requested in total: {})",
+            index, count, self.shared_state.requested_members
+        );
     }
 
     fn handle_relocate_event(
@@ -1589,6 +2979,400 @@ impl Approved {
         Ok(())
     }
 
+    fn handle_beacon_round_event(&mut self, core: &mut Core, input: BeaconInput, proof: Proof) {
+        let round = input.round;
+        let value = proof.signature.clone();
+
+        if self.shared_state.beacon.push(Proven::new(input, proof)) {
+            trace!("handle BeaconRound({})", round);
+            core.send_event(Event::BeaconRound { round, value });
+        }
+    }
+
+    fn handle_notarise_event(&mut self, hash: Digest256) {
+        let _ = self.shared_state.notary.pending.insert(hash);
+    }
+
+    fn handle_notary_tick_event(&mut self, core: &Core, timestamp: u64) {
+        let notary = &mut self.shared_state.notary;
+        if !notary.is_next(timestamp) {
+            return;
+        }
+
+        notary.timestamp = timestamp;
+
+        if notary.sealing.is_none() && !notary.pending.is_empty() {
+            let batch = notary.seal();
+            debug!("Sealed notary batch {} ({})", batch.index, batch.timestamp);
+
+            if self.is_our_elder(core.id()) {
+                self.vote_for_event(AccumulatingEvent::NotaryBatch(batch));
+            }
+        }
+
+        // Catch up if the notary time fell behind, e.g. after a slow round of consensus.
+        self.vote_for_notary_tick(core);
+    }
+
+    fn handle_notary_batch_event(&mut self, core: &mut Core, batch: NotaryBatch, proof: Proof) {
+        let receipts = self
+            .shared_state
+            .notary
+            .complete(Proven::new(batch, proof), &self.shared_state.our_history);
+
+        for receipt in receipts {
+            core.send_event(Event::Notarised(Box::new(receipt)));
+        }
+    }
+
+    fn handle_propose_upgrade_event(
+        &mut self,
+        core: &mut Core,
+        version: u16,
+        allow_quorum_loss: bool,
+    ) {
+        if !self.shared_state.upgrade.start(version, allow_quorum_loss) {
+            debug!(
+                "ignore ProposeUpgrade({}) - not newer than the active or pending version",
+                version
+            );
+            return;
+        }
+
+        info!("Upgrading our section to protocol version {}", version);
+
+        self.report_our_version(core);
+        self.continue_upgrade(core);
+    }
+
+    fn handle_upgrade_report_event(&mut self, core: &mut Core, name: XorName, version: u16) {
+        if self.shared_state.upgrade.report(name, version) {
+            info!("Elder {} runs protocol version {}", name, version);
+        }
+
+        self.continue_upgrade(core);
+    }
+
+    // Vote that we run the protocol version our section is being upgraded to, if we do.
+    fn report_our_version(&mut self, core: &Core) {
+        let target = if let Some(target) = self.shared_state.upgrade.target() {
+            target
+        } else {
+            return;
+        };
+
+        if PROTOCOL_VERSION < target
+            || self.reported_version == Some(target)
+            || self.shared_state.upgrade.is_upgraded(core.name())
+            || !self.is_our_elder(core.id())
+        {
+            return;
+        }
+
+        self.reported_version = Some(target);
+        self.vote_for_event(AccumulatingEvent::UpgradeReport {
+            name: *core.name(),
+            version: PROTOCOL_VERSION,
+        });
+    }
+
+    // Complete the upgrade of our section once all our elders run the target version. Otherwise
+    // ask the upper layers to restart us if it is our turn.
+    fn continue_upgrade(&mut self, core: &mut Core) {
+        if let Some(version) = self
+            .shared_state
+            .upgrade
+            .try_complete(self.shared_state.sections.our())
+        {
+            info!("Upgraded our section to protocol version {}", version);
+            core.send_event(Event::UpgradeCompleted { version });
+            return;
+        }
+
+        let target = if let Some(target) = self.shared_state.upgrade.target() {
+            target
+        } else {
+            return;
+        };
+
+        // If we already run the target version, our report is on its way.
+        if PROTOCOL_VERSION >= target
+            || self.restart_version == Some(target)
+            || !self.is_our_elder(core.id())
+        {
+            return;
+        }
+
+        let our_info = self.shared_state.our_info();
+        if self.shared_state.upgrade.next_to_restart(our_info) != Some(core.name()) {
+            return;
+        }
+
+        if !self.shared_state.upgrade.may_restart(our_info, core.name()) {
+            warn!(
+                "Upgrade to protocol version {} stalled - too few elders to keep a quorum while we \
+                 restart. Propose it again allowing quorum loss to go on.",
+                target
+            );
+            return;
+        }
+
+        if !Upgrade::keeps_quorum(our_info, core.name()) {
+            warn!(
+                "Too few elders to keep consensus going while we restart - it pauses until we are \
+                 back"
+            );
+        }
+
+        info!("Restarting into protocol version {}", target);
+        self.restart_version = Some(target);
+        core.send_event(Event::RestartForUpgrade { version: target });
+    }
+
+    fn handle_decrypt_event(
+        &mut self,
+        core: &mut Core,
+        key_index: u64,
+        ciphertext: bls::Ciphertext,
+    ) {
+        // We only hold the share of our current key. Decrypting with it a ciphertext encrypted
+        // with another key would yield a wrong plaintext.
+        if key_index != self.shared_state.our_history.last_key_index() {
+            debug!(
+                "ignore Decrypt - ciphertext for key {} which is not our current key",
+                key_index
+            );
+            return;
+        }
+
+        let key_share = if let Ok(key_share) = self.section_keys_provider.key_share() {
+            key_share
+        } else {
+            return;
+        };
+
+        // Invalid ciphertext would give us invalid decryption share.
+        let share = if let Some(share) = key_share.secret_key_share.decrypt_share(&ciphertext) {
+            share
+        } else {
+            debug!("ignore Decrypt - invalid ciphertext");
+            return;
+        };
+        let index = key_share.index;
+
+        trace!("handle Decrypt - sending decryption share {}", index);
+
+        let src = SrcLocation::Node(*core.name());
+        let variant = Variant::DecryptionShare {
+            ciphertext: Box::new(ciphertext.clone()),
+            index,
+            share: share.clone(),
+        };
+        let others: Vec<_> = self
+            .shared_state
+            .our_info()
+            .elders
+            .keys()
+            .filter(|name| *name != core.name())
+            .copied()
+            .collect();
+        for name in others {
+            let _ = self.send_routing_message(
+                core,
+                src,
+                DstLocation::Node(name),
+                variant.clone(),
+                None,
+            );
+        }
+
+        self.handle_decryption_share(core, ciphertext, index, share);
+    }
+
+    /// Handle a decryption share of a ciphertext our section agreed to decrypt.
+    pub fn handle_decryption_share(
+        &mut self,
+        core: &mut Core,
+        ciphertext: bls::Ciphertext,
+        index: usize,
+        share: bls::DecryptionShare,
+    ) {
+        let public_key_set = if let Ok(key_share) = self.section_keys_provider.key_share() {
+            &key_share.public_key_set
+        } else {
+            return;
+        };
+
+        match self
+            .decryption_accumulator
+            .add(&ciphertext, public_key_set, index, share)
+        {
+            Ok(plaintext) => core.send_event(Event::SectionDecrypted {
+                ciphertext,
+                plaintext,
+            }),
+            Err(AccumulationError::NotEnoughShares)
+            | Err(AccumulationError::AlreadyAccumulated) => (),
+            Err(error) => debug!("failed to add decryption share {}: {}", index, error),
+        }
+    }
+
+    // Signs the skip link to our new section key with our share of the older key the link is from,
+    // if we still have it, and sends the signature share to the other elders.
+    fn send_skip_link_share(&mut self, core: &mut Core) -> Result<()> {
+        let history = &self.shared_state.our_history;
+        let index = history.last_key_index();
+        let key_share = if let Some(key_share) = skip_link_source(index)
+            .and_then(|source| self.section_keys_provider.past_key_share(source))
+            .filter(|key_share| history.has_key(&key_share.public_key_set.public_key()))
+        {
+            key_share
+        } else {
+            return Ok(());
+        };
+
+        let key = *history.last_key();
+        let proof_share = ProofShare::new(
+            key_share.public_key_set.clone(),
+            key_share.index,
+            &key_share.secret_key_share,
+            &bincode::serialize(&(index, key))?,
+        );
+
+        trace!("sending skip link share to key {}", index);
+
+        let src = SrcLocation::Node(*core.name());
+        let variant = Variant::SkipLinkShare {
+            index,
+            key,
+            proof_share: proof_share.clone(),
+        };
+        let others: Vec<_> = self
+            .shared_state
+            .our_info()
+            .elders
+            .keys()
+            .filter(|name| *name != core.name())
+            .copied()
+            .collect();
+        for name in others {
+            let _ = self.send_routing_message(
+                core,
+                src,
+                DstLocation::Node(name),
+                variant.clone(),
+                None,
+            );
+        }
+
+        self.handle_skip_link_share(index, key, proof_share);
+        Ok(())
+    }
+
+    // Asks our elders to certify our current membership, unless our certificate is up to date.
+    // Backs off exponentially while our elders don't respond, so that members whose certificate
+    // can't be renewed don't keep flooding them with requests.
+    fn request_membership_certificate(&mut self, core: &mut Core) {
+        if self.membership_certificate(core.name()).is_some() {
+            self.membership_request_interval = MEMBERSHIP_CERTIFICATE_INTERVAL;
+            return;
+        }
+
+        self.membership_request_interval = cmp::min(
+            self.membership_request_interval * 2,
+            MAX_MEMBERSHIP_CERTIFICATE_INTERVAL,
+        );
+
+        let elders: Vec<_> = self
+            .shared_state
+            .our_info()
+            .elders
+            .values()
+            .cloned()
+            .collect();
+        for p2p_node in elders {
+            if p2p_node.name() == core.name() {
+                // Sign our own share directly.
+                if let Some(statement) = self.shared_state.membership_statement(core.name()) {
+                    if let Ok(proof_share) = self.membership_proof_share(&statement) {
+                        self.handle_membership_certificate_share(core, statement, proof_share);
+                    }
+                }
+            } else {
+                core.send_direct_message(
+                    p2p_node.peer_addr(),
+                    Variant::MembershipCertificateRequest,
+                );
+            }
+        }
+    }
+
+    fn membership_proof_share(&self, statement: &MembershipStatement) -> Result<ProofShare> {
+        let key_share = self.section_keys_provider.key_share()?;
+        Ok(ProofShare::new(
+            key_share.public_key_set.clone(),
+            key_share.index,
+            &key_share.secret_key_share,
+            &bincode::serialize(statement)?,
+        ))
+    }
+
+    /// Handle a signature share of the skip link to the key at `index` of our section proof chain.
+    pub fn handle_skip_link_share(
+        &mut self,
+        index: u64,
+        key: bls::PublicKey,
+        proof_share: ProofShare,
+    ) {
+        if self.shared_state.skip_links.contains(index) {
+            return;
+        }
+
+        let history = &self.shared_state.our_history;
+        let source_key = proof_share.public_key_set.public_key();
+        if history.key(index) != Some(&key)
+            || skip_link_source(index).and_then(|source| history.key(source)) != Some(&source_key)
+        {
+            trace!("ignore skip link share to key {} - not in our chain", index);
+            return;
+        }
+
+        // The skip links are part of our shared state, so they are agreed on before use and passed
+        // on to the future elders, which might not hold the older keys anymore.
+        match self.skip_link_accumulator.add((index, key), proof_share) {
+            Ok((_, proof)) => self.vote_for_event(AccumulatingEvent::SkipLink {
+                index,
+                signature: proof.signature,
+            }),
+            Err(AccumulationError::NotEnoughShares)
+            | Err(AccumulationError::AlreadyAccumulated) => (),
+            Err(error) => debug!("failed to add skip link share to key {}: {}", index, error),
+        }
+    }
+
+    fn handle_skip_link_event(&mut self, index: u64, signature: bls::Signature) {
+        let history = &self.shared_state.our_history;
+        let verified = match (
+            history.key(index),
+            skip_link_source(index).and_then(|source| history.key(source)),
+        ) {
+            (Some(key), Some(source_key)) => bincode::serialize(&(index, key))
+                .map(|bytes| source_key.verify(&signature, &bytes))
+                .unwrap_or(false),
+            _ => false,
+        };
+
+        if verified {
+            self.shared_state.skip_links.insert(index, signature);
+        } else {
+            log_or_panic!(
+                log::Level::Error,
+                "Accumulated invalid skip link to key {}",
+                index
+            );
+        }
+    }
+
     /// Handle an accumulated `User` event
     fn handle_user_event(&mut self, core: &mut Core, payload: Vec<u8>) -> Result<(), RoutingError> {
         core.send_event(Event::Consensus(payload));
@@ -1668,8 +3452,10 @@ impl Approved {
 
         self.send_genesis_updates(core);
         self.send_parsec_poke(core);
+        self.vote_for_recovery_snapshot(core);
+        self.send_skip_link_share(core)?;
 
-        self.print_network_stats();
+        self.print_network_stats(core);
 
         if !was_elder {
             info!("Promoted");
@@ -1688,6 +3474,9 @@ impl Approved {
                 .collect(),
         });
 
+        self.report_our_version(core);
+        self.continue_upgrade(core);
+
         Ok(())
     }
 
@@ -1697,8 +3486,11 @@ impl Approved {
         section_key: Proven<bls::PublicKey>,
         elders_info: Proven<EldersInfo>,
     ) -> Result<(), RoutingError> {
-        self.section_keys_provider
-            .finalise_dkg(core.name(), &elders_info.value)?;
+        self.section_keys_provider.finalise_dkg(
+            core.name(),
+            &elders_info.value,
+            self.shared_state.our_history.last_key_index(),
+        )?;
 
         let neighbour_elders_removed = NeighbourEldersRemoved::builder(&self.shared_state.sections);
         self.shared_state
@@ -1764,10 +3556,42 @@ impl Approved {
             AccumulatingEvent::Online { p2p_node, .. } => our_prefix.matches(p2p_node.name()),
             AccumulatingEvent::Offline(name) => our_prefix.matches(name),
             AccumulatingEvent::Relocate(details) => our_prefix.matches(details.pub_id.name()),
+            AccumulatingEvent::Evict(name) => our_prefix.matches(name),
+            AccumulatingEvent::AddressUpdate(p2p_node) => our_prefix.matches(p2p_node.name()),
+            AccumulatingEvent::UpgradeReport { name, .. } => our_prefix.matches(name),
+            // Only re-vote if the notary time is still the next one or the batch still being
+            // signed.
+            AccumulatingEvent::NotaryTick(timestamp) => {
+                self.shared_state.notary.is_next(*timestamp)
+            }
+            AccumulatingEvent::NotaryBatch(batch) => self
+                .shared_state
+                .notary
+                .sealing
+                .as_ref()
+                .map(|(sealing, _)| sealing == batch)
+                .unwrap_or(false),
+            // Only re-vote if we don't have the skip link yet.
+            AccumulatingEvent::SkipLink { index, .. } => {
+                !self.shared_state.skip_links.contains(*index)
+            }
+            // Only re-vote if the round is still the next one.
+            AccumulatingEvent::BeaconRound(input) => {
+                *input == self.shared_state.beacon.next_input()
+            }
+            // Only re-vote if our key hasn't changed in the meantime.
+            AccumulatingEvent::RefreshKey(index)
+            | AccumulatingEvent::DkgFailure {
+                section_key_index: index,
+                ..
+            } => *index == self.shared_state.our_history.last_key_index(),
             // Drop: no longer relevant after prefix change.
             AccumulatingEvent::Genesis { .. }
             | AccumulatingEvent::ParsecPrune
-            | AccumulatingEvent::OurKey { .. } => false,
+            | AccumulatingEvent::OurKey { .. }
+            | AccumulatingEvent::StartRecoverySnapshot(_)
+            | AccumulatingEvent::Decrypt { .. }
+            | AccumulatingEvent::RecoverySnapshot(_) => false,
 
             // Keep: Additional signatures for neighbours for sec-msg-relay.
             AccumulatingEvent::SectionInfo(elders_info) => {
@@ -1782,6 +3606,9 @@ impl Approved {
             // Keep: Still relevant after prefix change.
             AccumulatingEvent::TheirKey { .. }
             | AccumulatingEvent::TheirKnowledge { .. }
+            | AccumulatingEvent::RequestMoreNodes(_)
+            | AccumulatingEvent::Notarise(_)
+            | AccumulatingEvent::ProposeUpgrade { .. }
             | AccumulatingEvent::User(_) => true,
         });
         events
@@ -1955,11 +3782,42 @@ impl Approved {
 
     // Send message over the network.
     pub fn relay_message(&mut self, core: &mut Core, msg: &Message) -> Result<()> {
+        let bytes = if let Some(bytes) = msg.to_relay_bytes(core.name(), &core.network_params) {
+            bytes
+        } else {
+            core.hop_limit_drops += 1;
+            warn!(
+                "Not relaying message that took {} hops already: {:?}",
+                msg.route().hops,
+                msg
+            );
+            return Ok(());
+        };
+
+        // Light clients are not members of any section, so deliver to them directly.
+        if let Some(client) = msg
+            .dst()
+            .as_node()
+            .ok()
+            .and_then(|name| self.light_clients.get(name))
+        {
+            if core
+                .msg_filter
+                .filter_outgoing(msg, client.public_id())
+                .is_new()
+            {
+                core.send_message_to_target(client.peer_addr(), bytes, msg.variant().priority());
+            }
+
+            return Ok(());
+        }
+
         let (targets, dg_size) = delivery_group::delivery_targets(
             msg.dst(),
             core.id(),
             &self.shared_state.our_members,
             &self.shared_state.sections,
+            core.transport.failure_history(),
         )?;
 
         let targets: Vec<_> = targets
@@ -1976,7 +3834,7 @@ impl Approved {
         }
 
         let targets: Vec<_> = targets.into_iter().map(|node| *node.peer_addr()).collect();
-        core.send_message_to_targets(&targets, dg_size, msg.to_bytes());
+        core.send_message_to_targets(&targets, dg_size, bytes, msg.variant().priority());
 
         Ok(())
     }
@@ -2072,7 +3930,7 @@ impl Approved {
 
     // TODO: consider changing this so it sends only to a subset of the elders
     // (say 1/3 of the ones closest to our name or so)
-    fn send_message_to_our_elders(&self, core: &mut Core, msg_bytes: Bytes) {
+    fn send_message_to_our_elders(&self, core: &mut Core, msg_bytes: Bytes, priority: Priority) {
         let targets: Vec<_> = self
             .shared_state
             .sections
@@ -2080,7 +3938,7 @@ impl Approved {
             .map(P2pNode::peer_addr)
             .copied()
             .collect();
-        core.send_message_to_targets(&targets, targets.len(), msg_bytes)
+        core.send_message_to_targets(&targets, targets.len(), msg_bytes, priority)
     }
 
     ////////////////////////////////////////////////////////////////////////////
@@ -2130,7 +3988,11 @@ impl Approved {
         if self
             .shared_state
             .our_members
-            .elder_candidates(core.network_params.elder_size, self.shared_state.our_info())
+            .elder_candidates(
+                core.network_params.elder_size,
+                self.shared_state.our_info(),
+                &self.shared_state.dkg_excluded,
+            )
             .contains_key(core.id().name())
         {
             self.is_our_elder(core.id())
@@ -2139,8 +4001,11 @@ impl Approved {
         }
     }
 
-    fn print_network_stats(&self) {
-        self.shared_state.sections.network_stats().print()
+    fn print_network_stats(&self, core: &Core) {
+        self.shared_state
+            .sections
+            .network_stats()
+            .print(core.transport.queue_depths())
     }
 }
 
@@ -2149,6 +4014,13 @@ pub struct RelocateParams {
     pub details: SignedRelocateDetails,
 }
 
+// Message waiting to be acknowledged by its destination.
+struct PendingAck {
+    dst: DstLocation,
+    // Token of the timer after which we stop waiting for the ack.
+    timer_token: u64,
+}
+
 // Create `EldersInfo` for the first node.
 fn create_first_elders_info(
     pk_set: &bls::PublicKeySet,
@@ -2168,6 +4040,8 @@ fn create_first_shared_state(
     elders_info: Proven<EldersInfo>,
 ) -> Result<SharedState> {
     let mut shared_state = SharedState::new(elders_info, pk_set.public_key());
+    // A new network starts at the protocol version of the node that started it.
+    shared_state.upgrade = Upgrade::new(PROTOCOL_VERSION);
 
     for p2p_node in shared_state.sections.our().elders.values() {
         let proof = create_first_proof(
@@ -11,6 +11,7 @@ use crate::{
     error::Result,
     id::{FullId, P2pNode},
     messages::{BootstrapResponse, Message, MessageStatus, QueuedMessage, Variant, VerifyStatus},
+    protocol::Protocol,
     relocation::{RelocatePayload, SignedRelocateDetails},
     section::EldersInfo,
     time::Duration,
@@ -70,8 +71,18 @@ impl Bootstrapping {
                 Ok(MessageStatus::Useful)
             }
 
+            // Signed by the sender which is verified on deserialisation.
+            Variant::AddressChallenge { .. }
+            | Variant::AddressChallengeResponse { .. }
+            | Variant::MessageRejected { .. } => Ok(MessageStatus::Useful),
+
             Variant::NeighbourInfo { .. }
             | Variant::UserMessage(_)
+            | Variant::UserMessageChunk { .. }
+            | Variant::AckedUserMessage(_)
+            | Variant::Ack(_)
+            | Variant::OrderedUserMessage { .. }
+            | Variant::OrderedResendRequest { .. }
             | Variant::BouncedUntrustedMessage(_)
             | Variant::DKGMessage { .. }
             | Variant::DKGOldElders { .. } => Ok(MessageStatus::Unknown),
@@ -80,13 +91,24 @@ impl Bootstrapping {
             | Variant::GenesisUpdate(_)
             | Variant::Relocate(_)
             | Variant::MessageSignature(_)
-            | Variant::BootstrapRequest(_)
+            | Variant::BootstrapRequest { .. }
             | Variant::JoinRequest(_)
             | Variant::ParsecPoke(_)
             | Variant::ParsecRequest(..)
             | Variant::ParsecResponse(..)
             | Variant::Ping
-            | Variant::BouncedUnknownMessage { .. } => Ok(MessageStatus::Useless),
+            | Variant::BouncedUnknownMessage { .. }
+            | Variant::GenesisDkgMessage { .. }
+            | Variant::GenesisSignatureShares { .. }
+            | Variant::RecoveryCertificate(_)
+            | Variant::DecryptionShare { .. }
+            | Variant::SkipLinkShare { .. }
+            | Variant::SectionProofRequest { .. }
+            | Variant::SectionProofResponse { .. }
+            | Variant::LocateRequest
+            | Variant::LocateResponse(_)
+            | Variant::MembershipCertificateRequest
+            | Variant::MembershipCertificateShare { .. } => Ok(MessageStatus::Useless),
         }
     }
 
@@ -99,6 +121,7 @@ impl Bootstrapping {
         core: &mut Core,
         sender: P2pNode,
         response: BootstrapResponse,
+        protocol: &Protocol,
     ) -> Result<Option<JoinParams>> {
         // Ignore messages from peers we didn't send `BootstrapRequest` to.
         if !self.pending_requests.contains(sender.peer_addr()) {
@@ -110,11 +133,30 @@ impl Bootstrapping {
             return Ok(None);
         }
 
+        if core.check_protocol(sender.peer_addr(), protocol).is_err() {
+            self.abandon_request(core, sender.peer_addr());
+            return Ok(None);
+        }
+
         match response {
             BootstrapResponse::Join {
                 elders_info,
                 section_key,
+                network_id,
             } => {
+                if core
+                    .check_network_id(sender.peer_addr(), network_id)
+                    .is_err()
+                {
+                    self.abandon_request(core, sender.peer_addr());
+                    return Ok(None);
+                }
+
+                // Unless configured, adopt the network id of the section we are joining.
+                if core.network_id.is_none() {
+                    core.network_id = network_id;
+                }
+
                 info!(
                     "Joining a section {:?} (given by {:?})",
                     elders_info, sender
@@ -153,7 +195,22 @@ impl Bootstrapping {
         };
 
         debug!("Sending BootstrapRequest to {}.", dst);
-        core.send_direct_message(&dst, Variant::BootstrapRequest(destination));
+        core.send_direct_message(
+            &dst,
+            Variant::BootstrapRequest {
+                name: destination,
+                network_id: core.network_id,
+            },
+        );
+    }
+
+    // Gives up on the bootstrap request sent to `addr`, rebootstrapping if it was the last one.
+    fn abandon_request(&mut self, core: &mut Core, addr: &SocketAddr) {
+        let _ = self.pending_requests.remove(addr);
+        if self.pending_requests.is_empty() {
+            // Rebootstrap
+            core.transport.bootstrap();
+        }
     }
 
     fn reconnect_to_new_section(&mut self, core: &mut Core, new_conn_infos: Vec<SocketAddr>) {
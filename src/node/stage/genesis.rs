@@ -0,0 +1,533 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::approved::DKG_PROGRESS_INTERVAL;
+use crate::{
+    consensus::{DkgVoter, Proof, Proven},
+    core::Core,
+    crypto::signing::Signature,
+    error::{Result, RoutingError},
+    id::{P2pNode, PublicId},
+    location::DstLocation,
+    messages::{Message, MessageStatus, QueuedMessage, Variant},
+    protocol::PROTOCOL_VERSION,
+    section::{
+        member_info, quorum_count, recovery, EldersInfo, MemberState, RecoveryCertificate,
+        RecoverySnapshot, SectionKeyShare, SharedState, Upgrade, MIN_AGE,
+    },
+};
+use bls_dkg::key_gen::message::Message as DkgMessage;
+use bytes::Bytes;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem,
+    net::SocketAddr,
+};
+use xor_name::{Prefix, XorName};
+
+// The genesis stage - node is starting a new network together with a pre-configured set of other
+// nodes. The nodes run DKG to generate the genesis section key, so no single node ever knows the
+// whole secret key, then sign the genesis section state with their key shares.
+//
+// The same stage is used by the recovery elders of a section that lost all its elders, to
+// generate the new section key and sign the section state restored from the recovery snapshot.
+pub struct Genesis {
+    // The genesis elders, i.e. all the participants of the ceremony.
+    elders_info: EldersInfo,
+    // Members of the section to start, with their ages.
+    members: Vec<(P2pNode, u8)>,
+    // The snapshot we are recovering our section from, if any.
+    recovery_snapshot: Option<Proven<RecoverySnapshot>>,
+    // Parsec version to start the section with.
+    parsec_version: u64,
+    dkg_voter: DkgVoter,
+    // Our share of the genesis key, once DKG completed.
+    key_share: Option<SectionKeyShare>,
+    // Signature shares of the genesis state received from the participants, including ourselves.
+    signature_shares: BTreeMap<XorName, SignatureShares>,
+    // Messages we can't handle until the network is started.
+    msg_backlog: Vec<QueuedMessage>,
+}
+
+impl Genesis {
+    pub fn new(core: &mut Core, participants: Vec<P2pNode>) -> Result<Self> {
+        let elders: BTreeMap<_, _> = participants
+            .into_iter()
+            .map(|p2p_node| (*p2p_node.name(), p2p_node))
+            .collect();
+        let members = elders
+            .values()
+            .map(|p2p_node| (p2p_node.clone(), MIN_AGE))
+            .collect();
+
+        Self::start(
+            core,
+            EldersInfo::new(elders, Prefix::default()),
+            members,
+            None,
+            0,
+        )
+    }
+
+    // Start the recovery of our section from the snapshot, as one of its recovery elders.
+    pub fn recover(core: &mut Core, snapshot: Proven<RecoverySnapshot>) -> Result<Self> {
+        let elders_info = snapshot.value.recovery_elders_info();
+        let members = snapshot.value.survivors().cloned().collect();
+        // Start with a parsec version none of the survivors has used yet.
+        let parsec_version = snapshot.value.parsec_version + 1;
+
+        Self::start(core, elders_info, members, Some(snapshot), parsec_version)
+    }
+
+    fn start(
+        core: &mut Core,
+        elders_info: EldersInfo,
+        members: Vec<(P2pNode, u8)>,
+        recovery_snapshot: Option<Proven<RecoverySnapshot>>,
+        parsec_version: u64,
+    ) -> Result<Self> {
+        if elders_info.elders.get(core.name()).map(P2pNode::public_id) != Some(core.id()) {
+            error!("The genesis nodes don't include us.");
+            return Err(RoutingError::InvalidState);
+        }
+
+        let mut stage = Self {
+            elders_info,
+            members,
+            recovery_snapshot,
+            parsec_version,
+            dkg_voter: Default::default(),
+            key_share: None,
+            signature_shares: BTreeMap::new(),
+            msg_backlog: Vec::new(),
+        };
+
+        let dkg_key = (stage.participants(), 0);
+        for message in stage.dkg_voter.init_dkg_gen(&core.full_id, &dkg_key) {
+            stage.broadcast_dkg_message(core, message)?;
+        }
+        stage
+            .dkg_voter
+            .set_timer_token(core.timer.schedule(DKG_PROGRESS_INTERVAL));
+
+        Ok(stage)
+    }
+
+    pub fn elders_info(&self) -> &EldersInfo {
+        &self.elders_info
+    }
+
+    pub fn handle_timeout(&mut self, core: &mut Core, token: u64) -> Option<GenesisParams> {
+        if self.dkg_voter.timer_token() != token {
+            return None;
+        }
+
+        self.dkg_voter
+            .set_timer_token(core.timer.schedule(DKG_PROGRESS_INTERVAL));
+
+        for (_, message) in self.dkg_voter.progress_dkg(&mut core.rng) {
+            if let Err(error) = self.broadcast_dkg_message(core, message) {
+                debug!("Failed to broadcast genesis DKG message: {:?}", error);
+            }
+        }
+
+        match self.check_dkg(core) {
+            Ok(params) => params,
+            Err(error) => {
+                error!("Failed to complete genesis: {:?}", error);
+                None
+            }
+        }
+    }
+
+    pub fn decide_message_status(&self, msg: &Message) -> Result<MessageStatus> {
+        match msg.variant() {
+            Variant::GenesisDkgMessage { participants, .. } => {
+                if *participants == self.participants() && self.is_participant(msg) {
+                    Ok(MessageStatus::Useful)
+                } else {
+                    Ok(MessageStatus::Useless)
+                }
+            }
+            Variant::GenesisSignatureShares { .. } => {
+                if self.is_participant(msg) {
+                    Ok(MessageStatus::Useful)
+                } else {
+                    Ok(MessageStatus::Useless)
+                }
+            }
+
+            // Signed by the sender which is verified on deserialisation.
+            Variant::AddressChallenge { .. }
+            | Variant::AddressChallengeResponse { .. }
+            | Variant::MessageRejected { .. } => Ok(MessageStatus::Useful),
+
+            Variant::NeighbourInfo { .. }
+            | Variant::UserMessage(_)
+            | Variant::UserMessageChunk { .. }
+            | Variant::AckedUserMessage(_)
+            | Variant::Ack(_)
+            | Variant::OrderedUserMessage { .. }
+            | Variant::OrderedResendRequest { .. }
+            | Variant::MessageSignature(_)
+            | Variant::BootstrapRequest { .. }
+            | Variant::JoinRequest(_)
+            | Variant::ParsecPoke(_)
+            | Variant::ParsecRequest(..)
+            | Variant::ParsecResponse(..)
+            | Variant::BouncedUntrustedMessage(_)
+            | Variant::BouncedUnknownMessage { .. }
+            | Variant::DKGMessage { .. }
+            | Variant::DKGOldElders { .. }
+            | Variant::RecoveryCertificate(_)
+            | Variant::DecryptionShare { .. }
+            | Variant::SkipLinkShare { .. }
+            | Variant::SectionProofRequest { .. }
+            | Variant::LocateRequest
+            | Variant::MembershipCertificateRequest => Ok(MessageStatus::Unknown),
+
+            Variant::NodeApproval(_)
+            | Variant::GenesisUpdate(_)
+            | Variant::Relocate(_)
+            | Variant::BootstrapResponse(_)
+            | Variant::Ping
+            | Variant::SectionProofResponse { .. }
+            | Variant::LocateResponse(_)
+            | Variant::MembershipCertificateShare { .. } => Ok(MessageStatus::Useless),
+        }
+    }
+
+    pub fn handle_unknown_message(&mut self, sender: SocketAddr, msg: Message) {
+        self.msg_backlog.push(msg.into_queued(Some(sender)))
+    }
+
+    pub fn handle_dkg_message(
+        &mut self,
+        core: &mut Core,
+        message_bytes: &Bytes,
+        pub_id: PublicId,
+    ) -> Result<Option<GenesisParams>> {
+        trace!("handle genesis dkg message from {}", pub_id);
+
+        let message = bincode::deserialize(&message_bytes[..])?;
+        let responses = self.dkg_voter.process_dkg_message(
+            &mut core.rng,
+            &(self.participants(), 0),
+            pub_id,
+            message,
+        );
+
+        // Only a valid DkgMessage, which results in some responses, shall reset the ticker.
+        if !responses.is_empty() {
+            self.dkg_voter
+                .set_timer_token(core.timer.schedule(DKG_PROGRESS_INTERVAL));
+        }
+
+        for response in responses {
+            self.broadcast_dkg_message(core, response)?;
+        }
+
+        self.check_dkg(core)
+    }
+
+    pub fn handle_signature_shares(
+        &mut self,
+        public_key: bls::PublicKey,
+        shares: Vec<bls::SignatureShare>,
+        recovery_signature: Option<Signature>,
+        pub_id: PublicId,
+    ) -> Result<Option<GenesisParams>> {
+        trace!("handle genesis signature shares from {}", pub_id);
+
+        let _ = self.signature_shares.insert(
+            *pub_id.name(),
+            SignatureShares {
+                public_key,
+                shares,
+                recovery_signature,
+            },
+        );
+        self.try_complete()
+    }
+
+    fn participants(&self) -> BTreeSet<PublicId> {
+        self.elders_info.elder_ids().copied().collect()
+    }
+
+    fn is_participant(&self, msg: &Message) -> bool {
+        msg.src()
+            .as_node()
+            .map(|pub_id| {
+                self.elders_info
+                    .elders
+                    .get(pub_id.name())
+                    .map(P2pNode::public_id)
+                    == Some(pub_id)
+            })
+            .unwrap_or(false)
+    }
+
+    // Sends the variant to all the participants. Our own copy is queued for local handling.
+    fn send_to_participants(&self, core: &mut Core, variant: Variant) -> Result<()> {
+        for p2p_node in self.elders_info.elders.values() {
+            if p2p_node.public_id() == core.id() {
+                let msg =
+                    Message::single_src(&core.full_id, DstLocation::Direct, None, variant.clone())?;
+                core.msg_queue.push_back(msg.into_queued(None));
+            } else {
+                core.send_direct_message(p2p_node.peer_addr(), variant.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn broadcast_dkg_message(
+        &self,
+        core: &mut Core,
+        dkg_message: DkgMessage<PublicId>,
+    ) -> Result<()> {
+        let variant = Variant::GenesisDkgMessage {
+            participants: self.participants(),
+            message: bincode::serialize(&dkg_message)?.into(),
+        };
+        self.send_to_participants(core, variant)
+    }
+
+    // Once the DKG completes, signs the genesis state with our share of the genesis key and sends
+    // the signature shares to all the participants.
+    fn check_dkg(&mut self, core: &mut Core) -> Result<Option<GenesisParams>> {
+        if self.key_share.is_some() {
+            return Ok(None);
+        }
+
+        let dkg_key = (self.participants(), 0);
+        let (mut completed, _) = self.dkg_voter.check_dkg();
+        let dkg_result = if let Some(dkg_result) = completed.remove(&dkg_key) {
+            dkg_result
+        } else {
+            return Ok(None);
+        };
+        self.dkg_voter.remove_voter(&dkg_key);
+
+        let public_key_set = dkg_result.public_key_set;
+        let secret_key_share = dkg_result
+            .secret_key_share
+            .ok_or(RoutingError::InvalidElderDkgResult)?;
+        let index = self
+            .elders_info
+            .position(core.name())
+            .ok_or(RoutingError::InvalidElderDkgResult)?;
+
+        info!(
+            "Completed genesis DKG - section key: {:?}",
+            public_key_set.public_key()
+        );
+
+        let shares = self
+            .payloads()?
+            .iter()
+            .map(|payload| secret_key_share.sign(payload))
+            .collect();
+        let recovery_signature = self
+            .recovery_payload(&public_key_set.public_key())?
+            .map(|payload| core.full_id.sign(&payload));
+        let variant = Variant::GenesisSignatureShares {
+            public_key: public_key_set.public_key(),
+            shares,
+            recovery_signature,
+        };
+
+        self.key_share = Some(SectionKeyShare {
+            public_key_set,
+            index,
+            secret_key_share,
+        });
+
+        self.send_to_participants(core, variant)?;
+        self.try_complete()
+    }
+
+    // Combines the signature shares into the genesis state once we have enough of them.
+    fn try_complete(&mut self) -> Result<Option<GenesisParams>> {
+        let key_share = if let Some(key_share) = &self.key_share {
+            key_share
+        } else {
+            return Ok(None);
+        };
+        let public_key_set = &key_share.public_key_set;
+        let public_key = public_key_set.public_key();
+        let payloads = self.payloads()?;
+
+        let valid_shares: BTreeMap<_, _> = self
+            .signature_shares
+            .iter()
+            .filter(|(_, received)| {
+                received.public_key == public_key && received.shares.len() == payloads.len()
+            })
+            .filter_map(|(name, received)| {
+                let index = self.elders_info.position(name)?;
+                let public_key_share = public_key_set.public_key_share(index);
+                if received
+                    .shares
+                    .iter()
+                    .zip(&payloads)
+                    .all(|(share, payload)| public_key_share.verify(share, payload))
+                {
+                    Some((index, &received.shares))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if valid_shares.len() <= public_key_set.threshold() {
+            return Ok(None);
+        }
+
+        let recovery_signatures = if let Some(payload) = self.recovery_payload(&public_key)? {
+            let signatures: BTreeMap<_, _> = self
+                .signature_shares
+                .iter()
+                .filter_map(|(name, received)| {
+                    let pub_id = self.elders_info.elders.get(name)?.public_id();
+                    let signature = received.recovery_signature.as_ref()?;
+                    if received.public_key == public_key && pub_id.verify(&payload, signature) {
+                        Some((*pub_id, *signature))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            if signatures.len() < quorum_count(self.elders_info.elders.len()) {
+                return Ok(None);
+            }
+
+            Some(signatures)
+        } else {
+            None
+        };
+
+        let mut proofs = Vec::with_capacity(payloads.len());
+        for payload_index in 0..payloads.len() {
+            let signature = public_key_set
+                .combine_signatures(
+                    valid_shares
+                        .iter()
+                        .map(|(index, shares)| (*index, &shares[payload_index])),
+                )
+                .map_err(|_| RoutingError::InvalidSignatureShares)?;
+            proofs.push(Proof {
+                public_key,
+                signature,
+            });
+        }
+        let mut proofs = proofs.into_iter();
+
+        // Note: as there is no previous key, the genesis `EldersInfo` is signed with the genesis
+        // key itself, same as for the first node.
+        let elders_info_proof = proofs.next().ok_or(RoutingError::InvalidSignatureShares)?;
+        let elders_info = Proven::new(self.elders_info.clone(), elders_info_proof);
+        let mut shared_state = SharedState::new(elders_info.clone(), public_key);
+        // A new network, or a recovered section, starts at the protocol version of the nodes that
+        // started it.
+        shared_state.upgrade = Upgrade::new(PROTOCOL_VERSION);
+        for ((p2p_node, age), proof) in self.members.iter().zip(&mut proofs) {
+            shared_state.our_members.add(p2p_node.clone(), *age, proof);
+        }
+        for (neighbour_info, proof) in self.neighbours().iter().zip(&mut proofs) {
+            shared_state
+                .sections
+                .add_neighbour(Proven::new(neighbour_info.clone(), proof));
+        }
+        for (their_key, proof) in self.their_keys().iter().zip(&mut proofs) {
+            shared_state
+                .sections
+                .update_keys(Proven::new(*their_key, proof));
+        }
+
+        let recovery = match (&self.recovery_snapshot, recovery_signatures) {
+            (Some(snapshot), Some(signatures)) => Some(RecoveryCertificate {
+                snapshot: snapshot.clone(),
+                elders_info,
+                signatures,
+            }),
+            _ => None,
+        };
+
+        Ok(Some(GenesisParams {
+            shared_state,
+            section_key_share: key_share.clone(),
+            parsec_version: self.parsec_version,
+            recovery,
+            msg_backlog: mem::take(&mut self.msg_backlog),
+        }))
+    }
+
+    // The serialised payloads signed by the genesis key: the genesis `EldersInfo`, the join of
+    // every member, then the neighbour `EldersInfo`s and keys restored from the recovery
+    // snapshot.
+    fn payloads(&self) -> Result<Vec<Vec<u8>>> {
+        let mut payloads = vec![bincode::serialize(&self.elders_info)?];
+        for (p2p_node, _) in &self.members {
+            payloads.push(bincode::serialize(&member_info::to_sign(
+                p2p_node.name(),
+                MemberState::Joined,
+            ))?);
+        }
+        for neighbour_info in self.neighbours() {
+            payloads.push(bincode::serialize(neighbour_info)?);
+        }
+        for their_key in self.their_keys() {
+            payloads.push(bincode::serialize(their_key)?);
+        }
+        Ok(payloads)
+    }
+
+    // The serialised link between the key of the recovery snapshot and the new section key, if
+    // we are recovering.
+    fn recovery_payload(&self, new_key: &bls::PublicKey) -> Result<Option<Vec<u8>>> {
+        if let Some(snapshot) = &self.recovery_snapshot {
+            let to_sign = recovery::to_sign(&snapshot.value, &snapshot.proof.public_key, new_key);
+            Ok(Some(bincode::serialize(&to_sign)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn neighbours(&self) -> &[EldersInfo] {
+        self.recovery_snapshot
+            .as_ref()
+            .map(|snapshot| &snapshot.value.neighbours[..])
+            .unwrap_or(&[])
+    }
+
+    fn their_keys(&self) -> &[(Prefix, bls::PublicKey)] {
+        self.recovery_snapshot
+            .as_ref()
+            .map(|snapshot| &snapshot.value.their_keys[..])
+            .unwrap_or(&[])
+    }
+}
+
+// Signature shares of the genesis state received from a participant.
+struct SignatureShares {
+    public_key: bls::PublicKey,
+    shares: Vec<bls::SignatureShare>,
+    recovery_signature: Option<Signature>,
+}
+
+pub struct GenesisParams {
+    pub shared_state: SharedState,
+    pub section_key_share: SectionKeyShare,
+    pub parsec_version: u64,
+    // Certificate of the new section key, if we recovered our section.
+    pub recovery: Option<RecoveryCertificate>,
+    pub msg_backlog: Vec<QueuedMessage>,
+}
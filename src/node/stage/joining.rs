@@ -15,6 +15,7 @@ use crate::{
         self, BootstrapResponse, JoinRequest, Message, MessageStatus, QueuedMessage, Variant,
         VerifyStatus,
     },
+    network_id::NetworkId,
     relocation::RelocatePayload,
     section::EldersInfo,
 };
@@ -94,23 +95,44 @@ impl Joining {
                 Ok(MessageStatus::Useful)
             }
 
+            // Signed by the sender which is verified on deserialisation.
+            Variant::AddressChallenge { .. }
+            | Variant::AddressChallengeResponse { .. }
+            | Variant::MessageRejected { .. } => Ok(MessageStatus::Useful),
+
             Variant::NeighbourInfo { .. }
             | Variant::UserMessage(_)
+            | Variant::UserMessageChunk { .. }
+            | Variant::AckedUserMessage(_)
+            | Variant::Ack(_)
+            | Variant::OrderedUserMessage { .. }
+            | Variant::OrderedResendRequest { .. }
             | Variant::GenesisUpdate(_)
             | Variant::Relocate(_)
             | Variant::MessageSignature(_)
             | Variant::BouncedUntrustedMessage(_)
             | Variant::BouncedUnknownMessage { .. }
             | Variant::DKGMessage { .. }
-            | Variant::DKGOldElders { .. } => Ok(MessageStatus::Unknown),
+            | Variant::DKGOldElders { .. }
+            | Variant::RecoveryCertificate(_)
+            | Variant::DecryptionShare { .. }
+            | Variant::SkipLinkShare { .. } => Ok(MessageStatus::Unknown),
 
-            Variant::BootstrapRequest(_)
+            Variant::BootstrapRequest { .. }
             | Variant::BootstrapResponse(_)
             | Variant::JoinRequest(_)
             | Variant::ParsecPoke(_)
             | Variant::ParsecRequest(..)
             | Variant::ParsecResponse(..)
-            | Variant::Ping => Ok(MessageStatus::Useless),
+            | Variant::Ping
+            | Variant::GenesisDkgMessage { .. }
+            | Variant::GenesisSignatureShares { .. }
+            | Variant::SectionProofRequest { .. }
+            | Variant::SectionProofResponse { .. }
+            | Variant::LocateRequest
+            | Variant::LocateResponse(_)
+            | Variant::MembershipCertificateRequest
+            | Variant::MembershipCertificateShare { .. } => Ok(MessageStatus::Useless),
         }
     }
 
@@ -124,7 +146,10 @@ impl Joining {
         sender: P2pNode,
         new_elders_info: EldersInfo,
         new_section_key: bls::PublicKey,
+        network_id: Option<NetworkId>,
     ) -> Result<()> {
+        core.check_network_id(sender.peer_addr(), network_id)?;
+
         if new_section_key == self.section_key {
             return Ok(());
         }
@@ -176,6 +201,7 @@ impl Joining {
         for dst in self.elders_info.elders.values() {
             let join_request = JoinRequest {
                 section_key: self.section_key,
+                network_id: core.network_id,
                 relocate_payload: relocate_payload.cloned(),
             };
 
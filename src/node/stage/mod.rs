@@ -8,20 +8,26 @@
 
 mod approved;
 mod bootstrapping;
+mod genesis;
 mod joining;
 
 pub use self::{
-    approved::{Approved, RelocateParams},
+    approved::{Approved, RelocateParams, ACK_TIMEOUT},
     bootstrapping::{Bootstrapping, JoinParams},
+    genesis::{Genesis, GenesisParams},
     joining::Joining,
 };
 
 #[cfg(feature = "mock_base")]
 pub use self::{bootstrapping::BOOTSTRAP_TIMEOUT, joining::JOIN_TIMEOUT};
 
+#[cfg(all(test, feature = "mock"))]
+pub(crate) use self::approved::ORDERED_GAP_CHECK_INTERVAL;
+
 // Type to represent the various stages a node goes through during its lifetime.
 #[allow(clippy::large_enum_variant)]
 pub enum Stage {
+    Genesis(Genesis),
     Bootstrapping(Bootstrapping),
     Joining(Joining),
     Approved(Approved),
@@ -8,16 +8,20 @@
 
 use super::utils::{self as test_utils, MockTransport};
 use crate::{
-    consensus::{self, GenesisPrefixInfo},
+    consensus::{self, GenesisPrefixInfo, Proof, Proven},
     error::Result,
-    id::FullId,
+    id::{FullId, P2pNode},
     location::DstLocation,
     messages::{AccumulatingMessage, Message, PlainMessage, Variant},
-    network_params::NetworkParams,
+    network_params::{NetworkParams, COMPRESSION_THRESHOLD, MAX_HOPS, MAX_INFANTS},
     node::{Node, NodeConfig},
     rng::{self, MainRng},
-    section::{EldersInfo, SectionKeyShare, SectionKeysProvider, SharedState},
+    section::{
+        recovery, EldersInfo, RecoveryCertificate, RecoverySnapshot, SectionKeyShare,
+        SectionKeysProvider, SharedState, MIN_AGE,
+    },
 };
+use serde::Serialize;
 
 use mock_quic_p2p::Network;
 use rand::Rng;
@@ -28,6 +32,11 @@ const ELDER_SIZE: usize = 3;
 const NETWORK_PARAMS: NetworkParams = NetworkParams {
     elder_size: ELDER_SIZE,
     recommended_section_size: ELDER_SIZE + 1,
+    max_infants: MAX_INFANTS,
+    compression: None,
+    compression_threshold: COMPRESSION_THRESHOLD,
+    max_hops: MAX_HOPS,
+    record_route: false,
 };
 
 struct Env {
@@ -125,6 +134,100 @@ impl Env {
         self.accumulate_message(msg)
     }
 
+    // Makes the subject lose contact with all the elders.
+    fn lose_elders(&mut self) {
+        for elder in &self.elders {
+            test_utils::bind_peer(&mut self.subject, *elder.addr(), *elder.full_id.public_id());
+            self.subject.handle_peer_lost(*elder.addr());
+        }
+    }
+
+    // Creates the certificate of our section recovered by new adults, from a snapshot of the
+    // section with the given elders signed by the current section key.
+    fn create_recovery_certificate(&mut self, elders_info: EldersInfo) -> RecoveryCertificate {
+        let full_ids: Vec<_> = (0..ELDER_SIZE)
+            .map(|_| FullId::gen(&mut self.rng))
+            .collect();
+        let snapshot = RecoverySnapshot {
+            index: 0,
+            parsec_version: 0,
+            elders_info,
+            members: full_ids
+                .iter()
+                .map(|full_id| {
+                    let p2p_node = P2pNode::new(*full_id.public_id(), self.network.gen_addr());
+                    (p2p_node, MIN_AGE + 1)
+                })
+                .collect(),
+            neighbours: vec![],
+            their_keys: vec![],
+            history: self.elders[0].state.our_history.clone(),
+        };
+        let snapshot = self.prove_by_section(snapshot);
+
+        let new_secret_key = consensus::test_utils::gen_secret_key(&mut self.rng);
+        let elders_info =
+            consensus::test_utils::proven(&new_secret_key, snapshot.value.recovery_elders_info());
+        let bytes = bincode::serialize(&recovery::to_sign(
+            &snapshot.value,
+            &snapshot.proof.public_key,
+            &new_secret_key.public_key(),
+        ))
+        .unwrap();
+        let signatures = full_ids
+            .iter()
+            .map(|full_id| (*full_id.public_id(), full_id.sign(&bytes)))
+            .collect();
+
+        RecoveryCertificate {
+            snapshot,
+            elders_info,
+            signatures,
+        }
+    }
+
+    fn handle_recovery_certificate(&mut self, certificate: RecoveryCertificate) -> Result<()> {
+        let msg = Message::single_src(
+            &FullId::gen(&mut self.rng),
+            DstLocation::Direct,
+            None,
+            Variant::RecoveryCertificate(Box::new(certificate)),
+        )?;
+        test_utils::handle_message(&mut self.subject, self.network.gen_addr(), msg)
+    }
+
+    fn prove_by_section<T: Serialize>(&self, value: T) -> Proven<T> {
+        let bytes = bincode::serialize(&value).unwrap();
+        let public_key_set = &self.elders[0]
+            .section_keys_provider
+            .key_share()
+            .unwrap()
+            .public_key_set;
+        let signature_shares: Vec<_> = self
+            .elders
+            .iter()
+            .map(|elder| {
+                let key_share = elder.section_keys_provider.key_share().unwrap();
+                (key_share.index, key_share.secret_key_share.sign(&bytes))
+            })
+            .collect();
+        let signature = public_key_set
+            .combine_signatures(
+                signature_shares
+                    .iter()
+                    .map(|(index, share)| (*index, share)),
+            )
+            .unwrap();
+
+        Proven::new(
+            value,
+            Proof {
+                public_key: public_key_set.public_key(),
+                signature,
+            },
+        )
+    }
+
     fn accumulate_message(&self, content: PlainMessage) -> Message {
         test_utils::accumulate_messages(
             self.elders
@@ -371,3 +474,36 @@ fn handle_untrusted_accumulating_message() {
 
     panic!("BouncedUntrustedMessage not received")
 }
+
+#[test]
+fn handle_recovery_certificate() {
+    let mut env = Env::new();
+    let old_key = *env.subject.section_key().unwrap();
+
+    let elders_info = env.elders[0].state.our_info().clone();
+    let certificate = env.create_recovery_certificate(elders_info);
+    let new_key = *certificate.new_key();
+
+    // Ignored while our elders are reachable.
+    env.handle_recovery_certificate(certificate.clone())
+        .unwrap();
+    assert_eq!(env.subject.section_key(), Some(&old_key));
+
+    env.lose_elders();
+    env.handle_recovery_certificate(certificate).unwrap();
+    assert_eq!(env.subject.section_key(), Some(&new_key));
+}
+
+#[test]
+fn handle_recovery_certificate_with_outdated_snapshot() {
+    let mut env = Env::new();
+    let old_key = *env.subject.section_key().unwrap();
+
+    // Snapshot of the section with elders other than the current ones.
+    let (elders_info, _) = test_utils::create_elders_info(&mut env.rng, &env.network, ELDER_SIZE);
+    let certificate = env.create_recovery_certificate(elders_info.value);
+
+    env.lose_elders();
+    env.handle_recovery_certificate(certificate).unwrap();
+    assert_eq!(env.subject.section_key(), Some(&old_key));
+}
@@ -32,7 +32,8 @@ fn lose_proxy_connection() {
     };
     let node_a_endpoint = env.gen_addr();
     let node_a_config = TransportConfig::node().with_endpoint(node_a_endpoint);
-    let node_a_network_service = Transport::new(node_a_event_tx, node_a_config).unwrap();
+    let node_a_network_service =
+        Transport::new(node_a_event_tx, node_a_config, Default::default()).unwrap();
 
     // Construct a node "B" which will start in the bootstrapping stage and bootstrap off the
     // network service above.
@@ -70,7 +71,7 @@ fn lose_proxy_connection() {
 
         let message = Message::from_bytes(&msg).unwrap();
         match message.variant() {
-            Variant::BootstrapRequest(_) => (),
+            Variant::BootstrapRequest { .. } => (),
             _ => panic!("Should have received a `BootstrapRequest`."),
         };
     } else {
@@ -8,21 +8,30 @@
 
 use super::utils::{self as test_utils, MockTransport};
 use crate::{
-    consensus::{self, AccumulatingEvent, ParsecRequest},
+    consensus::{self, AccumulatingEvent, ParsecRequest, Proof, Proven},
     error::Result,
+    event::Event,
     id::{FullId, P2pNode, PublicId},
     location::DstLocation,
     messages::{
-        AccumulatingMessage, BootstrapResponse, Message, PlainMessage, SrcAuthority, Variant,
+        AccumulatingMessage, BootstrapResponse, JoinRequest, Message, MessageHash, PlainMessage,
+        SrcAuthority, Variant, VerifyStatus,
     },
-    node::{Node, NodeConfig},
+    network_id::NetworkId,
+    network_params::{Compression, NetworkParams},
+    node::{stage::ORDERED_GAP_CHECK_INTERVAL, Node, NodeConfig, ACK_TIMEOUT},
+    notary::NOTARY_TIMESTAMP_GRANULARITY,
+    protocol::{COMPRESSION_VERSION, PROTOCOL_VERSION},
     rng::{self, MainRng},
     section::{
-        member_info, EldersInfo, MemberState, SectionKeyShare, SectionProofChain, SharedState,
-        MIN_AGE,
+        member_info, BeaconInput, CompactProofChain, EldersInfo, LocatedSection, MemberState,
+        SectionKeyShare, SectionProofChain, SharedState, SkipLinks, TrustStatus, Upgrade, MIN_AGE,
     },
     utils, ELDER_SIZE,
 };
+use bytes::Bytes;
+use crossbeam_channel::{self as mpmc, Receiver};
+use fake_clock::FakeClock;
 use itertools::Itertools;
 use mock_quic_p2p::Network;
 use rand::Rng;
@@ -44,6 +53,7 @@ struct Env {
     pub rng: MainRng,
     pub network: Network,
     pub subject: Node,
+    pub event_rx: Receiver<Event>,
     pub other_ids: Vec<(FullId, bls::SecretKeyShare)>,
     pub elders_info: EldersInfo,
     pub public_key_set: bls::PublicKeySet,
@@ -52,12 +62,37 @@ struct Env {
 
 impl Env {
     fn new(sec_size: usize) -> Self {
+        Self::with_network_params(sec_size, NetworkParams::default())
+    }
+
+    fn with_network_params(sec_size: usize, network_params: NetworkParams) -> Self {
+        Self::with_active_version(sec_size, network_params, PROTOCOL_VERSION)
+    }
+
+    fn with_active_version(
+        sec_size: usize,
+        network_params: NetworkParams,
+        active_version: u16,
+    ) -> Self {
+        Self::build(sec_size, network_params, active_version, Prefix::default())
+    }
+
+    fn with_prefix(sec_size: usize, prefix: Prefix) -> Self {
+        Self::build(sec_size, NetworkParams::default(), PROTOCOL_VERSION, prefix)
+    }
+
+    fn build(
+        sec_size: usize,
+        network_params: NetworkParams,
+        active_version: u16,
+        prefix: Prefix,
+    ) -> Self {
         let mut rng = rng::new();
         let network = Network::new();
 
-        let (proven_elders_info, full_ids) =
-            test_utils::create_elders_info(&mut rng, &network, sec_size);
-        let elders_info = proven_elders_info.value.clone();
+        let (elders_info, full_ids) =
+            test_utils::create_elders_info_with_prefix(&mut rng, &network, sec_size, prefix);
+        let elders_info = elders_info.value;
 
         let secret_key_set = consensus::generate_secret_key_set(&mut rng, full_ids.len());
         let public_key_set = secret_key_set.public_keys();
@@ -72,7 +107,11 @@ impl Env {
         let (full_id, secret_key_share) = full_and_bls_ids.remove(0);
         let other_ids = full_and_bls_ids;
 
-        let mut shared_state = SharedState::new(proven_elders_info, public_key);
+        // Our first `EldersInfo` is signed with the section key, as it is by the genesis node.
+        let proof = test_utils::create_proof(&secret_key_set, &elders_info);
+        let mut shared_state =
+            SharedState::new(Proven::new(elders_info.clone(), proof), public_key);
+        shared_state.upgrade = Upgrade::new(active_version);
         for p2p_node in elders_info.elders.values() {
             let proof = test_utils::create_proof(
                 &secret_key_set,
@@ -89,9 +128,11 @@ impl Env {
             secret_key_share,
         };
 
-        let (subject, ..) = Node::approved(
+        let (subject, event_rx, _) = Node::approved(
             NodeConfig {
                 full_id: Some(full_id),
+                network_id: Some(NetworkId::from_genesis_key(&public_key)),
+                network_params,
                 ..Default::default()
             },
             shared_state,
@@ -105,6 +146,7 @@ impl Env {
             rng,
             network,
             subject,
+            event_rx,
             other_ids,
             elders_info,
             public_key_set: secret_key_set.public_keys(),
@@ -267,6 +309,10 @@ impl Env {
         let _ = self.create_gossip();
     }
 
+    fn accumulate_event(&mut self, event: AccumulatingEvent) {
+        let _ = self.n_vote_for_gossipped(ACCUMULATE_VOTE_COUNT, iter::once(event));
+    }
+
     fn accumulate_offline(&mut self, offline_payload: XorName) {
         let _ = self.n_vote_for_gossipped(
             ACCUMULATE_VOTE_COUNT,
@@ -350,6 +396,10 @@ impl Env {
         self.updated_other_ids(new_elders_info)
     }
 
+    fn network_id(&self) -> NetworkId {
+        NetworkId::from_genesis_key(&self.public_key_set.public_key())
+    }
+
     fn has_unpolled_observations(&self) -> bool {
         self.subject.has_unpolled_observations()
     }
@@ -491,6 +541,305 @@ fn when_accumulate_online_then_node_is_added_to_our_members() {
     assert!(!env.is_candidate_elder());
 }
 
+#[test]
+fn when_accumulate_request_more_nodes_then_infants_are_admitted_over_the_limit() {
+    let max_infants = 0;
+    let mut env = Env::with_network_params(
+        ELDER_SIZE - 1,
+        NetworkParams {
+            max_infants,
+            ..Default::default()
+        },
+    );
+    assert!(!is_accepting_infants(&env, max_infants));
+
+    // Agreeing on the second request agrees on the first one too.
+    env.accumulate_event(AccumulatingEvent::RequestMoreNodes(1));
+    assert_eq!(requested_members(&env), 2);
+    env.accumulate_event(AccumulatingEvent::RequestMoreNodes(0));
+    assert_eq!(requested_members(&env), 2);
+    assert!(is_accepting_infants(&env, max_infants));
+
+    env.accumulate_online(env.candidate.clone());
+    assert!(env.is_candidate_member());
+    assert_eq!(requested_members(&env), 1);
+}
+
+#[test]
+fn infant_joins_below_the_limit_are_not_counted_against_requested_nodes() {
+    let mut env = Env::new(ELDER_SIZE - 1);
+    env.accumulate_event(AccumulatingEvent::RequestMoreNodes(0));
+    env.accumulate_event(AccumulatingEvent::RequestMoreNodes(1));
+
+    env.accumulate_online(env.candidate.clone());
+    assert!(env.is_candidate_member());
+    assert_eq!(requested_members(&env), 2);
+}
+
+#[test]
+fn requests_for_more_nodes_are_voted_for_one_node_at_a_time() {
+    let mut env = Env::new(ELDER_SIZE - 1);
+    env.accumulate_event(AccumulatingEvent::RequestMoreNodes(0));
+
+    // Our votes continue from the requests already agreed on and from our previous votes.
+    env.subject.request_more_nodes(2).unwrap();
+    env.subject.request_more_nodes(1).unwrap();
+
+    let indices: Vec<_> = env
+        .subject
+        .consensus_engine()
+        .unwrap()
+        .parsec_map()
+        .our_unpolled_observations()
+        .filter_map(|obs| match obs {
+            consensus::Observation::OpaquePayload(event) => match event.payload {
+                AccumulatingEvent::RequestMoreNodes(index) => Some(index),
+                _ => None,
+            },
+            _ => None,
+        })
+        .sorted()
+        .collect();
+    assert_eq!(indices, vec![1, 2, 3]);
+}
+
+#[test]
+fn when_accumulate_evict_then_node_is_removed_from_our_members() {
+    let mut env = Env::new(ELDER_SIZE - 1);
+    env.accumulate_online(env.candidate.clone());
+    assert!(env.is_candidate_member());
+
+    env.accumulate_event(AccumulatingEvent::Evict(*env.candidate.name()));
+
+    assert!(!env.has_unpolled_observations());
+    assert!(!env.is_candidate_member());
+}
+
+#[test]
+fn when_accumulate_refresh_key_then_dkg_starts_among_current_elders() {
+    let mut env = Env::new(ELDER_SIZE);
+    let other_node = env.create_transport_for_other_elder(0);
+    let expected_participants: BTreeSet<_> = env.elders_info.elder_ids().copied().collect();
+
+    env.accumulate_event(AccumulatingEvent::RefreshKey(0));
+    env.poll();
+
+    assert!(!env.has_unpolled_observations());
+    assert!(other_node
+        .received_messages()
+        .any(|(_, msg)| match msg.variant() {
+            Variant::DKGMessage {
+                participants,
+                section_key_index,
+                ..
+            } => *participants == expected_participants && *section_key_index == 0,
+            _ => false,
+        }));
+}
+
+#[test]
+fn when_accumulate_dkg_failure_then_dkg_restarts_without_non_contributors() {
+    let mut env = Env::new(ELDER_SIZE);
+    env.accumulate_online(env.candidate.clone());
+    assert!(!env.is_candidate_elder());
+
+    let other_node = env.create_transport_for_other_elder(1);
+    let participants: BTreeSet<_> = env.elders_info.elder_ids().copied().collect();
+    let non_contributor = *env.other_ids[0].0.public_id();
+
+    env.accumulate_event(AccumulatingEvent::DkgFailure {
+        participants,
+        section_key_index: 0,
+        non_contributor,
+    });
+    env.poll();
+
+    assert!(other_node
+        .received_messages()
+        .any(|(_, msg)| match msg.variant() {
+            Variant::DKGMessage { participants, .. } => {
+                participants.contains(env.candidate.public_id())
+                    && !participants.contains(&non_contributor)
+            }
+            _ => false,
+        }));
+}
+
+#[test]
+fn when_accumulate_decrypt_then_decryption_share_is_sent_to_other_elders() {
+    let mut env = Env::new(ELDER_SIZE);
+    let other_node = env.create_transport_for_other_elder(0);
+    let ciphertext = env.public_key_set.public_key().encrypt(b"sealed bid");
+
+    env.accumulate_event(AccumulatingEvent::Decrypt {
+        key_index: 0,
+        ciphertext: Box::new(ciphertext.clone()),
+    });
+    env.poll();
+
+    let public_key_set = env.public_key_set.clone();
+    assert!(other_node
+        .received_messages()
+        .any(|(_, msg)| match msg.variant() {
+            Variant::DecryptionShare {
+                ciphertext: received,
+                index,
+                share,
+            } => {
+                **received == ciphertext
+                    && public_key_set
+                        .public_key_share(*index)
+                        .verify_decryption_share(share, &ciphertext)
+            }
+            _ => false,
+        }));
+}
+
+#[test]
+fn when_accumulate_decrypt_for_other_key_then_it_is_ignored() {
+    let mut env = Env::new(ELDER_SIZE);
+    let other_node = env.create_transport_for_other_elder(0);
+    let ciphertext = env.public_key_set.public_key().encrypt(b"sealed bid");
+
+    // We don't hold the share of any key but the one at index 0.
+    env.accumulate_event(AccumulatingEvent::Decrypt {
+        key_index: 1,
+        ciphertext: Box::new(ciphertext),
+    });
+    env.poll();
+
+    assert!(!other_node
+        .received_messages()
+        .any(|(_, msg)| matches!(msg.variant(), Variant::DecryptionShare { .. })));
+}
+
+#[test]
+fn when_accumulate_beacon_rounds_then_they_are_chained() {
+    let mut env = Env::new(ELDER_SIZE);
+    let public_key = env.public_key_set.public_key();
+
+    let first = BeaconInput {
+        round: 0,
+        previous: None,
+    };
+    env.accumulate_event(AccumulatingEvent::BeaconRound(first.clone()));
+    let first_value = env.subject.beacon(0).cloned().expect("round 0 missing");
+    assert!(public_key.verify(&first_value, &bincode::serialize(&first).unwrap()));
+
+    let second = BeaconInput {
+        round: 1,
+        previous: Some(first_value),
+    };
+    env.accumulate_event(AccumulatingEvent::BeaconRound(second.clone()));
+    let second_value = env.subject.beacon(1).expect("round 1 missing");
+    assert!(public_key.verify(second_value, &bincode::serialize(&second).unwrap()));
+}
+
+#[test]
+fn when_accumulate_notary_batch_then_pending_hashes_are_notarised() {
+    let mut env = Env::new(ELDER_SIZE);
+    let hash: [u8; 32] = env.rng.gen();
+
+    env.accumulate_event(AccumulatingEvent::Notarise(hash));
+    env.accumulate_event(AccumulatingEvent::NotaryTick(NOTARY_TIMESTAMP_GRANULARITY));
+
+    let notary = &env.subject.shared_state().unwrap().notary;
+    assert!(notary.pending.is_empty());
+    let batch = match &notary.sealing {
+        Some((batch, hashes)) if *hashes == [hash] => batch.clone(),
+        _ => panic!("batch not sealed"),
+    };
+    assert_eq!(batch.timestamp, NOTARY_TIMESTAMP_GRANULARITY);
+
+    env.accumulate_event(AccumulatingEvent::NotaryBatch(batch));
+
+    let notary = &env.subject.shared_state().unwrap().notary;
+    assert!(notary.sealing.is_none());
+    assert_eq!(notary.next_index, 1);
+}
+
+#[test]
+fn notary_time_only_advances_one_step_at_a_time() {
+    let mut env = Env::new(ELDER_SIZE);
+    let start = 100 * NOTARY_TIMESTAMP_GRANULARITY;
+
+    env.accumulate_event(AccumulatingEvent::NotaryTick(start));
+    assert_eq!(env.subject.shared_state().unwrap().notary.timestamp, start);
+
+    // Neither a skipped step nor a stale one is accepted.
+    env.accumulate_event(AccumulatingEvent::NotaryTick(
+        start + 2 * NOTARY_TIMESTAMP_GRANULARITY,
+    ));
+    env.accumulate_event(AccumulatingEvent::NotaryTick(start));
+    assert_eq!(env.subject.shared_state().unwrap().notary.timestamp, start);
+
+    env.accumulate_event(AccumulatingEvent::NotaryTick(
+        start + NOTARY_TIMESTAMP_GRANULARITY,
+    ));
+    assert_eq!(
+        env.subject.shared_state().unwrap().notary.timestamp,
+        start + NOTARY_TIMESTAMP_GRANULARITY
+    );
+}
+
+#[test]
+fn when_accumulate_upgrade_reports_of_all_elders_then_upgrade_completes() {
+    let mut env = Env::new(ELDER_SIZE);
+    let version = PROTOCOL_VERSION + 1;
+
+    env.accumulate_event(AccumulatingEvent::ProposeUpgrade {
+        version,
+        allow_quorum_loss: false,
+    });
+    assert_eq!(
+        env.subject.shared_state().unwrap().upgrade.target(),
+        Some(version)
+    );
+
+    let names: Vec<_> = env.elders_info.elders.keys().copied().collect();
+    for name in names {
+        assert_eq!(
+            env.subject.active_protocol_version(),
+            Some(PROTOCOL_VERSION)
+        );
+        env.accumulate_event(AccumulatingEvent::UpgradeReport { name, version });
+    }
+
+    assert_eq!(env.subject.active_protocol_version(), Some(version));
+    assert_eq!(env.subject.shared_state().unwrap().upgrade.target(), None);
+}
+
+#[test]
+fn compression_switches_on_once_all_elders_run_its_version() {
+    let network_params = NetworkParams {
+        compression: Some(Compression::Snappy),
+        ..Default::default()
+    };
+    // The section still runs the version preceding compression.
+    let mut env = Env::with_active_version(ELDER_SIZE, network_params, COMPRESSION_VERSION - 1);
+    assert_eq!(env.subject.core.compression(), None);
+
+    env.accumulate_event(AccumulatingEvent::ProposeUpgrade {
+        version: PROTOCOL_VERSION,
+        allow_quorum_loss: false,
+    });
+
+    let names: Vec<_> = env.elders_info.elders.keys().copied().collect();
+    for name in names {
+        assert_eq!(env.subject.core.compression(), None);
+        env.accumulate_event(AccumulatingEvent::UpgradeReport {
+            name,
+            version: PROTOCOL_VERSION,
+        });
+    }
+
+    assert_eq!(
+        env.subject.active_protocol_version(),
+        Some(PROTOCOL_VERSION)
+    );
+    assert_eq!(env.subject.core.compression(), Some(Compression::Snappy));
+}
+
 #[test]
 #[ignore] //FIXME DKG is no longer carried out by parsec
 fn when_accumulate_online_and_start_dkg_and_section_info_then_node_is_added_to_our_elders() {
@@ -556,18 +905,252 @@ fn handle_bootstrap() {
     let new_node = OtherNode::new(&mut env.rng);
 
     let addr = *new_node.addr();
-    let msg = new_node.bootstrap_request().unwrap();
+    let msg = new_node.bootstrap_request(None).unwrap();
 
     test_utils::handle_message(&mut env.subject, addr, msg).unwrap();
     env.poll();
 
     let response = new_node.expect_bootstrap_response();
     match response {
-        BootstrapResponse::Join { elders_info, .. } => assert_eq!(elders_info, env.elders_info),
+        BootstrapResponse::Join {
+            elders_info,
+            network_id,
+            ..
+        } => {
+            assert_eq!(elders_info, env.elders_info);
+            assert_eq!(network_id, Some(env.network_id()));
+        }
         BootstrapResponse::Rebootstrap(_) => panic!("Unexpected Rebootstrap response"),
     }
 }
 
+#[test]
+fn handle_bootstrap_from_different_network() {
+    let mut env = Env::new(ELDER_SIZE);
+    let new_node = OtherNode::new(&mut env.rng);
+
+    let other_network_id = NetworkId::from_genesis_key(
+        &consensus::test_utils::gen_secret_key(&mut env.rng).public_key(),
+    );
+    let addr = *new_node.addr();
+    let msg = new_node.bootstrap_request(Some(other_network_id)).unwrap();
+
+    test_utils::handle_message(&mut env.subject, addr, msg).unwrap();
+    env.poll();
+
+    assert!(new_node
+        .received_messages()
+        .all(|msg| !matches!(msg.variant(), Variant::BootstrapResponse(_))));
+}
+
+#[test]
+fn join_request_is_deferred_until_address_is_verified() {
+    let mut env = Env::new(ELDER_SIZE);
+    let new_node = OtherNode::new(&mut env.rng);
+    let addr = *new_node.addr();
+
+    let msg = Message::single_src(
+        &new_node.full_id,
+        DstLocation::Direct,
+        None,
+        Variant::JoinRequest(Box::new(JoinRequest {
+            section_key: env.public_key_set.public_key(),
+            network_id: Some(env.network_id()),
+            relocate_payload: None,
+        })),
+    )
+    .unwrap();
+    test_utils::handle_message(&mut env.subject, addr, msg).unwrap();
+    env.poll();
+    assert!(!env.has_unpolled_observations());
+
+    let nonce = new_node
+        .received_messages()
+        .find_map(|msg| match msg.variant() {
+            Variant::AddressChallenge { nonce, .. } => Some(*nonce),
+            _ => None,
+        })
+        .expect("AddressChallenge not received");
+
+    // Once the node proves it owns its address, its request is handled.
+    let msg = Message::single_src(
+        &new_node.full_id,
+        DstLocation::Direct,
+        None,
+        Variant::AddressChallengeResponse {
+            nonce,
+            network_id: Some(env.network_id()),
+        },
+    )
+    .unwrap();
+    test_utils::handle_message(&mut env.subject, addr, msg).unwrap();
+    assert!(env.has_unpolled_observations());
+}
+
+#[test]
+fn lost_peer_is_identified_by_its_verified_address() {
+    let mut env = Env::new(ELDER_SIZE);
+    let other_id = *env.other_ids[0].0.public_id();
+    let addr = *env.elders_info.elders[other_id.name()].peer_addr();
+
+    // Unverified address.
+    env.subject.handle_peer_lost(addr);
+    assert!(!env.has_unpolled_observations());
+
+    // Address verified as owned by someone else.
+    let impostor = env.gen_peer();
+    test_utils::bind_peer(&mut env.subject, addr, *impostor.full_id.public_id());
+    env.subject.handle_peer_lost(addr);
+    assert!(!env.has_unpolled_observations());
+
+    test_utils::bind_peer(&mut env.subject, addr, other_id);
+    env.subject.handle_peer_lost(addr);
+    assert!(env.has_unpolled_observations());
+}
+
+#[test]
+fn handle_address_update() {
+    let mut env = Env::new(ELDER_SIZE);
+    env.accumulate_online(env.candidate.clone());
+
+    let new_addr = env.network.gen_addr();
+    let moved = P2pNode::new(*env.candidate.public_id(), new_addr);
+    env.accumulate_event(AccumulatingEvent::AddressUpdate(moved));
+    assert_eq!(member_addr(&env, env.candidate.name()), Some(new_addr));
+
+    // Updates of non-members are ignored.
+    let stranger = env.gen_peer().to_p2p_node();
+    env.accumulate_event(AccumulatingEvent::AddressUpdate(stranger.clone()));
+    assert_eq!(member_addr(&env, stranger.name()), None);
+}
+
+#[test]
+fn handle_locate_response() {
+    let their_prefix = Prefix::default().pushed(true);
+    let mut env = Env::with_prefix(ELDER_SIZE, Prefix::default().pushed(false));
+    let name = their_prefix.substituted_in(env.rng.gen());
+    assert!(env.subject.locate(&name).unwrap().is_none());
+
+    // Their first key, linked from our key at the split.
+    let their_sk = consensus::test_utils::gen_secret_key(&mut env.rng);
+    let their_pk = their_sk.public_key();
+    let (our_key, signature) = env.sign_by_section(&bincode::serialize(&their_pk).unwrap());
+    let mut their_history = SectionProofChain::new(our_key);
+    their_history.push(their_pk, signature);
+
+    let (their_info, their_ids) = test_utils::create_elders_info_with_prefix(
+        &mut env.rng,
+        &env.network,
+        ELDER_SIZE,
+        their_prefix,
+    );
+    let their_info = their_info.value;
+    let their_elder = their_ids.values().next().unwrap().clone();
+    let located = LocatedSection {
+        elders_info: consensus::test_utils::proven(&their_sk, their_info.clone()),
+        proof_chain: CompactProofChain::from(their_history),
+    };
+
+    // Not sent by one of their elders.
+    let stranger = env.gen_peer().full_id;
+    assert!(!deliver_locate_response(
+        &mut env,
+        &stranger,
+        located.clone()
+    ));
+
+    // The section we both descend from.
+    let ancestor_info = EldersInfo::new(their_info.elders.clone(), Prefix::default());
+    let stale = LocatedSection {
+        elders_info: consensus::test_utils::proven(&their_sk, ancestor_info),
+        proof_chain: located.proof_chain.clone(),
+    };
+    assert!(!deliver_locate_response(&mut env, &their_elder, stale));
+
+    // Signed by the key we still share.
+    let (_, signature) = env.sign_by_section(&bincode::serialize(&their_info).unwrap());
+    let unsplit = LocatedSection {
+        elders_info: Proven::new(
+            their_info,
+            Proof {
+                public_key: our_key,
+                signature,
+            },
+        ),
+        proof_chain: CompactProofChain::from(SectionProofChain::new(our_key)),
+    };
+    assert!(!deliver_locate_response(&mut env, &their_elder, unsplit));
+
+    assert!(deliver_locate_response(&mut env, &their_elder, located));
+}
+
+#[test]
+fn handle_section_proof_request() {
+    let mut env = Env::new(ELDER_SIZE);
+    let client = OtherNode::new(&mut env.rng);
+
+    let genesis_key = *env.subject.shared_state().unwrap().our_history.first_key();
+    let addr = *client.addr();
+    let msg = client.section_proof_request(genesis_key).unwrap();
+
+    test_utils::handle_message(&mut env.subject, addr, msg).unwrap();
+    env.poll();
+
+    let (elders_info, proof_chain) = client
+        .received_messages()
+        .find_map(|msg| match msg.variant() {
+            Variant::SectionProofResponse {
+                elders_info,
+                proof_chain,
+            } => Some((elders_info.clone(), proof_chain.clone())),
+            _ => None,
+        })
+        .expect("SectionProofResponse not received");
+
+    assert_eq!(elders_info.value, env.elders_info);
+    assert_eq!(
+        proof_chain.check_trust(iter::once(&genesis_key)),
+        TrustStatus::Trusted
+    );
+    assert!(proof_chain
+        .keys()
+        .any(|(_, key)| *key == elders_info.proof.public_key));
+}
+
+#[test]
+fn handle_membership_certificate_request() {
+    let mut env = Env::new(ELDER_SIZE);
+    let other_node = env.create_transport_for_other_elder(0);
+    let other_full_id = &env.other_ids[0].0;
+    let other_name = *other_full_id.public_id().name();
+
+    let msg = Message::single_src(
+        other_full_id,
+        DstLocation::Direct,
+        None,
+        Variant::MembershipCertificateRequest,
+    )
+    .unwrap();
+    test_utils::handle_message(&mut env.subject, *other_node.addr(), msg).unwrap();
+    env.poll();
+
+    let public_key_set = env.public_key_set.clone();
+    assert!(other_node
+        .received_messages()
+        .any(|(_, msg)| match msg.variant() {
+            Variant::MembershipCertificateShare {
+                statement,
+                proof_share,
+            } => {
+                statement.name == other_name
+                    && statement.prefix == env.elders_info.prefix
+                    && proof_share.public_key_set == public_key_set
+                    && proof_share.verify(&bincode::serialize(statement).unwrap())
+            }
+            _ => false,
+        }));
+}
+
 #[test]
 #[ignore] //FIXME DKG is no longer carried out by parsec
 fn send_genesis_update() {
@@ -639,6 +1222,79 @@ fn handle_bounced_unknown_message() {
     assert!(received_resent_message);
 }
 
+#[test]
+fn reject_message_of_newer_protocol() {
+    let mut env = Env::new(ELDER_SIZE);
+    let other_node = env.create_transport_for_other_elder(0);
+
+    // Envelope of the next protocol version whose body we can't decode.
+    let body = b"message of a future variant";
+    let mut bytes = vec![0xff];
+    bytes.extend_from_slice(&(PROTOCOL_VERSION + 1).to_be_bytes());
+    bytes.extend_from_slice(&0u32.to_be_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(body);
+
+    env.subject
+        .handle_new_message(*other_node.addr(), Bytes::from(bytes));
+    env.poll();
+
+    assert!(other_node
+        .received_messages()
+        .any(|(_, msg)| match msg.variant() {
+            Variant::MessageRejected {
+                hash,
+                protocol_version,
+            } => *hash == MessageHash::from_bytes(body) && *protocol_version == PROTOCOL_VERSION,
+            _ => false,
+        }));
+}
+
+#[test]
+fn relay_drops_message_over_hop_limit() {
+    let network_params = NetworkParams {
+        max_hops: 1,
+        ..Default::default()
+    };
+    let mut env = Env::with_network_params(ELDER_SIZE, network_params);
+    let sender = env.create_transport_for_other_elder(0);
+    let recipient = env.create_transport_for_other_elder(1);
+    let sender_id = env.other_ids[0].0.clone();
+    let dst = DstLocation::Node(*env.other_ids[1].0.public_id().name());
+
+    let create_message = |content: &[u8]| {
+        Message::single_src(
+            &sender_id,
+            dst,
+            None,
+            Variant::UserMessage(content.to_vec()),
+        )
+        .unwrap()
+    };
+
+    // A message below the limit is relayed, one hop further.
+    test_utils::handle_message(&mut env.subject, *sender.addr(), create_message(b"first")).unwrap();
+    env.poll();
+    let relayed: Vec<_> = recipient
+        .received_messages()
+        .filter(|(_, msg)| is_user_message(msg))
+        .collect();
+    assert_eq!(relayed.len(), 1);
+    assert_eq!(relayed[0].1.route().hops, 1);
+    assert_eq!(env.subject.hop_limit_drops(), 0);
+
+    // A message that already took `max_hops` hops is dropped.
+    let bytes = create_message(b"second")
+        .to_relay_bytes(&env.rng.gen(), &network_params)
+        .unwrap();
+    env.subject.handle_new_message(*sender.addr(), bytes);
+    env.poll();
+    assert!(!recipient
+        .received_messages()
+        .any(|(_, msg)| is_user_message(&msg)));
+    assert_eq!(env.subject.hop_limit_drops(), 1);
+}
+
 #[test]
 #[ignore] //FIXME Any message invalidly signed will not deserialise / be created
 fn handle_bounced_untrusted_message() {
@@ -747,6 +1403,298 @@ fn receive_message_with_invalid_proof_chain() {
     test_utils::handle_message(&mut env.subject, sender, msg).unwrap();
 }
 
+#[test]
+fn verify_message_with_compact_proof_chain() {
+    let mut env = Env::new(ELDER_SIZE);
+
+    // Chain of our key and two new keys, the last one also signed by our key via a skip link.
+    let sk1 = consensus::test_utils::gen_secret_key(&mut env.rng);
+    let pk1 = sk1.public_key();
+    let sk2 = consensus::test_utils::gen_secret_key(&mut env.rng);
+    let pk2 = sk2.public_key();
+    let pk2_bytes = bincode::serialize(&pk2).unwrap();
+
+    let (pk0, signature1) = env.sign_by_section(&bincode::serialize(&pk1).unwrap());
+    let skip_link_bytes = bincode::serialize(&(2u64, pk2)).unwrap();
+    let (_, skip_link) = env.sign_by_section(&skip_link_bytes);
+    let mut full_chain = SectionProofChain::new(pk0);
+    full_chain.push(pk1, signature1);
+    full_chain.push(pk2, sk1.sign(&pk2_bytes));
+
+    let content = PlainMessage {
+        src: Prefix::default(),
+        dst: DstLocation::Section(*env.subject.name()),
+        dst_key: pk0,
+        variant: Variant::UserMessage(b"hello".to_vec()),
+    };
+    let signature = sk2.sign(&bincode::serialize(&content.as_signable()).unwrap());
+    let create_msg = |skip_links: &SkipLinks| {
+        let proof_chain = CompactProofChain::new(&full_chain, 0, skip_links);
+        let src = SrcAuthority::CompactSection {
+            prefix: Prefix::default(),
+            signature: signature.clone(),
+            proof_chain,
+        };
+        Message::unverified(
+            src,
+            content.dst,
+            Some(content.dst_key),
+            content.variant.clone(),
+        )
+        .unwrap()
+    };
+    let trusted_keys = [(Prefix::default(), pk0)];
+
+    // The skip link skips `pk1`.
+    let mut skip_links = SkipLinks::default();
+    skip_links.insert(2, skip_link);
+    let msg = create_msg(&skip_links);
+    match msg.src() {
+        SrcAuthority::CompactSection { proof_chain, .. } => assert!(!proof_chain.has_key(&pk1)),
+        _ => panic!("unexpected source authority"),
+    }
+    assert_eq!(
+        msg.verify(trusted_keys.iter().map(|(prefix, key)| (prefix, key)))
+            .unwrap(),
+        VerifyStatus::Full
+    );
+
+    // Skip link not signed by our key.
+    let mut skip_links = SkipLinks::default();
+    skip_links.insert(2, sk1.sign(&skip_link_bytes));
+    let msg = create_msg(&skip_links);
+    assert!(msg
+        .verify(trusted_keys.iter().map(|(prefix, key)| (prefix, key)))
+        .is_err());
+}
+
+#[test]
+fn ack_from_destination_confirms_delivery() {
+    let mut env = Env::new(ELDER_SIZE);
+    let our_dst = DstLocation::Node(*env.subject.name());
+    let dst_id = env.other_ids[0].0.clone();
+    let dst = DstLocation::Node(*dst_id.public_id().name());
+
+    let msg_hash = env
+        .subject
+        .send_message_with_ack(dst, b"hello".to_vec())
+        .unwrap();
+
+    // Only the destination can acknowledge the message.
+    let ack =
+        Message::single_src(&env.other_ids[1].0, our_dst, None, Variant::Ack(msg_hash)).unwrap();
+    test_utils::handle_message(&mut env.subject, env.network.gen_addr(), ack).unwrap();
+
+    let ack = Message::single_src(&dst_id, our_dst, None, Variant::Ack(msg_hash)).unwrap();
+    test_utils::handle_message(&mut env.subject, env.network.gen_addr(), ack).unwrap();
+
+    let delivered: Vec<_> = env
+        .event_rx
+        .try_iter()
+        .filter_map(|event| match event {
+            Event::Delivered { dst, msg_hash } => Some((dst, msg_hash)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(delivered, vec![(dst, msg_hash)]);
+}
+
+#[test]
+fn section_acks_message_with_quorum_of_signatures() {
+    let mut env = Env::new(ELDER_SIZE);
+    let sender = env.create_transport_for_other_elder(0);
+    let sender_id = env.other_ids[0].0.clone();
+    let sender_dst = DstLocation::Node(*sender_id.public_id().name());
+
+    let msg = Message::single_src(
+        &sender_id,
+        DstLocation::Section(*env.subject.name()),
+        None,
+        Variant::AckedUserMessage(b"hello".to_vec()),
+    )
+    .unwrap();
+    let msg_hash = *msg.hash();
+    test_utils::handle_message(&mut env.subject, *sender.addr(), msg).unwrap();
+    env.poll();
+
+    // The sender is the elder closest to itself, so it receives our signature share of the ack.
+    let our_share = sender
+        .received_messages()
+        .find_map(|(_, msg)| match msg.variant() {
+            Variant::MessageSignature(accumulating_msg)
+                if accumulating_msg.content.variant == Variant::Ack(msg_hash) =>
+            {
+                Some(*accumulating_msg.clone())
+            }
+            _ => None,
+        })
+        .expect("ack signature share not sent");
+    assert_eq!(our_share.content.dst, sender_dst);
+
+    // Our share together with the shares of a threshold of other elders makes the ack.
+    let our_index = env.elders_info.position(env.subject.name()).unwrap();
+    let content = our_share.content.clone();
+    let proof_chain = our_share.proof_chain.clone();
+    let other_shares: Vec<_> = env
+        .secret_key_shares()
+        .filter(|(index, _)| *index != our_index)
+        .take(env.public_key_set.threshold())
+        .map(|(index, secret_key_share)| {
+            let proof_share = content
+                .prove(env.public_key_set.clone(), index, secret_key_share)
+                .unwrap();
+            AccumulatingMessage::new(content.clone(), proof_chain.clone(), proof_share)
+        })
+        .collect();
+
+    let ack = test_utils::accumulate_messages(iter::once(our_share).chain(other_shares));
+    assert!(ack.src().is_section());
+    assert_eq!(*ack.dst(), sender_dst);
+    assert_eq!(*ack.variant(), Variant::Ack(msg_hash));
+}
+
+#[test]
+fn unacked_message_times_out() {
+    let mut env = Env::new(ELDER_SIZE);
+    let dst = DstLocation::Node(env.rng.gen());
+
+    let msg_hash = env
+        .subject
+        .send_message_with_ack(dst, b"hello".to_vec())
+        .unwrap();
+
+    FakeClock::advance_time(ACK_TIMEOUT.as_millis() as u64 + 1);
+    handle_pending_operations(&mut env.subject);
+
+    let timed_out: Vec<_> = env
+        .event_rx
+        .try_iter()
+        .filter_map(|event| match event {
+            Event::DeliveryTimeout { dst, msg_hash } => Some((dst, msg_hash)),
+            Event::Delivered { .. } => panic!("unexpected Delivered event"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(timed_out, vec![(dst, msg_hash)]);
+}
+
+#[test]
+fn missing_ordered_message_is_requested_and_delivered_once_resent() {
+    let mut env = Env::new(ELDER_SIZE);
+    let src_node = env.create_transport_for_other_elder(0);
+    let src_id = env.other_ids[0].0.clone();
+    let channel = DstLocation::Node(*env.subject.name());
+
+    let ordered_msg = |seq: u64, resend| {
+        let variant = Variant::OrderedUserMessage {
+            channel,
+            epoch: 1,
+            seq,
+            resend,
+            content: vec![seq as u8],
+        };
+        Message::single_src(&src_id, channel, None, variant).unwrap()
+    };
+
+    // The first message is lost on the way, so the second one waits for it.
+    test_utils::handle_message(&mut env.subject, *src_node.addr(), ordered_msg(1, 0)).unwrap();
+
+    // The second gap check finds the gap still there and asks the source for the missing message.
+    for _ in 0..2 {
+        FakeClock::advance_time(ORDERED_GAP_CHECK_INTERVAL.as_millis() as u64 + 1);
+        handle_pending_operations(&mut env.subject);
+    }
+    env.poll();
+
+    let requested = src_node
+        .received_messages()
+        .any(|(_, msg)| match msg.variant() {
+            Variant::OrderedResendRequest {
+                channel: requested_channel,
+                epoch,
+                from,
+                to,
+                attempt,
+            } => {
+                *requested_channel == channel
+                    && *epoch == 1
+                    && *from == 0
+                    && *to == 1
+                    && *attempt == 1
+            }
+            _ => false,
+        });
+    assert!(requested);
+
+    // The resent message fills the gap and both are delivered in order.
+    test_utils::handle_message(&mut env.subject, *src_node.addr(), ordered_msg(0, 1)).unwrap();
+
+    let received: Vec<_> = env
+        .event_rx
+        .try_iter()
+        .filter_map(|event| match event {
+            Event::MessageReceived { content, .. } => Some(content),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(received, vec![vec![0], vec![1]]);
+}
+
+#[test]
+fn ordered_resend_request_is_answered() {
+    let mut env = Env::new(ELDER_SIZE);
+    let dst_node = env.create_transport_for_other_elder(0);
+    let dst_id = env.other_ids[0].0.clone();
+    let channel = DstLocation::Node(*dst_id.public_id().name());
+
+    for content in vec![b"first".to_vec(), b"second".to_vec()] {
+        env.subject.send_ordered_message(channel, content).unwrap();
+    }
+    env.poll();
+
+    let epoch = dst_node
+        .received_messages()
+        .find_map(|(_, msg)| match msg.variant() {
+            Variant::OrderedUserMessage { epoch, .. } => Some(*epoch),
+            _ => None,
+        })
+        .expect("ordered message not sent");
+
+    let request = Message::single_src(
+        &dst_id,
+        DstLocation::Node(*env.subject.name()),
+        None,
+        Variant::OrderedResendRequest {
+            channel,
+            epoch,
+            from: 0,
+            to: 2,
+            attempt: 1,
+        },
+    )
+    .unwrap();
+    test_utils::handle_message(&mut env.subject, *dst_node.addr(), request).unwrap();
+    env.poll();
+
+    let mut resent: Vec<_> = dst_node
+        .received_messages()
+        .filter_map(|(_, msg)| match msg.variant() {
+            Variant::OrderedUserMessage {
+                seq,
+                resend: 1,
+                content,
+                ..
+            } => Some((*seq, content.clone())),
+            _ => None,
+        })
+        .collect();
+    resent.sort();
+    assert_eq!(
+        resent,
+        vec![(0, b"first".to_vec()), (1, b"second".to_vec())]
+    );
+}
+
 struct OtherNode {
     transport: MockTransport,
     full_id: FullId,
@@ -768,8 +1716,11 @@ impl OtherNode {
         self.full_id.public_id()
     }
 
-    fn bootstrap_request(&self) -> Result<Message> {
-        let variant = Variant::BootstrapRequest(*self.public_id().name());
+    fn bootstrap_request(&self, network_id: Option<NetworkId>) -> Result<Message> {
+        let variant = Variant::BootstrapRequest {
+            name: *self.public_id().name(),
+            network_id,
+        };
         Ok(Message::single_src(
             &self.full_id,
             DstLocation::Direct,
@@ -778,6 +1729,15 @@ impl OtherNode {
         )?)
     }
 
+    fn section_proof_request(&self, known_key: bls::PublicKey) -> Result<Message> {
+        Ok(Message::single_src(
+            &self.full_id,
+            DstLocation::Direct,
+            None,
+            Variant::SectionProofRequest { known_key },
+        )?)
+    }
+
     fn received_messages(&self) -> impl Iterator<Item = Message> + '_ {
         self.transport.received_messages().map(|(_, msg)| msg)
     }
@@ -791,3 +1751,60 @@ impl OtherNode {
             .expect("BootstrapResponse not received")
     }
 }
+
+// Handles the expired timers and transport events of the node until none is left.
+fn handle_pending_operations(node: &mut Node) {
+    loop {
+        let mut sel = mpmc::Select::new();
+        node.register(&mut sel);
+
+        if let Ok(op_index) = sel.try_ready() {
+            let _ = node.handle_selected_operation(op_index);
+        } else {
+            break;
+        }
+    }
+}
+
+fn requested_members(env: &Env) -> usize {
+    env.subject.shared_state().unwrap().requested_members
+}
+
+fn is_accepting_infants(env: &Env, max_infants: usize) -> bool {
+    env.subject
+        .shared_state()
+        .unwrap()
+        .is_accepting_infants(max_infants)
+}
+
+fn is_user_message(msg: &Message) -> bool {
+    match msg.variant() {
+        Variant::UserMessage(_) => true,
+        _ => false,
+    }
+}
+
+fn member_addr(env: &Env, name: &XorName) -> Option<SocketAddr> {
+    env.subject
+        .shared_state()
+        .unwrap()
+        .our_members
+        .get(name)
+        .map(|info| *info.p2p_node.peer_addr())
+}
+
+// Delivers the `LocateResponse` from `sender` to the subject. Returns whether it raised
+// `Event::Located`.
+fn deliver_locate_response(env: &mut Env, sender: &FullId, located: LocatedSection) -> bool {
+    let msg = Message::single_src(
+        sender,
+        DstLocation::Node(*env.subject.name()),
+        None,
+        Variant::LocateResponse(Box::new(located)),
+    )
+    .unwrap();
+    test_utils::handle_message(&mut env.subject, env.network.gen_addr(), msg).unwrap();
+    env.event_rx
+        .try_iter()
+        .any(|event| matches!(event, Event::Located(_)))
+}
@@ -16,7 +16,7 @@
 use crate::{
     consensus::{self, Proof, Proven},
     error::Result,
-    id::{FullId, P2pNode},
+    id::{FullId, P2pNode, PublicId},
     messages::{AccumulatingMessage, Message, MessageAccumulator},
     node::Node,
     quic_p2p::{EventSenders, Peer, QuicP2p},
@@ -34,10 +34,19 @@ pub fn create_elders_info(
     rng: &mut MainRng,
     network: &Network,
     elder_size: usize,
+) -> (Proven<EldersInfo>, BTreeMap<XorName, FullId>) {
+    create_elders_info_with_prefix(rng, network, elder_size, Prefix::default())
+}
+
+pub fn create_elders_info_with_prefix(
+    rng: &mut MainRng,
+    network: &Network,
+    elder_size: usize,
+    prefix: Prefix,
 ) -> (Proven<EldersInfo>, BTreeMap<XorName, FullId>) {
     let full_ids: BTreeMap<_, _> = (0..elder_size)
         .map(|_| {
-            let id = FullId::gen(rng);
+            let id = FullId::within_range(rng, &prefix.range_inclusive());
             (*id.public_id().name(), id)
         })
         .collect();
@@ -50,7 +59,7 @@ pub fn create_elders_info(
         })
         .collect();
 
-    let elders_info = EldersInfo::new(members_map, Prefix::default());
+    let elders_info = EldersInfo::new(members_map, prefix);
 
     let sk = consensus::test_utils::gen_secret_key(rng);
     let elders_info = consensus::test_utils::proven(&sk, elders_info);
@@ -80,6 +89,17 @@ pub fn handle_message(node: &mut Node, sender: SocketAddr, msg: Message) -> Resu
     Ok(())
 }
 
+// Binds `addr` to `pub_id` in the node, as if the peer answered our `AddressChallenge` from it.
+pub fn bind_peer(node: &mut Node, addr: SocketAddr, pub_id: PublicId) {
+    node.core.peer_bindings.remove(&addr);
+    let nonce = node
+        .core
+        .peer_bindings
+        .challenge(addr, &mut node.core.rng)
+        .unwrap();
+    assert!(node.core.peer_bindings.handle_response(addr, pub_id, nonce));
+}
+
 pub fn accumulate_messages<I>(accumulating_msgs: I) -> Message
 where
     I: IntoIterator<Item = AccumulatingMessage>,
@@ -0,0 +1,290 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    consensus::Proven,
+    crypto::{self, Digest256},
+    section::{SectionProofChain, TrustStatus},
+};
+use std::{
+    collections::BTreeSet,
+    mem,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Granularity of the notary timestamps, in seconds. The notary time of a section advances in
+/// steps of this.
+pub const NOTARY_TIMESTAMP_GRANULARITY: u64 = 60;
+
+/// Returns the current time as a notary timestamp.
+pub fn timestamp_now() -> u64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    secs - secs % NOTARY_TIMESTAMP_GRANULARITY
+}
+
+/// A batch of document hashes notarised by a section, identified by the Merkle root of the hashes.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Debug)]
+pub struct NotaryBatch {
+    pub index: u64,
+    pub timestamp: u64,
+    pub root: Digest256,
+}
+
+/// Notary state of a section, part of its shared state.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Notary {
+    /// Hashes agreed on but not yet included in any batch.
+    pub pending: BTreeSet<Digest256>,
+    /// The batch being signed, together with its hashes.
+    pub sealing: Option<(NotaryBatch, Vec<Digest256>)>,
+    /// Index of the next batch.
+    pub next_index: u64,
+    /// Current notary time, used as the timestamp of the batches. Zero if not started yet.
+    ///
+    /// The elders start it by agreeing on the current time, then advance it one
+    /// `NOTARY_TIMESTAMP_GRANULARITY` step at a time, so the following timestamps are derived
+    /// from the previous one and don't depend on the clock of any single elder.
+    pub timestamp: u64,
+}
+
+impl Notary {
+    /// Returns the timestamp to vote for advancing the notary time to, given the current time:
+    /// one step after the current notary time or, if not started yet, the current time. Returns
+    /// `None` if the next step is still in the future, so the notary time never runs ahead of the
+    /// clocks of a quorum of the elders.
+    pub fn next_timestamp(&self, now: u64) -> Option<u64> {
+        if self.timestamp == 0 {
+            Some(now)
+        } else {
+            Some(self.timestamp + NOTARY_TIMESTAMP_GRANULARITY).filter(|next| *next <= now)
+        }
+    }
+
+    /// Returns whether the notary time can be advanced to the given timestamp.
+    pub fn is_next(&self, timestamp: u64) -> bool {
+        if self.timestamp == 0 {
+            timestamp > 0 && timestamp % NOTARY_TIMESTAMP_GRANULARITY == 0
+        } else {
+            timestamp == self.timestamp + NOTARY_TIMESTAMP_GRANULARITY
+        }
+    }
+
+    /// Moves the pending hashes into a new batch with the current notary time and returns it.
+    pub fn seal(&mut self) -> NotaryBatch {
+        let leaves: Vec<_> = mem::take(&mut self.pending).into_iter().collect();
+        let batch = NotaryBatch {
+            index: self.next_index,
+            timestamp: self.timestamp,
+            root: merkle_root(&leaves),
+        };
+
+        self.next_index += 1;
+        self.sealing = Some((batch.clone(), leaves));
+        batch
+    }
+
+    /// Completes the batch being signed and returns the receipts of all its hashes.
+    pub fn complete(
+        &mut self,
+        batch: Proven<NotaryBatch>,
+        history: &SectionProofChain,
+    ) -> Vec<NotaryReceipt> {
+        let leaves = match self.sealing.take() {
+            Some((sealing, leaves)) if sealing == batch.value => leaves,
+            other => {
+                self.sealing = other;
+                return vec![];
+            }
+        };
+
+        leaves
+            .iter()
+            .enumerate()
+            .map(|(index, hash)| NotaryReceipt {
+                hash: *hash,
+                proof: MerkleProof::new(&leaves, index),
+                batch: batch.clone(),
+                history: history.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Proof that a document hash was notarised by a section at a given time. It can be verified
+/// offline given a trusted key of the section.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct NotaryReceipt {
+    hash: Digest256,
+    proof: MerkleProof,
+    batch: Proven<NotaryBatch>,
+    history: SectionProofChain,
+}
+
+impl NotaryReceipt {
+    /// The notarised hash.
+    pub fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+
+    /// Time the hash was notarised at, in seconds since the UNIX epoch, rounded down to a multiple
+    /// of `NOTARY_TIMESTAMP_GRANULARITY`.
+    pub fn timestamp(&self) -> u64 {
+        self.batch.value.timestamp
+    }
+
+    /// The section key that signed the batch containing the hash.
+    pub fn section_key(&self) -> &bls::PublicKey {
+        &self.batch.proof.public_key
+    }
+
+    /// Verifies that the hash is part of a batch signed by the section whose key history contains
+    /// one of the given trusted keys.
+    pub fn verify<'a, I>(&self, trusted_keys: I) -> bool
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        self.proof.root(&self.hash) == self.batch.value.root
+            && self.batch.verify(&self.history)
+            && self.history.check_trust(trusted_keys) == TrustStatus::Trusted
+    }
+}
+
+/// Proof of inclusion of a leaf in a Merkle tree: the hashes of the siblings on the path from the
+/// leaf to the root, each with a flag telling whether it is on the left.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+struct MerkleProof(Vec<(bool, Digest256)>);
+
+impl MerkleProof {
+    fn new(leaves: &[Digest256], mut index: usize) -> Self {
+        let mut siblings = Vec::new();
+        let mut level: Vec<_> = leaves.iter().map(leaf_hash).collect();
+
+        while level.len() > 1 {
+            let sibling_index = index ^ 1;
+            if let Some(sibling) = level.get(sibling_index) {
+                siblings.push((sibling_index < index, *sibling));
+            }
+
+            level = next_level(&level);
+            index /= 2;
+        }
+
+        Self(siblings)
+    }
+
+    fn root(&self, leaf: &Digest256) -> Digest256 {
+        self.0
+            .iter()
+            .fold(leaf_hash(leaf), |hash, (is_left, sibling)| {
+                if *is_left {
+                    node_hash(sibling, &hash)
+                } else {
+                    node_hash(&hash, sibling)
+                }
+            })
+    }
+}
+
+fn merkle_root(leaves: &[Digest256]) -> Digest256 {
+    let mut level: Vec<_> = leaves.iter().map(leaf_hash).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+
+    level.pop().unwrap_or_default()
+}
+
+// Hashes the nodes of a tree level pairwise. The last node of a level with an odd number of nodes
+// is moved to the next level unchanged.
+fn next_level(level: &[Digest256]) -> Vec<Digest256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+// Leaves and inner nodes are hashed with different prefixes so an inner node can't be passed off
+// as a leaf.
+fn leaf_hash(leaf: &Digest256) -> Digest256 {
+    let mut buffer = Vec::with_capacity(1 + leaf.len());
+    buffer.push(0);
+    buffer.extend_from_slice(leaf);
+    crypto::sha3_256(&buffer)
+}
+
+fn node_hash(left: &Digest256, right: &Digest256) -> Digest256 {
+    let mut buffer = Vec::with_capacity(1 + left.len() + right.len());
+    buffer.push(1);
+    buffer.extend_from_slice(left);
+    buffer.extend_from_slice(right);
+    crypto::sha3_256(&buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::test_utils::{gen_secret_key, proven},
+        rng,
+    };
+    use rand::Rng;
+
+    #[test]
+    fn merkle_proofs() {
+        let mut rng = rng::new();
+
+        for count in 1..10 {
+            let leaves: Vec<Digest256> = (0..count).map(|_| rng.gen()).collect();
+            let root = merkle_root(&leaves);
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = MerkleProof::new(&leaves, index);
+                assert_eq!(proof.root(leaf), root);
+                assert_ne!(proof.root(&rng.gen()), root);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_receipts() {
+        let mut rng = rng::new();
+        let sk = gen_secret_key(&mut rng);
+        let history = SectionProofChain::new(sk.public_key());
+
+        let mut notary = Notary::default();
+        notary.timestamp = timestamp_now();
+        for _ in 0..3 {
+            let _ = notary.pending.insert(rng.gen());
+        }
+
+        let batch = notary.seal();
+        assert_eq!(batch.timestamp, notary.timestamp);
+        assert!(notary.pending.is_empty());
+
+        let receipts = notary.complete(proven(&sk, batch), &history);
+        assert_eq!(receipts.len(), 3);
+        assert!(notary.sealing.is_none());
+
+        for receipt in &receipts {
+            assert!(receipt.verify(&[sk.public_key()]));
+            assert!(!receipt.verify(&[gen_secret_key(&mut rng).public_key()]));
+        }
+
+        // Receipt for a hash that isn't in the batch.
+        let mut forged = receipts[0].clone();
+        forged.hash = rng.gen();
+        assert!(!forged.verify(&[sk.public_key()]));
+    }
+}
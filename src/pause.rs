@@ -11,7 +11,10 @@ use crate::{
     id::FullId,
     message_filter::MessageFilter,
     messages::{MessageAccumulator, QueuedMessage},
+    network_id::NetworkId,
     network_params::NetworkParams,
+    peer_binding::PeerBindings,
+    rate_limit::RateLimiter,
     section::{SectionKeysProvider, SectionUpdateBarrier, SharedState},
     transport::Transport,
     TransportEvent,
@@ -29,12 +32,15 @@ use std::collections::VecDeque;
 // version >= X.
 pub struct PausedState {
     pub(super) network_params: NetworkParams,
+    pub(super) network_id: Option<NetworkId>,
     pub(super) consensus_engine: ConsensusEngine,
     pub(super) shared_state: SharedState,
     pub(super) section_keys_provider: SectionKeysProvider,
     pub(super) full_id: FullId,
     pub(super) msg_filter: MessageFilter,
     pub(super) msg_queue: VecDeque<QueuedMessage>,
+    pub(super) peer_bindings: PeerBindings,
+    pub(super) rate_limiter: RateLimiter,
     // TODO: instead of storing both transport and network_rx, store only the network config.
     pub(super) transport: Transport,
     pub(super) transport_rx: Option<mpmc::Receiver<TransportEvent>>,
@@ -0,0 +1,131 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    id::{P2pNode, PublicId},
+    rng::MainRng,
+};
+use lru_time_cache::LruCache;
+use rand::Rng;
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+// How long to wait for the peer to respond to our challenge.
+const CHALLENGE_EXPIRY_DURATION: Duration = Duration::from_secs(60);
+
+// Keeps track of which `PublicId` has proven to be reachable at which address.
+//
+// On connection setup we send the peer a random nonce which it has to send back in a message
+// signed with its own key. As the nonce was only ever sent to that address, a valid response proves
+// the owner of the key is reachable there.
+pub struct PeerBindings {
+    challenges: LruCache<SocketAddr, u64>,
+    verified: HashMap<SocketAddr, PublicId>,
+}
+
+impl PeerBindings {
+    pub fn new() -> Self {
+        Self {
+            challenges: LruCache::with_expiry_duration(CHALLENGE_EXPIRY_DURATION),
+            verified: HashMap::new(),
+        }
+    }
+
+    // Creates new challenge for the peer at `addr` and returns its nonce, or returns `None` if
+    // there already is one pending.
+    pub fn challenge(&mut self, addr: SocketAddr, rng: &mut MainRng) -> Option<u64> {
+        if self.challenges.contains_key(&addr) {
+            return None;
+        }
+
+        let nonce = rng.gen();
+        let _ = self.challenges.insert(addr, nonce);
+        Some(nonce)
+    }
+
+    // Handles a response to our challenge signed by `pub_id`. Returns whether the binding got
+    // established.
+    pub fn handle_response(&mut self, addr: SocketAddr, pub_id: PublicId, nonce: u64) -> bool {
+        if self.challenges.get(&addr) != Some(&nonce) {
+            return false;
+        }
+
+        let _ = self.challenges.remove(&addr);
+        // Only one identity per address. A new binding replaces the previous one.
+        let _ = self.verified.insert(addr, pub_id);
+        true
+    }
+
+    // Returns whether the peer has proven to own the `PublicId` at the address of `p2p_node`.
+    pub fn is_bound(&self, p2p_node: &P2pNode) -> bool {
+        self.verified.get(p2p_node.peer_addr()) == Some(p2p_node.public_id())
+    }
+
+    // Returns the `PublicId` the peer at `addr` has proven to own, if any.
+    pub fn bound_id(&self, addr: &SocketAddr) -> Option<&PublicId> {
+        self.verified.get(addr)
+    }
+
+    pub fn remove(&mut self, addr: &SocketAddr) {
+        let _ = self.challenges.remove(addr);
+        let _ = self.verified.remove(addr);
+    }
+}
+
+impl Default for PeerBindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{id::FullId, rng};
+
+    #[test]
+    fn binding_requires_matching_nonce() {
+        let mut rng = rng::new();
+        let mut bindings = PeerBindings::new();
+
+        let pub_id = *FullId::gen(&mut rng).public_id();
+        let addr: SocketAddr = ([127, 0, 0, 1], 5000).into();
+        let p2p_node = P2pNode::new(pub_id, addr);
+
+        let nonce = bindings.challenge(addr, &mut rng).unwrap();
+        assert_eq!(bindings.challenge(addr, &mut rng), None);
+
+        assert!(!bindings.handle_response(addr, pub_id, nonce.wrapping_add(1)));
+        assert!(!bindings.is_bound(&p2p_node));
+
+        assert!(bindings.handle_response(addr, pub_id, nonce));
+        assert!(bindings.is_bound(&p2p_node));
+
+        // The nonce can't be reused.
+        assert!(!bindings.handle_response(addr, pub_id, nonce));
+
+        bindings.remove(&addr);
+        assert!(!bindings.is_bound(&p2p_node));
+    }
+
+    #[test]
+    fn binding_is_per_address() {
+        let mut rng = rng::new();
+        let mut bindings = PeerBindings::new();
+
+        let pub_id = *FullId::gen(&mut rng).public_id();
+        let addr: SocketAddr = ([127, 0, 0, 1], 5000).into();
+        let other_addr: SocketAddr = ([127, 0, 0, 1], 5001).into();
+
+        let nonce = bindings.challenge(addr, &mut rng).unwrap();
+        assert!(!bindings.handle_response(other_addr, pub_id, nonce));
+        assert!(bindings.handle_response(addr, pub_id, nonce));
+
+        assert!(bindings.is_bound(&P2pNode::new(pub_id, addr)));
+        assert!(!bindings.is_bound(&P2pNode::new(pub_id, other_addr)));
+    }
+}
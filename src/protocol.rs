@@ -0,0 +1,111 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use std::fmt::{self, Display, Formatter};
+
+/// Version of the wire protocol spoken by this node. Later versions must keep decoding the messages
+/// of their previous version, so the network can be upgraded one node at a time as long as no node
+/// is more than one version behind.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// Oldest protocol version whose messages we can still decode. Version 1 isn't supported: several
+/// of its message variants changed shape or position since, so its messages can't be decoded
+/// reliably.
+pub const MIN_PROTOCOL_VERSION: u16 = 2;
+
+/// Protocol version introducing compressed messages. A node only compresses its messages once
+/// every elder of its section runs this version.
+pub(crate) const COMPRESSION_VERSION: u16 = 2;
+
+/// Protocol version introducing messages proven with compact section proof chains. A section only
+/// sends them once every one of its elders runs this version.
+pub(crate) const COMPACT_PROOF_VERSION: u16 = 2;
+
+/// Feature bit: the node can decode messages compressed with `Compression::Deflate`.
+pub const FEATURE_DEFLATE: u32 = 1;
+/// Feature bit: the node can decode messages compressed with `Compression::Snappy`.
+pub const FEATURE_SNAPPY: u32 = 1 << 1;
+/// Feature bit: the node's messages carry the hop count and recorded route after their body.
+pub const FEATURE_ROUTE: u32 = 1 << 2;
+
+// Features supported by this node.
+const SUPPORTED_FEATURES: u32 = FEATURE_DEFLATE | FEATURE_SNAPPY | FEATURE_ROUTE;
+
+/// Protocol version and feature bits declared by the sender of a message in its wire envelope.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Protocol {
+    /// Protocol version.
+    pub version: u16,
+    /// Bitset of the `FEATURE_*` flags.
+    pub features: u32,
+}
+
+impl Protocol {
+    /// Protocol of this node.
+    pub const OURS: Self = Self {
+        version: PROTOCOL_VERSION,
+        features: SUPPORTED_FEATURES,
+    };
+
+    /// Protocol of the nodes predating versioned envelopes. They send bare, uncompressed messages
+    /// which we recognise and reject.
+    pub(crate) const LEGACY: Self = Self {
+        version: 1,
+        features: 0,
+    };
+
+    /// Returns whether we can talk to a peer speaking this protocol: each side must be able to
+    /// decode the messages of the other and the peer must support all of `required_features`.
+    pub fn is_compatible(&self, required_features: u32) -> bool {
+        self.version >= MIN_PROTOCOL_VERSION
+            && self.version.saturating_sub(1) <= PROTOCOL_VERSION
+            && self.features & required_features == required_features
+    }
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::OURS
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "v{} ({:#x})", self.version, self.features)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatibility() {
+        assert!(Protocol::OURS.is_compatible(SUPPORTED_FEATURES));
+        assert!(!Protocol::LEGACY.is_compatible(0));
+
+        let newer = Protocol {
+            version: PROTOCOL_VERSION + 1,
+            features: SUPPORTED_FEATURES,
+        };
+        assert!(newer.is_compatible(0));
+
+        let too_new = Protocol {
+            version: PROTOCOL_VERSION + 2,
+            features: SUPPORTED_FEATURES,
+        };
+        assert!(!too_new.is_compatible(0));
+
+        let no_snappy = Protocol {
+            version: PROTOCOL_VERSION,
+            features: FEATURE_DEFLATE,
+        };
+        assert!(no_snappy.is_compatible(FEATURE_DEFLATE));
+        assert!(!no_snappy.is_compatible(FEATURE_SNAPPY));
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    id::PublicId,
+    time::{Duration, Instant},
+};
+use lru_time_cache::LruCache;
+use std::net::SocketAddr;
+
+// How long to keep the bucket of a peer that stopped sending us messages. Longer than it takes any
+// reasonable bucket to refill, so forgetting it doesn't let the peer send more.
+const BUCKET_EXPIRY: Duration = Duration::from_secs(10 * 60);
+// Maximum number of peers we keep buckets for.
+const MAX_BUCKETS: usize = 10_000;
+// Tokens are counted in thousandths, to refill them precisely at low rates.
+const TOKEN: u64 = 1000;
+
+/// Token bucket limit on the number of messages accepted from a single peer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Number of messages per second accepted in the long run.
+    pub per_second: u32,
+    /// Number of messages accepted at once from a peer that was idle for long enough.
+    pub burst: u32,
+}
+
+/// Limits on the incoming traffic from individual peers, checked before the messages are
+/// deserialised or their signatures verified. Messages over the limits are dropped.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RateLimits {
+    /// Limit per peer address, or `None` for no limit.
+    pub per_addr: Option<RateLimit>,
+    /// Limit per `PublicId`, applied to the peers that proved to own one, or `None` for no limit.
+    /// Limits the peers that connect from several addresses.
+    pub per_id: Option<RateLimit>,
+    /// Number of messages from a member of our section dropped by `per_id` after which, if we are
+    /// an elder, we propose to evict it. `None` to never propose it.
+    pub eviction_threshold: Option<u64>,
+}
+
+/// Number of incoming messages dropped by the rate limits.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RateLimitStats {
+    /// Dropped because their sender address was over the limit.
+    pub rejected_by_addr: u64,
+    /// Dropped because their sender `PublicId` was over the limit.
+    pub rejected_by_id: u64,
+}
+
+// Outcome of checking an incoming message against the rate limits.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Admission {
+    Accept,
+    Reject,
+    // Rejected, and the sender reached the eviction threshold.
+    RejectAndEvict(PublicId),
+}
+
+pub struct RateLimiter {
+    limits: RateLimits,
+    by_addr: LruCache<SocketAddr, Bucket>,
+    by_id: LruCache<PublicId, Bucket>,
+    // Number of messages dropped by `per_id` since the peer was last proposed for eviction.
+    offences: LruCache<PublicId, u64>,
+    pub stats: RateLimitStats,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            by_addr: new_cache(),
+            by_id: new_cache(),
+            offences: new_cache(),
+            stats: RateLimitStats::default(),
+        }
+    }
+
+    // Checks a message from `addr`, sent by `pub_id` if the peer proved to own it.
+    pub fn admit(&mut self, addr: SocketAddr, pub_id: Option<&PublicId>) -> Admission {
+        if let Some(limit) = self.limits.per_addr {
+            if !take(&mut self.by_addr, addr, &limit) {
+                self.stats.rejected_by_addr += 1;
+                return Admission::Reject;
+            }
+        }
+
+        let (limit, pub_id) = match (self.limits.per_id, pub_id) {
+            (Some(limit), Some(pub_id)) => (limit, pub_id),
+            _ => return Admission::Accept,
+        };
+
+        if take(&mut self.by_id, *pub_id, &limit) {
+            return Admission::Accept;
+        }
+
+        self.stats.rejected_by_id += 1;
+
+        let threshold = if let Some(threshold) = self.limits.eviction_threshold {
+            threshold
+        } else {
+            return Admission::Reject;
+        };
+
+        let offences = self.offences.entry(*pub_id).or_insert(0);
+        *offences += 1;
+        if *offences >= threshold {
+            *offences = 0;
+            Admission::RejectAndEvict(*pub_id)
+        } else {
+            Admission::Reject
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimits::default())
+    }
+}
+
+fn new_cache<K: Ord + Clone, V>() -> LruCache<K, V> {
+    LruCache::with_expiry_duration_and_capacity(BUCKET_EXPIRY, MAX_BUCKETS)
+}
+
+// Takes a token from the bucket of `key`, creating a full one if there is none. Returns whether
+// there was any.
+fn take<K: Ord + Clone>(buckets: &mut LruCache<K, Bucket>, key: K, limit: &RateLimit) -> bool {
+    buckets
+        .entry(key)
+        .or_insert_with(|| Bucket::full(limit))
+        .take(limit)
+}
+
+struct Bucket {
+    tokens: u64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit) -> Self {
+        Self {
+            tokens: u64::from(limit.burst) * TOKEN,
+            refilled: Instant::now(),
+        }
+    }
+
+    fn take(&mut self, limit: &RateLimit) -> bool {
+        let elapsed = self.refilled.elapsed();
+        self.refilled = Instant::now();
+
+        let refill = elapsed.as_millis() as u64 * u64::from(limit.per_second);
+        self.tokens = (self.tokens + refill).min(u64::from(limit.burst) * TOKEN);
+
+        if self.tokens >= TOKEN {
+            self.tokens -= TOKEN;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{id::FullId, rng};
+
+    const LIMIT: RateLimit = RateLimit {
+        per_second: 10,
+        burst: 5,
+    };
+
+    #[test]
+    #[cfg(feature = "mock_base")]
+    fn burst_then_sustained_rate() {
+        use fake_clock::FakeClock;
+
+        let mut limiter = RateLimiter::new(RateLimits {
+            per_addr: Some(LIMIT),
+            ..Default::default()
+        });
+        let addr = ([127, 0, 0, 1], 9000).into();
+
+        for _ in 0..LIMIT.burst {
+            assert_eq!(limiter.admit(addr, None), Admission::Accept);
+        }
+        assert_eq!(limiter.admit(addr, None), Admission::Reject);
+        assert_eq!(limiter.stats.rejected_by_addr, 1);
+
+        // Other peers have their own budget.
+        let other_addr = ([127, 0, 0, 1], 9001).into();
+        assert_eq!(limiter.admit(other_addr, None), Admission::Accept);
+
+        // One token is refilled every 100ms.
+        FakeClock::advance_time(100);
+        assert_eq!(limiter.admit(addr, None), Admission::Accept);
+        assert_eq!(limiter.admit(addr, None), Admission::Reject);
+    }
+
+    #[test]
+    fn repeat_offender_is_evicted() {
+        let threshold = 3;
+        let mut limiter = RateLimiter::new(RateLimits {
+            per_id: Some(LIMIT),
+            eviction_threshold: Some(threshold),
+            ..Default::default()
+        });
+        let pub_id = *FullId::gen(&mut rng::new()).public_id();
+
+        // The limit applies across all the addresses of the peer.
+        for port in 0..LIMIT.burst {
+            let addr = ([127, 0, 0, 1], 9000 + port as u16).into();
+            assert_eq!(limiter.admit(addr, Some(&pub_id)), Admission::Accept);
+        }
+
+        let addr = ([127, 0, 0, 1], 9000).into();
+        for _ in 1..threshold {
+            assert_eq!(limiter.admit(addr, Some(&pub_id)), Admission::Reject);
+        }
+        assert_eq!(
+            limiter.admit(addr, Some(&pub_id)),
+            Admission::RejectAndEvict(pub_id)
+        );
+        assert_eq!(limiter.stats.rejected_by_id, threshold);
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::consensus::Proven;
+use std::collections::VecDeque;
+
+/// Maximum number of the most recent beacon rounds we keep.
+pub const BEACON_HISTORY_SIZE: usize = 64;
+
+/// Input of a round of the section randomness beacon. The value of the round is the section
+/// signature of its input. BLS signatures are unique, so the value can't be predicted or biased
+/// without the cooperation of a quorum of the elders.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Debug)]
+pub struct BeaconInput {
+    /// Number of the round.
+    pub round: u64,
+    /// Value of the previous round, `None` for the first round.
+    pub previous: Option<bls::Signature>,
+}
+
+/// The most recent rounds of the section randomness beacon.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Beacon(VecDeque<Proven<BeaconInput>>);
+
+impl Beacon {
+    /// Returns the input of the next round.
+    pub fn next_input(&self) -> BeaconInput {
+        match self.0.back() {
+            Some(last) => BeaconInput {
+                round: last.value.round + 1,
+                previous: Some(last.proof.signature.clone()),
+            },
+            None => BeaconInput {
+                round: 0,
+                previous: None,
+            },
+        }
+    }
+
+    /// Returns the value of the given round, if we still have it.
+    pub fn get(&self, round: u64) -> Option<&bls::Signature> {
+        let first = self.0.front()?.value.round;
+        let index = round.checked_sub(first)?;
+        self.0
+            .get(index as usize)
+            .map(|proven| &proven.proof.signature)
+    }
+
+    /// Adds the next round. Returns whether it was added, that is if its input follows the last
+    /// round.
+    pub fn push(&mut self, round: Proven<BeaconInput>) -> bool {
+        if round.value != self.next_input() {
+            return false;
+        }
+
+        self.0.push_back(round);
+        if self.0.len() > BEACON_HISTORY_SIZE {
+            let _ = self.0.pop_front();
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::test_utils::{gen_secret_key, proven},
+        rng,
+    };
+
+    #[test]
+    fn rounds_are_chained() {
+        let mut rng = rng::new();
+        let sk = gen_secret_key(&mut rng);
+        let mut beacon = Beacon::default();
+
+        let first = proven(&sk, beacon.next_input());
+        assert!(beacon.push(first));
+
+        // Replaying a round is rejected.
+        let replayed = proven(
+            &sk,
+            BeaconInput {
+                round: 0,
+                previous: None,
+            },
+        );
+        assert!(!beacon.push(replayed));
+
+        let input = beacon.next_input();
+        assert_eq!(input.round, 1);
+        assert_eq!(input.previous.as_ref(), beacon.get(0));
+
+        assert!(beacon.push(proven(&sk, input)));
+        assert!(beacon.get(1).is_some());
+        assert!(beacon.get(2).is_none());
+    }
+
+    #[test]
+    fn old_rounds_are_dropped() {
+        let mut rng = rng::new();
+        let sk = gen_secret_key(&mut rng);
+        let mut beacon = Beacon::default();
+
+        for _ in 0..=BEACON_HISTORY_SIZE {
+            let input = beacon.next_input();
+            assert!(beacon.push(proven(&sk, input)));
+        }
+
+        assert!(beacon.get(0).is_none());
+        assert!(beacon.get(1).is_some());
+        assert!(beacon.get(BEACON_HISTORY_SIZE as u64).is_some());
+    }
+}
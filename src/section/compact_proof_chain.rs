@@ -0,0 +1,417 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::section_proof_chain::{SectionProofChain, TrustStatus};
+use crate::consensus::Proven;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    iter,
+};
+
+/// Returns the index of the key that signs the skip link to the key at `index`, or `None` if the
+/// key at `index` has no skip link (only the regular link from the previous key).
+///
+/// The key at index `n` is linked from the key at `n` with its lowest set bit cleared. This way
+/// any key can be reached from any earlier key in a logarithmic number of links.
+pub fn skip_link_source(index: u64) -> Option<u64> {
+    let source = index & index.wrapping_sub(1);
+    if source + 1 < index {
+        Some(source)
+    } else {
+        None
+    }
+}
+
+/// Returns whether the key at index `source` signs the skip link to any key after the one at
+/// `index`, i.e. whether our share of it is still needed once the key at `index` is our last key.
+/// The genesis key links to every power of two, so this is always the case for it: the retention of
+/// its share is bounded by our time as elder instead.
+pub fn signs_skip_links_after(source: u64, index: u64) -> bool {
+    if source == 0 {
+        return true;
+    }
+
+    // The furthest key linked from `source` is half way to the next key with fewer set bits.
+    let furthest = source + (source & source.wrapping_neg()) / 2;
+    skip_link_source(furthest) == Some(source) && index < furthest
+}
+
+/// Skip links of our section proof chain: signatures of keys by the older keys given by
+/// `skip_link_source`, indexed by the index of the signed key. Unlike the regular links, they sign
+/// the index of the key together with the key, so a link can't be passed off as one to another
+/// position in the chain.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
+pub struct SkipLinks(BTreeMap<u64, bls::Signature>);
+
+impl SkipLinks {
+    /// Adds the skip link to the key at the given index.
+    pub fn insert(&mut self, index: u64, signature: bls::Signature) {
+        let _ = self.0.insert(index, signature);
+    }
+
+    /// Returns whether we have the skip link to the key at the given index.
+    pub fn contains(&self, index: u64) -> bool {
+        self.0.contains_key(&index)
+    }
+
+    fn get(&self, index: u64) -> Option<&bls::Signature> {
+        self.0.get(&index)
+    }
+}
+
+/// Chain of section BLS keys where every key is proven (signed) by the previous key in the chain,
+/// except the first one. Unlike in `SectionProofChain`, the keys don't have to be consecutive: a
+/// key can be signed by an older key via a skip link, so proofs of keys far apart stay short.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize)]
+pub struct CompactProofChain {
+    head_index: u64,
+    head: bls::PublicKey,
+    tail: Vec<Link>,
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl CompactProofChain {
+    /// Creates the compact form of `chain` from the key at index `from` to the last key, using the
+    /// given skip links where possible. If `from` is past the last key, the chain consists of the
+    /// last key only.
+    pub fn new(chain: &SectionProofChain, from: u64, skip_links: &SkipLinks) -> Self {
+        let head_index = from.min(chain.last_key_index());
+        let head = *chain.key(head_index).unwrap_or_else(|| chain.last_key());
+
+        let mut tail = Vec::new();
+        let mut index = head_index;
+        while let Some(link) = next_link(chain, index, skip_links) {
+            index = link.index;
+            tail.push(link);
+        }
+
+        Self {
+            head_index,
+            head,
+            tail,
+        }
+    }
+
+    /// Appends the keys of `chain` that come after our last key, each linked from the previous
+    /// one. `chain` must contain our last key at the same index.
+    pub fn extend(&mut self, chain: &SectionProofChain) {
+        for index in self.last_key_index() + 1..=chain.last_key_index() {
+            if let (Some(key), Some(signature)) = (chain.key(index), chain.signature(index)) {
+                self.tail.push(Link {
+                    index,
+                    key: *key,
+                    signature: signature.clone(),
+                });
+            }
+        }
+    }
+
+    /// Returns the last key of the chain.
+    pub fn last_key(&self) -> &bls::PublicKey {
+        self.tail.last().map(|link| &link.key).unwrap_or(&self.head)
+    }
+
+    /// Index of the last key in the full chain.
+    pub fn last_key_index(&self) -> u64 {
+        self.tail
+            .last()
+            .map(|link| link.index)
+            .unwrap_or(self.head_index)
+    }
+
+    /// Returns the keys in the chain together with their indices in the full chain.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = (u64, &bls::PublicKey)> {
+        iter::once((self.head_index, &self.head))
+            .chain(self.tail.iter().map(|link| (link.index, &link.key)))
+    }
+
+    /// Returns whether the chain contains the given key.
+    pub fn has_key(&self, key: &bls::PublicKey) -> bool {
+        self.keys().any(|(_, chain_key)| chain_key == key)
+    }
+
+    /// Number of keys in the chain.
+    pub fn len(&self) -> usize {
+        1 + self.tail.len()
+    }
+
+    /// Verify this proof chain against the given trusted keys, with the same semantics as
+    /// `SectionProofChain::check_trust`. A full chain can be checked this way too by converting it
+    /// with `From`.
+    pub fn check_trust<'a, I>(&self, trusted_keys: I) -> TrustStatus
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        let trusted_keys: HashSet<_> = trusted_keys.into_iter().collect();
+        let keys: Vec<_> = self.keys().collect();
+        let trusted_position = keys.iter().rposition(|(_, key)| trusted_keys.contains(key));
+        let start = trusted_position.unwrap_or(0);
+
+        let (mut current_index, mut current_key) = keys[start];
+        for link in &self.tail[start..] {
+            if !link.verify(current_index, current_key) {
+                return TrustStatus::Invalid;
+            }
+
+            current_index = link.index;
+            current_key = &link.key;
+        }
+
+        if trusted_position.is_some() {
+            TrustStatus::Trusted
+        } else {
+            TrustStatus::Unknown
+        }
+    }
+
+    /// Verify that `proven` is signed by one of the keys of this chain and the chain against the
+    /// given trusted keys. Returns `Invalid` if the signature is invalid or by a key not in the
+    /// chain, otherwise the same as `check_trust`.
+    pub fn check_proven<'a, T, I>(&self, proven: &Proven<T>, trusted_keys: I) -> TrustStatus
+    where
+        T: Serialize,
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        let status = self.check_trust(trusted_keys);
+        if status == TrustStatus::Invalid {
+            return status;
+        }
+
+        let signed_by_chain = self.keys().any(|(_, key)| *key == proven.proof.public_key)
+            && bincode::serialize(&proven.value)
+                .map(|bytes| proven.proof.verify(&bytes))
+                .unwrap_or(false);
+
+        if signed_by_chain {
+            status
+        } else {
+            TrustStatus::Invalid
+        }
+    }
+}
+
+// Returns the link from the key at `index` to the furthest key of `chain` we have a skip link to,
+// or else to the next key. Returns `None` at the end of the chain.
+fn next_link(chain: &SectionProofChain, index: u64, skip_links: &SkipLinks) -> Option<Link> {
+    let last_index = chain.last_key_index();
+    let (next_index, signature) = (1..64)
+        .rev()
+        .filter_map(|shift| index.checked_add(1 << shift))
+        .filter(|&next_index| {
+            next_index <= last_index && skip_link_source(next_index) == Some(index)
+        })
+        .find_map(|next_index| {
+            skip_links
+                .get(next_index)
+                .map(|signature| (next_index, signature))
+        })
+        .or_else(|| {
+            let next_index = index.checked_add(1)?;
+            chain
+                .signature(next_index)
+                .map(|signature| (next_index, signature))
+        })?;
+
+    Some(Link {
+        index: next_index,
+        key: *chain.key(next_index)?,
+        signature: signature.clone(),
+    })
+}
+
+impl From<SectionProofChain> for CompactProofChain {
+    fn from(chain: SectionProofChain) -> Self {
+        Self::new(&chain, 0, &SkipLinks::default())
+    }
+}
+
+// Link of the compact proof chain: the key at the given index in the full chain, signed by the
+// previous key of the compact chain. That is either the previous key in the full chain or, for a
+// skip link, the key at `skip_link_source(index)`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize)]
+struct Link {
+    index: u64,
+    key: bls::PublicKey,
+    signature: bls::Signature,
+}
+
+impl Link {
+    // Verifies the link from the key at `index`.
+    #[cfg_attr(feature = "mock_base", allow(clippy::trivially_copy_pass_by_ref))]
+    fn verify(&self, index: u64, public_key: &bls::PublicKey) -> bool {
+        let bytes = if index.checked_add(1) == Some(self.index) {
+            bincode::serialize(&self.key)
+        } else if skip_link_source(self.index) == Some(index) {
+            bincode::serialize(&(self.index, &self.key))
+        } else {
+            return false;
+        };
+
+        bytes
+            .map(|bytes| public_key.verify(&self.signature, &bytes))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::test_utils::gen_secret_key,
+        rng::{self, MainRng},
+    };
+
+    #[test]
+    fn skip_link_sources() {
+        assert_eq!(skip_link_source(0), None);
+        assert_eq!(skip_link_source(1), None);
+        assert_eq!(skip_link_source(2), Some(0));
+        assert_eq!(skip_link_source(3), None);
+        assert_eq!(skip_link_source(12), Some(8));
+        assert_eq!(skip_link_source(16), Some(0));
+    }
+
+    #[test]
+    fn skip_link_sources_are_needed_until_their_furthest_link() {
+        // The genesis key links to every power of two.
+        assert!(signs_skip_links_after(0, 1 << 40));
+
+        // 8 links to 10 and 12.
+        assert!(signs_skip_links_after(8, 11));
+        assert!(!signs_skip_links_after(8, 12));
+
+        // Neither 2 nor 5 link to any key.
+        assert!(!signs_skip_links_after(2, 2));
+        assert!(!signs_skip_links_after(5, 5));
+
+        for index in 1..64 {
+            if let Some(source) = skip_link_source(index) {
+                assert!(signs_skip_links_after(source, index - 1));
+            }
+        }
+    }
+
+    #[test]
+    fn full_form() {
+        let mut rng = rng::new();
+        let (chain, _, _) = gen_chain(&mut rng, 5);
+        let compact = CompactProofChain::from(chain.clone());
+
+        assert_eq!(compact.len(), chain.len());
+        assert_eq!(compact.last_key(), chain.last_key());
+        assert_eq!(
+            compact.check_trust(iter::once(chain.first_key())),
+            TrustStatus::Trusted
+        );
+    }
+
+    #[test]
+    fn skip_links_shorten_the_chain() {
+        let mut rng = rng::new();
+        let (chain, secret_keys, skip_links) = gen_chain(&mut rng, 14);
+
+        // 0 -> 8 -> 12 -> 13
+        let compact = CompactProofChain::new(&chain, 0, &skip_links);
+        let indices: Vec<_> = compact.keys().map(|(index, _)| index).collect();
+        assert_eq!(indices, vec![0, 8, 12, 13]);
+        assert!(compact.has_key(chain.key(12).unwrap()));
+        assert!(!compact.has_key(chain.key(11).unwrap()));
+        assert_eq!(compact.last_key_index(), 13);
+        assert_eq!(
+            compact.check_trust(iter::once(chain.first_key())),
+            TrustStatus::Trusted
+        );
+
+        // 3 -> 4 -> 6 -> 7 -> 8 -> 12 -> 13
+        let compact = CompactProofChain::new(&chain, 3, &skip_links);
+        let indices: Vec<_> = compact.keys().map(|(index, _)| index).collect();
+        assert_eq!(indices, vec![3, 4, 6, 7, 8, 12, 13]);
+
+        // None of the keys is trusted.
+        let other_key = gen_secret_key(&mut rng).public_key();
+        assert_eq!(
+            compact.check_trust(iter::once(&other_key)),
+            TrustStatus::Unknown
+        );
+
+        // Skip link signed by a wrong key.
+        let mut skip_links = skip_links;
+        let bytes = bincode::serialize(&(8u64, chain.key(8).unwrap())).unwrap();
+        skip_links.insert(8, secret_keys[1].sign(&bytes));
+        let compact = CompactProofChain::new(&chain, 0, &skip_links);
+        assert_eq!(
+            compact.check_trust(iter::once(chain.first_key())),
+            TrustStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn start_past_the_last_key() {
+        let mut rng = rng::new();
+        let (chain, _, skip_links) = gen_chain(&mut rng, 3);
+
+        let compact = CompactProofChain::new(&chain, 5, &skip_links);
+        assert_eq!(compact.len(), 1);
+        assert_eq!(compact.last_key_index(), 2);
+        assert_eq!(compact.last_key(), chain.last_key());
+    }
+
+    #[test]
+    fn links_must_match_their_index() {
+        let mut rng = rng::new();
+        let (chain, _, skip_links) = gen_chain(&mut rng, 9);
+        let trusted_key = chain.first_key();
+
+        // 0 -> 8
+        let compact = CompactProofChain::new(&chain, 0, &skip_links);
+        assert_eq!(
+            compact.check_trust(iter::once(trusted_key)),
+            TrustStatus::Trusted
+        );
+
+        // The skip link presented as a link to another index.
+        let mut relabelled = compact.clone();
+        relabelled.tail[0].index = 4;
+        assert_eq!(
+            relabelled.check_trust(iter::once(trusted_key)),
+            TrustStatus::Invalid
+        );
+
+        // The link to key 1 presented as a link to key 0.
+        let mut repeated = CompactProofChain::from(chain.slice(..=1));
+        repeated.tail[0].index = 0;
+        assert_eq!(
+            repeated.check_trust(iter::once(trusted_key)),
+            TrustStatus::Invalid
+        );
+    }
+
+    // Generates a chain of `len` keys together with their secret keys and all the skip links.
+    fn gen_chain(
+        rng: &mut MainRng,
+        len: u64,
+    ) -> (SectionProofChain, Vec<bls::SecretKey>, SkipLinks) {
+        let secret_keys: Vec<_> = (0..len).map(|_| gen_secret_key(rng)).collect();
+        let mut chain = SectionProofChain::new(secret_keys[0].public_key());
+        let mut skip_links = SkipLinks::default();
+
+        for index in 1..len {
+            let key = secret_keys[index as usize].public_key();
+            let bytes = bincode::serialize(&key).unwrap();
+            chain.push(key, secret_keys[index as usize - 1].sign(&bytes));
+
+            if let Some(source) = skip_link_source(index) {
+                let bytes = bincode::serialize(&(index, key)).unwrap();
+                skip_links.insert(index, secret_keys[source as usize].sign(&bytes));
+            }
+        }
+
+        (chain, secret_keys, skip_links)
+    }
+}
@@ -115,10 +115,14 @@ pub fn to_sign(name: &XorName, state: MemberState) -> (&XorName, MemberState) {
     (name, state)
 }
 
+/// Membership state of a section member.
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, Debug)]
 pub enum MemberState {
+    /// The node is an active member of the section.
     Joined,
+    /// The node is being relocated to another section.
     Relocating,
+    /// The node left the section.
     // TODO: we should track how long the node has been away. If longer than some limit, remove it
     // from the list. Otherwise we allow it to return.
     Left,
@@ -0,0 +1,127 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    compact_proof_chain::CompactProofChain, member_info::MemberState,
+    section_proof_chain::TrustStatus,
+};
+use crate::consensus::Proven;
+use xor_name::{Prefix, XorName};
+
+/// Statement about a member of a section, signed by the section in `MembershipCertificate`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MembershipStatement {
+    /// Name of the member.
+    pub name: XorName,
+    /// Age of the member.
+    pub age: u8,
+    /// Membership state of the member.
+    pub state: MemberState,
+    /// Prefix of the section.
+    pub prefix: Prefix,
+}
+
+/// Portable proof that a node is a member of a section with the given age, as returned by
+/// `Node::membership_certificate`. Can be verified by anyone trusting a key of the section proof
+/// chain, typically the genesis key, without connecting to the network.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct MembershipCertificate {
+    /// The statement, signed by the section.
+    pub statement: Proven<MembershipStatement>,
+    /// Proof of the key that signed the statement, starting from the first section key.
+    pub proof_chain: CompactProofChain,
+}
+
+impl MembershipCertificate {
+    /// Name of the certified member.
+    pub fn name(&self) -> &XorName {
+        &self.statement.value.name
+    }
+
+    /// Age of the certified member.
+    pub fn age(&self) -> u8 {
+        self.statement.value.age
+    }
+
+    /// Verify the certificate against the given trusted keys. Returns `Trusted` only if the
+    /// statement is signed by a key of the proof chain and the chain is trusted.
+    ///
+    /// Note the certificate proves the membership at the time it was signed. Applications that
+    /// need a recent one can require the signing key to be the latest section key they know.
+    pub fn verify<'a, I>(&self, trusted_keys: I) -> TrustStatus
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        if !self.statement.value.prefix.matches(self.name()) {
+            return TrustStatus::Invalid;
+        }
+
+        self.proof_chain.check_proven(&self.statement, trusted_keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::test_utils::{gen_secret_key, proven},
+        rng,
+        section::SectionProofChain,
+    };
+    use rand::Rng;
+    use std::iter;
+
+    #[test]
+    fn verify() {
+        let mut rng = rng::new();
+        let sk0 = gen_secret_key(&mut rng);
+        let sk1 = gen_secret_key(&mut rng);
+        let mut chain = SectionProofChain::new(sk0.public_key());
+        let pk1 = sk1.public_key();
+        chain.push(pk1, sk0.sign(&bincode::serialize(&pk1).unwrap()));
+
+        let statement = MembershipStatement {
+            name: rng.gen(),
+            age: 7,
+            state: MemberState::Joined,
+            prefix: Prefix::default(),
+        };
+        let certificate = MembershipCertificate {
+            statement: proven(&sk1, statement.clone()),
+            proof_chain: CompactProofChain::from(chain.clone()),
+        };
+        assert_eq!(
+            certificate.verify(iter::once(&sk0.public_key())),
+            TrustStatus::Trusted
+        );
+
+        let other_key = gen_secret_key(&mut rng).public_key();
+        assert_eq!(
+            certificate.verify(iter::once(&other_key)),
+            TrustStatus::Unknown
+        );
+
+        // Tampered age.
+        let mut tampered = certificate.clone();
+        tampered.statement.value.age = 20;
+        assert_eq!(
+            tampered.verify(iter::once(&sk0.public_key())),
+            TrustStatus::Invalid
+        );
+
+        // Signed by a key not in the chain.
+        let certificate = MembershipCertificate {
+            statement: proven(&gen_secret_key(&mut rng), statement),
+            proof_chain: CompactProofChain::from(chain),
+        };
+        assert_eq!(
+            certificate.verify(iter::once(&sk0.public_key())),
+            TrustStatus::Invalid
+        );
+    }
+}
@@ -7,8 +7,13 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 pub mod member_info;
+pub mod recovery;
 
+mod beacon;
+mod compact_proof_chain;
 mod elders_info;
+mod membership_certificate;
+mod network_map;
 mod network_stats;
 mod prefix_map;
 mod section_keys;
@@ -17,15 +22,22 @@ mod section_members;
 mod section_proof_chain;
 mod section_update_barrier;
 mod shared_state;
+mod upgrade;
 
 pub use self::{
+    beacon::{Beacon, BeaconInput},
+    compact_proof_chain::{skip_link_source, CompactProofChain, SkipLinks},
     elders_info::{quorum_count, EldersInfo},
     member_info::{AgeCounter, MemberInfo, MemberState, MIN_AGE, MIN_AGE_COUNTER},
+    membership_certificate::{MembershipCertificate, MembershipStatement},
+    network_map::{LocatedSection, NetworkMap, SectionEntry},
     network_stats::NetworkStats,
+    recovery::{RecoveryCertificate, RecoverySnapshot},
     section_keys::{SectionKeyShare, SectionKeysProvider},
     section_map::{NeighbourEldersRemoved, SectionMap},
     section_members::SectionMembers,
     section_proof_chain::{SectionProofChain, TrustStatus},
     section_update_barrier::{SectionUpdateBarrier, SectionUpdateDetails},
     shared_state::SharedState,
+    upgrade::Upgrade,
 };
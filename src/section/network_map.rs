@@ -0,0 +1,188 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    compact_proof_chain::CompactProofChain,
+    elders_info::EldersInfo,
+    section_proof_chain::{SectionProofChain, TrustStatus},
+};
+use crate::consensus::Proven;
+use std::collections::BTreeMap;
+use xor_name::Prefix;
+
+/// Section known to a node, with its elders and latest key as agreed on by the node's section.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SectionEntry {
+    /// Elders of the section, signed by one of the keys of `NetworkMap::proof_chain`.
+    pub elders_info: Proven<EldersInfo>,
+    /// Latest known key of the section, signed by one of the keys of `NetworkMap::proof_chain`.
+    /// `None` for our own section (its latest key is the last key of the proof chain) or if we
+    /// haven't learned the key yet.
+    pub key: Option<Proven<(Prefix, bls::PublicKey)>>,
+}
+
+/// Every section known to a node, together with the proof chain of the node's section which the
+/// signatures of all the entries can be verified against.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NetworkMap {
+    /// Prefix of our section.
+    pub our_prefix: Prefix,
+    /// Known sections, including ours, by their prefixes.
+    pub sections: BTreeMap<Prefix, SectionEntry>,
+    /// Our section proof chain.
+    pub proof_chain: SectionProofChain,
+}
+
+impl NetworkMap {
+    /// Verify the map against the given trusted keys. Returns `Trusted` if the proof chain is
+    /// trusted and every entry is signed by a key of the chain, `Unknown` if the chain is valid but
+    /// not trusted and `Invalid` otherwise.
+    pub fn check_trust<'a, I>(&self, trusted_keys: I) -> TrustStatus
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        let status = self.proof_chain.check_trust(trusted_keys);
+        if status == TrustStatus::Invalid {
+            return status;
+        }
+
+        let entries_valid = self.sections.values().all(|entry| {
+            entry.elders_info.verify(&self.proof_chain)
+                && entry
+                    .key
+                    .as_ref()
+                    .map(|key| key.verify(&self.proof_chain))
+                    .unwrap_or(true)
+        });
+
+        if entries_valid {
+            status
+        } else {
+            TrustStatus::Invalid
+        }
+    }
+}
+
+/// Section responsible for a name, as returned by `Node::locate`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct LocatedSection {
+    /// Elders of the section. Signed by the section itself if it answered our query, or by our
+    /// section if it is one of our neighbours.
+    pub elders_info: Proven<EldersInfo>,
+    /// Proof of the key that signed `elders_info`.
+    pub proof_chain: CompactProofChain,
+}
+
+impl LocatedSection {
+    /// Verify the located section against the given trusted keys, with the same semantics as
+    /// `NetworkMap::check_trust`.
+    pub fn check_trust<'a, I>(&self, trusted_keys: I) -> TrustStatus
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        self.proof_chain
+            .check_proven(&self.elders_info, trusted_keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::test_utils::{gen_secret_key, proven},
+        rng,
+    };
+    use std::iter;
+
+    #[test]
+    fn network_map_check_trust() {
+        let mut rng = rng::new();
+        let sk0 = gen_secret_key(&mut rng);
+        let sk1 = gen_secret_key(&mut rng);
+        let mut proof_chain = SectionProofChain::new(sk0.public_key());
+        let pk1 = sk1.public_key();
+        proof_chain.push(pk1, sk0.sign(&bincode::serialize(&pk1).unwrap()));
+
+        let p0: Prefix = "0".parse().unwrap();
+        let p1: Prefix = "1".parse().unwrap();
+        let their_key = gen_secret_key(&mut rng).public_key();
+
+        let mut map = NetworkMap {
+            our_prefix: p0,
+            sections: iter::once((
+                p0,
+                SectionEntry {
+                    elders_info: proven(&sk1, EldersInfo::new(Default::default(), p0)),
+                    key: None,
+                },
+            ))
+            .collect(),
+            proof_chain,
+        };
+        let _ = map.sections.insert(
+            p1,
+            SectionEntry {
+                elders_info: proven(&sk0, EldersInfo::new(Default::default(), p1)),
+                key: Some(proven(&sk1, (p1, their_key))),
+            },
+        );
+
+        assert_eq!(
+            map.check_trust(iter::once(&sk0.public_key())),
+            TrustStatus::Trusted
+        );
+        assert_eq!(
+            map.check_trust(iter::once(&their_key)),
+            TrustStatus::Unknown
+        );
+
+        // Entry signed by a key not in the chain.
+        let other_sk = gen_secret_key(&mut rng);
+        let _ = map.sections.insert(
+            p1,
+            SectionEntry {
+                elders_info: proven(&other_sk, EldersInfo::new(Default::default(), p1)),
+                key: None,
+            },
+        );
+        assert_eq!(
+            map.check_trust(iter::once(&sk0.public_key())),
+            TrustStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn located_section_check_trust() {
+        let mut rng = rng::new();
+        let sk0 = gen_secret_key(&mut rng);
+        let sk1 = gen_secret_key(&mut rng);
+        let mut chain = SectionProofChain::new(sk0.public_key());
+        let pk1 = sk1.public_key();
+        chain.push(pk1, sk0.sign(&bincode::serialize(&pk1).unwrap()));
+
+        let elders_info = EldersInfo::new(Default::default(), Prefix::default());
+        let located = LocatedSection {
+            elders_info: proven(&sk1, elders_info.clone()),
+            proof_chain: CompactProofChain::from(chain.clone()),
+        };
+        assert_eq!(
+            located.check_trust(iter::once(&sk0.public_key())),
+            TrustStatus::Trusted
+        );
+
+        let other_sk = gen_secret_key(&mut rng);
+        let located = LocatedSection {
+            elders_info: proven(&other_sk, elders_info),
+            proof_chain: CompactProofChain::from(chain),
+        };
+        assert_eq!(
+            located.check_trust(iter::once(&sk0.public_key())),
+            TrustStatus::Invalid
+        );
+    }
+}
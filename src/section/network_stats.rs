@@ -6,6 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::transport::QueueDepths;
 use std::{cmp, iter};
 
 pub struct NetworkStats {
@@ -15,7 +16,7 @@ pub struct NetworkStats {
 }
 
 impl NetworkStats {
-    pub fn print(&self) {
+    pub fn print(&self, queue_depths: QueueDepths) {
         const LEVEL: log::Level = log::Level::Info;
 
         if log::log_enabled!(LEVEL) {
@@ -25,11 +26,16 @@ impl NetworkStats {
             } else {
                 format!("Estimated total network elders: {}", self.total_elders)
             };
-            let sep_len = cmp::max(status_str.len(), network_estimate.len());
+            let queues_str = format!("Queued messages: {}", queue_depths);
+            let sep_len = cmp::max(
+                cmp::max(status_str.len(), network_estimate.len()),
+                queues_str.len(),
+            );
             let sep_str = iter::repeat('-').take(sep_len).collect::<String>();
             log!(target: "stats", LEVEL, " -{}- ", sep_str);
             log!(target: "stats", LEVEL, "| {:<1$} |", status_str, sep_len);
             log!(target: "stats", LEVEL, "| {:<1$} |", network_estimate, sep_len);
+            log!(target: "stats", LEVEL, "| {:<1$} |", queues_str, sep_len);
             log!(target: "stats", LEVEL, " -{}- ", sep_str);
         }
     }
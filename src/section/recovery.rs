@@ -0,0 +1,304 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{quorum_count, EldersInfo, SectionProofChain, SharedState, TrustStatus};
+use crate::{
+    consensus::Proven,
+    crypto::signing::Signature,
+    id::{P2pNode, PublicId},
+};
+use std::collections::BTreeMap;
+use xor_name::{Prefix, XorName};
+
+/// Snapshot of the state of our section, signed by the section key. The elders produce it
+/// periodically and every member keeps the latest one, so that if all the elders are lost at once
+/// the surviving members can use it to re-establish the section under a new key.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Debug)]
+pub struct RecoverySnapshot {
+    /// Sequence number of the snapshot, incremented with every new snapshot of the section.
+    pub index: u64,
+    /// Parsec version of the section at the time the snapshot was taken.
+    pub parsec_version: u64,
+    /// The elders of the section at the time the snapshot was taken.
+    pub elders_info: EldersInfo,
+    /// All the active members of the section together with their ages.
+    pub members: Vec<(P2pNode, u8)>,
+    /// Elders of the neighbour sections.
+    pub neighbours: Vec<EldersInfo>,
+    /// Latest known keys of the other sections.
+    pub their_keys: Vec<(Prefix, bls::PublicKey)>,
+    /// History of our section keys. The snapshot is signed with its last key.
+    pub history: SectionProofChain,
+}
+
+impl RecoverySnapshot {
+    /// Captures the given section state.
+    pub fn new(index: u64, parsec_version: u64, shared_state: &SharedState) -> Self {
+        Self {
+            index,
+            parsec_version,
+            elders_info: shared_state.our_info().clone(),
+            members: shared_state
+                .our_members
+                .active()
+                .map(|info| (info.p2p_node.clone(), info.age()))
+                .collect(),
+            neighbours: shared_state.sections.neighbours().cloned().collect(),
+            their_keys: shared_state
+                .sections
+                .keys()
+                .map(|(prefix, key)| (*prefix, *key))
+                .collect(),
+            history: shared_state.our_history.clone(),
+        }
+    }
+
+    /// Returns the members that survive the loss of the elders, i.e. all the members except the
+    /// elders.
+    pub fn survivors(&self) -> impl Iterator<Item = &(P2pNode, u8)> {
+        self.members
+            .iter()
+            .filter(move |(p2p_node, _)| !self.elders_info.elders.contains_key(p2p_node.name()))
+    }
+
+    /// Returns the members that become the elders of the recovered section: the oldest survivors,
+    /// ties broken by name, one for each of the lost elders.
+    pub fn recovery_elders(&self) -> BTreeMap<XorName, P2pNode> {
+        let mut survivors: Vec<_> = self.survivors().collect();
+        survivors.sort_by(|(lhs_node, lhs_age), (rhs_node, rhs_age)| {
+            rhs_age
+                .cmp(lhs_age)
+                .then_with(|| lhs_node.name().cmp(rhs_node.name()))
+        });
+
+        survivors
+            .into_iter()
+            .take(self.elders_info.elders.len())
+            .map(|(p2p_node, _)| (*p2p_node.name(), p2p_node.clone()))
+            .collect()
+    }
+
+    /// Returns the `EldersInfo` of the recovered section.
+    pub fn recovery_elders_info(&self) -> EldersInfo {
+        EldersInfo::new(self.recovery_elders(), self.elders_info.prefix)
+    }
+}
+
+/// Certificate linking the key of a recovered section to the key of the section that signed the
+/// recovery snapshot. Only the recovery elders designated by the snapshot can create it.
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Debug)]
+pub struct RecoveryCertificate {
+    /// The snapshot the section was recovered from.
+    pub snapshot: Proven<RecoverySnapshot>,
+    /// The elders of the recovered section, signed with the new section key.
+    pub elders_info: Proven<EldersInfo>,
+    /// Signatures of the new section key by the recovery elders.
+    pub signatures: BTreeMap<PublicId, Signature>,
+}
+
+impl RecoveryCertificate {
+    /// The key of the recovered section.
+    pub fn new_key(&self) -> &bls::PublicKey {
+        &self.elders_info.proof.public_key
+    }
+
+    /// Verifies the certificate against the given trusted keys of the recovered section.
+    pub fn verify<'a, I>(&self, trusted_keys: I) -> bool
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        let history = &self.snapshot.value.history;
+        if *history.last_key() != self.snapshot.proof.public_key
+            || !self.snapshot.verify(history)
+            || history.check_trust(trusted_keys) != TrustStatus::Trusted
+        {
+            return false;
+        }
+
+        let elders_info = self.snapshot.value.recovery_elders_info();
+        if elders_info != self.elders_info.value {
+            return false;
+        }
+
+        let new_key_history = SectionProofChain::new(*self.new_key());
+        if !self.elders_info.verify(&new_key_history) {
+            return false;
+        }
+
+        let bytes = if let Ok(bytes) = bincode::serialize(&to_sign(
+            &self.snapshot.value,
+            &self.snapshot.proof.public_key,
+            self.new_key(),
+        )) {
+            bytes
+        } else {
+            return false;
+        };
+
+        let valid_signatures = self
+            .signatures
+            .iter()
+            .filter(|(pub_id, _)| {
+                elders_info
+                    .elders
+                    .get(pub_id.name())
+                    .map(P2pNode::public_id)
+                    == Some(pub_id)
+            })
+            .filter(|(pub_id, signature)| pub_id.verify(&bytes, signature))
+            .count();
+
+        valid_signatures >= quorum_count(elders_info.elders.len())
+    }
+}
+
+/// Get the fields the recovery elders sign to link the new section key to the snapshot.
+pub fn to_sign<'a>(
+    snapshot: &RecoverySnapshot,
+    snapshot_key: &'a bls::PublicKey,
+    new_key: &'a bls::PublicKey,
+) -> (u64, &'a bls::PublicKey, &'a bls::PublicKey) {
+    (snapshot.index, snapshot_key, new_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus::test_utils::{gen_secret_key, proven},
+        id::FullId,
+        rng::{self, MainRng},
+    };
+    use std::net::SocketAddr;
+
+    #[test]
+    fn recovery_elders_are_the_oldest_survivors() {
+        let mut rng = rng::new();
+        let key = gen_secret_key(&mut rng).public_key();
+        let (snapshot, _) = gen_snapshot(&mut rng, key, &[10, 10, 10], &[5, 8, 6, 8, 7]);
+
+        let mut ages: Vec<_> = snapshot
+            .recovery_elders()
+            .keys()
+            .map(|name| {
+                snapshot
+                    .members
+                    .iter()
+                    .find(|(p2p_node, _)| p2p_node.name() == name)
+                    .map(|(_, age)| *age)
+                    .unwrap()
+            })
+            .collect();
+        ages.sort();
+        assert_eq!(ages, vec![7, 8, 8]);
+    }
+
+    #[test]
+    fn verify_certificate() {
+        let mut rng = rng::new();
+        let old_sk = gen_secret_key(&mut rng);
+        let (snapshot, full_ids) = gen_snapshot(
+            &mut rng,
+            old_sk.public_key(),
+            &[10, 10, 10],
+            &[5, 8, 6, 8, 7],
+        );
+        let snapshot = proven(&old_sk, snapshot);
+
+        let new_sk = gen_secret_key(&mut rng);
+        let elders_info = proven(&new_sk, snapshot.value.recovery_elders_info());
+        let bytes = bincode::serialize(&to_sign(
+            &snapshot.value,
+            &old_sk.public_key(),
+            &new_sk.public_key(),
+        ))
+        .unwrap();
+
+        let recovery_elder_ids: Vec<_> = elders_info
+            .value
+            .elders
+            .keys()
+            .map(|name| {
+                full_ids
+                    .iter()
+                    .find(|id| id.public_id().name() == name)
+                    .unwrap()
+            })
+            .collect();
+        let mut certificate = RecoveryCertificate {
+            snapshot,
+            elders_info,
+            signatures: recovery_elder_ids
+                .iter()
+                .map(|full_id| (*full_id.public_id(), full_id.sign(&bytes)))
+                .collect(),
+        };
+
+        assert!(certificate.verify(&[old_sk.public_key()]));
+        assert!(!certificate.verify(&[gen_secret_key(&mut rng).public_key()]));
+
+        // Without a quorum of the recovery elders.
+        let signer = *recovery_elder_ids[0].public_id();
+        certificate.signatures = certificate
+            .signatures
+            .into_iter()
+            .filter(|(pub_id, _)| *pub_id == signer)
+            .collect();
+        assert!(!certificate.verify(&[old_sk.public_key()]));
+    }
+
+    // Generate a snapshot with elders and adults of the given ages, with a history consisting of
+    // the single given key. Returns also the ids of the adults.
+    fn gen_snapshot(
+        rng: &mut MainRng,
+        key: bls::PublicKey,
+        elder_ages: &[u8],
+        adult_ages: &[u8],
+    ) -> (RecoverySnapshot, Vec<FullId>) {
+        let gen_node = |rng: &mut MainRng, index: usize| {
+            let full_id = FullId::gen(rng);
+            let addr: SocketAddr = ([127, 0, 0, 1], 9000 + index as u16).into();
+            let p2p_node = P2pNode::new(*full_id.public_id(), addr);
+            (full_id, p2p_node)
+        };
+
+        let elders: Vec<_> = elder_ages
+            .iter()
+            .enumerate()
+            .map(|(index, age)| (gen_node(rng, index).1, *age))
+            .collect();
+        let (adult_ids, adults): (Vec<_>, Vec<_>) = adult_ages
+            .iter()
+            .enumerate()
+            .map(|(index, age)| {
+                let (full_id, p2p_node) = gen_node(rng, elders.len() + index);
+                (full_id, (p2p_node, *age))
+            })
+            .unzip();
+
+        let elders_info = EldersInfo::new(
+            elders
+                .iter()
+                .map(|(p2p_node, _)| (*p2p_node.name(), p2p_node.clone()))
+                .collect(),
+            Prefix::default(),
+        );
+
+        let snapshot = RecoverySnapshot {
+            index: 0,
+            parsec_version: 0,
+            elders_info,
+            members: elders.into_iter().chain(adults).collect(),
+            neighbours: vec![],
+            their_keys: vec![],
+            history: SectionProofChain::new(key),
+        };
+
+        (snapshot, adult_ids)
+    }
+}
@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::elders_info::EldersInfo;
+use super::{compact_proof_chain::signs_skip_links_after, elders_info::EldersInfo};
 use crate::{
     consensus::DkgResult,
     error::{Result, RoutingError},
@@ -35,6 +35,12 @@ pub struct SectionKeysProvider {
     /// then members are disjoint. We are working around not having access to the prefix for the
     /// DkgResult but only the list of participants.
     new: BTreeMap<XorName, DkgResult>,
+    /// Our shares of the previous section keys, by the index of the key in our section proof
+    /// chain. Kept as long as they have skip links to sign from those keys to the new ones, which
+    /// for the older keys can be many keys later, but only while we remain an elder: once demoted
+    /// we don't sign the links to the new keys anymore. This bounds the shares to the keys of our
+    /// time as elder, of which at most one per bit of the key index still has links to sign.
+    past: BTreeMap<u64, SectionKeyShare>,
 }
 
 impl SectionKeysProvider {
@@ -42,6 +48,7 @@ impl SectionKeysProvider {
         Self {
             current,
             new: Default::default(),
+            past: Default::default(),
         }
     }
 
@@ -66,7 +73,19 @@ impl SectionKeysProvider {
         Ok(())
     }
 
-    pub fn finalise_dkg(&mut self, our_name: &XorName, elders_info: &EldersInfo) -> Result<()> {
+    /// Returns our share of the past section key at the given index, if we still have it.
+    pub fn past_key_share(&self, index: u64) -> Option<&SectionKeyShare> {
+        self.past.get(&index)
+    }
+
+    /// Replaces the current key share with the one from the DKG result for the given elders. The
+    /// current key share, if any, is kept as the share of the key at `current_index`.
+    pub fn finalise_dkg(
+        &mut self,
+        our_name: &XorName,
+        elders_info: &EldersInfo,
+        current_index: u64,
+    ) -> Result<()> {
         let first_name = elders_info
             .elders
             .keys()
@@ -78,6 +97,10 @@ impl SectionKeysProvider {
             .ok_or(RoutingError::InvalidElderDkgResult)?;
         let public_key_set = dkg_result.public_key_set;
 
+        if let Some(current) = self.current.take() {
+            let _ = self.past.insert(current_index, current);
+        }
+
         self.current = dkg_result
             .secret_key_share
             .and_then(|secret_key_share| {
@@ -92,6 +115,20 @@ impl SectionKeysProvider {
             });
         self.new.clear();
 
+        if self.current.is_some() {
+            let done: Vec<_> = self
+                .past
+                .keys()
+                .copied()
+                .filter(|source| !signs_skip_links_after(*source, current_index))
+                .collect();
+            for source in done {
+                let _ = self.past.remove(&source);
+            }
+        } else {
+            self.past.clear();
+        }
+
         Ok(())
     }
 
@@ -103,3 +140,67 @@ impl SectionKeysProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consensus,
+        id::{FullId, P2pNode},
+        rng::{self, MainRng},
+    };
+    use std::{iter, net::SocketAddr};
+    use xor_name::Prefix;
+
+    #[test]
+    fn past_key_shares_are_kept_only_while_elder() {
+        let mut rng = rng::new();
+        let full_id = FullId::gen(&mut rng);
+        let addr: SocketAddr = ([127, 0, 0, 1], 9000).into();
+        let elders_info = EldersInfo::new(
+            iter::once((
+                *full_id.public_id().name(),
+                P2pNode::new(*full_id.public_id(), addr),
+            ))
+            .collect(),
+            Prefix::default(),
+        );
+        let participants: BTreeSet<_> = iter::once(*full_id.public_id()).collect();
+
+        let secret_key_set = consensus::generate_secret_key_set(&mut rng, 1);
+        let mut provider = SectionKeysProvider::new(Some(SectionKeyShare {
+            public_key_set: secret_key_set.public_keys(),
+            index: 0,
+            secret_key_share: secret_key_set.secret_key_share(0),
+        }));
+        for index in 0..3 {
+            let dkg_result = gen_dkg_result(&mut rng, true);
+            provider
+                .handle_dkg_result_event(&participants, &dkg_result)
+                .unwrap();
+            provider
+                .finalise_dkg(full_id.public_id().name(), &elders_info, index)
+                .unwrap();
+        }
+
+        // The genesis key links to the key at index 4, the key at index 1 to none.
+        assert!(provider.past_key_share(0).is_some());
+        assert!(provider.past_key_share(1).is_none());
+
+        let dkg_result = gen_dkg_result(&mut rng, false);
+        provider
+            .handle_dkg_result_event(&participants, &dkg_result)
+            .unwrap();
+        provider
+            .finalise_dkg(full_id.public_id().name(), &elders_info, 3)
+            .unwrap();
+        assert!(provider.key_share().is_err());
+        assert!(provider.past_key_share(0).is_none());
+    }
+
+    fn gen_dkg_result(rng: &mut MainRng, with_share: bool) -> DkgResult {
+        let secret_key_set = consensus::generate_secret_key_set(rng, 1);
+        let secret_key_share = Some(secret_key_set.secret_key_share(0)).filter(|_| with_share);
+        DkgResult::new(secret_key_set.public_keys(), secret_key_share)
+    }
+}
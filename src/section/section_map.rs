@@ -276,10 +276,24 @@ impl SectionMap {
     }
 
     /// Returns iterator over all neighbours sections.
-    #[cfg(any(test, feature = "mock_base"))]
     pub fn neighbours(&self) -> impl Iterator<Item = &EldersInfo> {
         self.neighbours.iter().map(|info| &info.value)
     }
+
+    /// Returns iterator over all neighbours sections together with their proofs.
+    pub fn proven_neighbours(&self) -> impl Iterator<Item = &Proven<EldersInfo>> {
+        self.neighbours.iter()
+    }
+
+    /// Returns the neighbour section that `name` belongs to, together with its proof.
+    pub fn proven_neighbour_by_name(&self, name: &XorName) -> Option<&Proven<EldersInfo>> {
+        self.neighbours.get_matching(name)
+    }
+
+    /// Returns the latest known key of the section with the given prefix, together with its proof.
+    pub fn proven_key(&self, prefix: &Prefix) -> Option<&Proven<(Prefix, bls::PublicKey)>> {
+        self.keys.get(prefix)
+    }
 }
 
 // Neighbour section elders that got removed/demoted.
@@ -16,8 +16,9 @@ use crate::{consensus::Proof, id::P2pNode};
 use itertools::Itertools;
 use std::{
     cmp::Ordering,
-    collections::{btree_map::Entry, BTreeMap},
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
     mem,
+    net::SocketAddr,
 };
 use xor_name::{Prefix, XorName};
 
@@ -73,27 +74,37 @@ impl SectionMembers {
             .map(|info| &info.p2p_node)
     }
 
-    /// Returns the candidates for elders out of all the nodes in this section.
+    /// Returns the candidates for elders out of all the nodes in this section, except the
+    /// `excluded` ones.
     pub fn elder_candidates(
         &self,
         elder_size: usize,
         current_elders: &EldersInfo,
+        excluded: &BTreeSet<XorName>,
     ) -> BTreeMap<XorName, P2pNode> {
-        elder_candidates(elder_size, current_elders, self.joined())
+        elder_candidates(
+            elder_size,
+            current_elders,
+            self.joined()
+                .filter(|info| !excluded.contains(info.p2p_node.name())),
+        )
     }
 
-    /// Returns the candidates for elders out of all nodes matching the prefix.
+    /// Returns the candidates for elders out of all nodes matching the prefix, except the
+    /// `excluded` ones.
     pub fn elder_candidates_matching_prefix(
         &self,
         prefix: &Prefix,
         elder_size: usize,
         current_elders: &EldersInfo,
+        excluded: &BTreeSet<XorName>,
     ) -> BTreeMap<XorName, P2pNode> {
         elder_candidates(
             elder_size,
             current_elders,
-            self.joined()
-                .filter(|info| prefix.matches(info.p2p_node.name())),
+            self.joined().filter(|info| {
+                prefix.matches(info.p2p_node.name()) && !excluded.contains(info.p2p_node.name())
+            }),
         )
     }
 
@@ -149,6 +160,23 @@ impl SectionMembers {
         }
     }
 
+    /// Updates the address of an active member. Returns the previous address or `None` if there was
+    /// no such member or its address didn't change.
+    pub fn update_peer_addr(&mut self, p2p_node: P2pNode) -> Option<SocketAddr> {
+        let info = self
+            .members
+            .get_mut(p2p_node.name())
+            .filter(|info| info.state != MemberState::Left)?;
+
+        let old_addr = *info.p2p_node.peer_addr();
+        if old_addr == *p2p_node.peer_addr() {
+            return None;
+        }
+
+        info.p2p_node = p2p_node;
+        Some(old_addr)
+    }
+
     /// Remove a member from our section. Returns the removed `MemberInfo` or `None` if there was
     /// no such member.
     pub fn remove(&mut self, name: &XorName, proof: Proof) -> Option<MemberInfo> {
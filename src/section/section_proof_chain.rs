@@ -6,6 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::consensus::Proof;
 use std::{
     collections::HashSet,
     iter,
@@ -14,7 +15,7 @@ use std::{
 
 /// Chain of section BLS keys where every key is proven (signed) by the previous key, except the
 /// first one.
-#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Serialize, Deserialize)]
 pub struct SectionProofChain {
     head: bls::PublicKey,
     tail: Vec<Block>,
@@ -70,6 +71,26 @@ impl SectionProofChain {
         iter::once(&self.head).chain(self.tail.iter().map(|block| &block.key))
     }
 
+    /// Returns the key at the given index or `None` if the index is out of bounds.
+    pub fn key(&self, index: u64) -> Option<&bls::PublicKey> {
+        if index == 0 {
+            Some(&self.head)
+        } else {
+            self.tail.get(index as usize - 1).map(|block| &block.key)
+        }
+    }
+
+    // Returns the signature of the key at the given index by the previous key.
+    pub(super) fn signature(&self, index: u64) -> Option<&bls::Signature> {
+        if index == 0 {
+            None
+        } else {
+            self.tail
+                .get(index as usize - 1)
+                .map(|block| &block.signature)
+        }
+    }
+
     /// Returns whether this chain contains the given key.
     #[cfg_attr(feature = "mock_base", allow(clippy::trivially_copy_pass_by_ref))]
     pub fn has_key(&self, key: &bls::PublicKey) -> bool {
@@ -165,6 +186,34 @@ impl SectionProofChain {
         }
     }
 
+    /// Verify that `payload` was signed with `proof` by one of the keys in this chain and check
+    /// the trust of the chain up to that key against the given trusted keys. Keys after the
+    /// signing key don't make the signature any more trustworthy, so they are not considered.
+    ///
+    /// Returns the trust status together with the index of the signing key in the chain, or
+    /// `None` if the signing key is not in the chain (in which case the status is `Invalid`).
+    pub fn verify_proof<'a, I>(
+        &self,
+        trusted_keys: I,
+        payload: &[u8],
+        proof: &Proof,
+    ) -> (TrustStatus, Option<u64>)
+    where
+        I: IntoIterator<Item = &'a bls::PublicKey>,
+    {
+        let index = if let Some(index) = self.index_of(&proof.public_key) {
+            index
+        } else {
+            return (TrustStatus::Invalid, None);
+        };
+
+        if !proof.verify(payload) {
+            return (TrustStatus::Invalid, Some(index));
+        }
+
+        (self.slice(..=index).check_trust(trusted_keys), Some(index))
+    }
+
     // Returns the latest key in this chain that is among the trusted keys, together with its index.
     fn latest_trusted_key<'a, 'b, I>(
         &'a self,
@@ -184,22 +233,22 @@ impl SectionProofChain {
     }
 }
 
-// Result of a message trust check.
-#[derive(Debug, Eq, PartialEq)]
+/// Result of a proof chain trust check.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum TrustStatus {
-    // Proof chain is trusted.
+    /// Proof chain is trusted.
     Trusted,
-    // Proof chain is untrusted because one or more blocks in the chain have invalid signatures.
+    /// Proof chain is untrusted because one or more blocks in the chain have invalid signatures.
     Invalid,
-    // Proof chain is self-validated but its trust cannot be determined because none of the keys
-    // in the chain is among the trusted keys.
+    /// Proof chain is self-validated but its trust cannot be determined because none of the keys
+    /// in the chain is among the trusted keys.
     Unknown,
 }
 
 // Block of the section proof chain. Contains the section BLS public key and is signed by the
 // previous block. Note that the first key in the chain is not signed and so is not stored in
 // `Block`.
-#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Serialize, Deserialize)]
 struct Block {
     key: bls::PublicKey,
     signature: bls::Signature,
@@ -316,6 +365,58 @@ mod tests {
         assert_keys_eq(chain.slice(0..=3), &keys[0..3]);
     }
 
+    #[test]
+    fn verify_proof() {
+        let mut rng = rng::new();
+        let (key, secret_key) = gen_keys(&mut rng);
+        let mut chain = SectionProofChain::new(key);
+        let (key, signature, last_secret_key) = gen_block(&mut rng, &secret_key);
+        chain.push(key, signature);
+
+        let payload = b"receipt";
+        let proof = Proof {
+            public_key: secret_key.public_key(),
+            signature: secret_key.sign(payload),
+        };
+
+        assert_eq!(
+            chain.verify_proof(chain.keys().take(1), payload, &proof),
+            (TrustStatus::Trusted, Some(0))
+        );
+
+        // Trusting only a later key than the signing one is not enough.
+        assert_eq!(
+            chain.verify_proof(chain.keys().skip(1), payload, &proof),
+            (TrustStatus::Unknown, Some(0))
+        );
+
+        // Signature of a different payload.
+        assert_eq!(
+            chain.verify_proof(chain.keys(), b"forged", &proof),
+            (TrustStatus::Invalid, Some(0))
+        );
+
+        // Signing key not in the chain.
+        let (_, other_secret_key) = gen_keys(&mut rng);
+        let proof = Proof {
+            public_key: other_secret_key.public_key(),
+            signature: other_secret_key.sign(payload),
+        };
+        assert_eq!(
+            chain.verify_proof(chain.keys(), payload, &proof),
+            (TrustStatus::Invalid, None)
+        );
+
+        let proof = Proof {
+            public_key: last_secret_key.public_key(),
+            signature: last_secret_key.sign(payload),
+        };
+        assert_eq!(
+            chain.verify_proof(chain.keys().take(1), payload, &proof),
+            (TrustStatus::Trusted, Some(1))
+        );
+    }
+
     fn gen_keys(rng: &mut MainRng) -> (bls::PublicKey, bls::SecretKey) {
         let secret_key = consensus::test_utils::gen_secret_key(rng);
         (secret_key.public_key(), secret_key)
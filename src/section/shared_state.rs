@@ -6,7 +6,11 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::{EldersInfo, MemberInfo, MemberState, SectionMap, SectionMembers, SectionProofChain};
+use super::{
+    Beacon, CompactProofChain, EldersInfo, LocatedSection, MemberInfo, MemberState,
+    MembershipStatement, NetworkMap, RecoverySnapshot, SectionEntry, SectionMap, SectionMembers,
+    SectionProofChain, SkipLinks, Upgrade,
+};
 use crate::{
     consensus::{AccumulatingEvent, Proof, Proven},
     error::RoutingError,
@@ -14,6 +18,8 @@ use crate::{
     location::DstLocation,
     messages::{MessageHash, SrcAuthority},
     network_params::NetworkParams,
+    notary::Notary,
+    protocol::MIN_PROTOCOL_VERSION,
     relocation::{self, RelocateDetails},
 };
 
@@ -21,7 +27,7 @@ use std::{
     collections::{BTreeMap, BTreeSet, VecDeque},
     convert::TryInto,
     fmt::Debug,
-    iter,
+    iter, mem,
     net::SocketAddr,
 };
 use xor_name::{Prefix, XorName};
@@ -40,6 +46,24 @@ pub struct SharedState {
     pub sections: SectionMap,
     /// Queue of pending relocations.
     pub relocate_queue: VecDeque<RelocateDetails>,
+    /// Number of additional members requested by the upper layers that haven't joined yet.
+    pub requested_members: usize,
+    /// Number of additional members requested by the upper layers so far. Elders index their
+    /// `RequestMoreNodes` votes from it.
+    pub more_nodes_requests: u64,
+    /// Latest snapshot of our section to recover from in case all our elders are lost.
+    pub recovery_snapshot: Option<Proven<RecoverySnapshot>>,
+    /// Members that failed to take part in the DKG for a new section key and so are not
+    /// considered as elder candidates until our next `EldersInfo`.
+    pub dkg_excluded: BTreeSet<XorName>,
+    /// The most recent rounds of our section randomness beacon.
+    pub beacon: Beacon,
+    /// State of our section notary.
+    pub notary: Notary,
+    /// Skip links of our section proof chain, for compact proofs. Only added once agreed on.
+    pub skip_links: SkipLinks,
+    /// Rolling upgrade of our section to a new protocol version.
+    pub upgrade: Upgrade,
 }
 
 impl SharedState {
@@ -50,6 +74,16 @@ impl SharedState {
             sections: SectionMap::new(elders_info),
             our_members: SectionMembers::default(),
             relocate_queue: VecDeque::new(),
+            requested_members: 0,
+            more_nodes_requests: 0,
+            recovery_snapshot: None,
+            dkg_excluded: BTreeSet::new(),
+            beacon: Beacon::default(),
+            notary: Notary::default(),
+            skip_links: SkipLinks::default(),
+            // Until our section's agreed state arrives with the parsec genesis, assume the oldest
+            // version we can talk to, so newer features stay switched off.
+            upgrade: Upgrade::new(MIN_PROTOCOL_VERSION),
         }
     }
 
@@ -93,8 +127,14 @@ impl SharedState {
         // TODO: avoid this clone.
         let elders_info = self.sections.proven_our().clone();
         let section_key = *self.our_history.last_key();
+        let recovery_snapshot = self.recovery_snapshot.take();
+        let beacon = mem::take(&mut self.beacon);
+        let upgrade = self.upgrade.clone();
 
         *self = Self::new(elders_info, section_key);
+        self.recovery_snapshot = recovery_snapshot;
+        self.beacon = beacon;
+        self.upgrade = upgrade;
     }
 
     /// Returns our own current section info.
@@ -146,6 +186,20 @@ impl SharedState {
         self.our_info().elders.contains_key(name)
     }
 
+    /// Returns the number of our members that are neither mature nor elders.
+    pub fn infant_count(&self) -> usize {
+        self.our_members
+            .joined()
+            .filter(|info| !info.is_mature() && !self.is_peer_our_elder(info.p2p_node.name()))
+            .count()
+    }
+
+    /// Returns whether we admit new infants: while we have less than `max_infants` of them, or if
+    /// more nodes were requested.
+    pub fn is_accepting_infants(&self, max_infants: usize) -> bool {
+        self.requested_members > 0 || self.infant_count() < max_infants
+    }
+
     pub fn find_p2p_node_from_addr(&self, socket_addr: &SocketAddr) -> Option<&P2pNode> {
         self.known_nodes()
             .find(|p2p_node| p2p_node.peer_addr() == socket_addr)
@@ -316,6 +370,10 @@ impl SharedState {
         self.our_history
             .push(section_key.value, section_key.proof.signature);
         self.sections.set_our(elders_info);
+
+        // The exclusions only apply to the elder change they were made for, so a member that
+        // failed a DKG once, e.g. due to a transient network issue, can become an elder again.
+        self.dkg_excluded.clear();
     }
 
     pub fn poll_relocation(&mut self) -> Option<RelocateDetails> {
@@ -363,6 +421,92 @@ impl SharedState {
         self.our_history.slice(index..)
     }
 
+    /// Provide a compact proof of our current section key, starting from the key at the given
+    /// index and using the skip links we have. The proof always contains the key that signed our
+    /// current `EldersInfo`, so it can be verified with the proof too.
+    pub fn prove_compact(&self, from: u64) -> CompactProofChain {
+        self.prove_compact_including(from, &self.sections.proven_our().proof.public_key)
+    }
+
+    /// Same as `prove_compact`, but the proof contains `key` instead of the key that signed our
+    /// current `EldersInfo`.
+    pub fn prove_compact_including(&self, from: u64, key: &bls::PublicKey) -> CompactProofChain {
+        let key_index = self
+            .our_history
+            .index_of(key)
+            .unwrap_or_else(|| self.our_history.last_key_index());
+
+        let mut proof = CompactProofChain::new(
+            &self.our_history.slice(..=key_index),
+            from.min(key_index),
+            &self.skip_links,
+        );
+        proof.extend(&self.our_history);
+        proof
+    }
+
+    /// Returns the compact form of `proof_chain`, which must be a part of our history, using the
+    /// skip links we have.
+    pub fn compact_proof(&self, proof_chain: &SectionProofChain) -> Option<CompactProofChain> {
+        let from = self.our_history.index_of(proof_chain.first_key())?;
+        let to = self.our_history.index_of(proof_chain.last_key())?;
+
+        Some(CompactProofChain::new(
+            &self.our_history.slice(..=to),
+            from,
+            &self.skip_links,
+        ))
+    }
+
+    /// Returns the statement about the given member of our section that our section certifies.
+    pub fn membership_statement(&self, name: &XorName) -> Option<MembershipStatement> {
+        self.our_members.get(name).map(|info| MembershipStatement {
+            name: *name,
+            age: info.age(),
+            state: info.state,
+            prefix: *self.our_prefix(),
+        })
+    }
+
+    /// Returns all the sections we know, with the proofs of their elders and keys.
+    pub fn network_map(&self) -> NetworkMap {
+        let our_entry = SectionEntry {
+            elders_info: self.sections.proven_our().clone(),
+            key: None,
+        };
+        let neighbour_entries = self
+            .sections
+            .proven_neighbours()
+            .map(|elders_info| SectionEntry {
+                elders_info: elders_info.clone(),
+                key: self.sections.proven_key(&elders_info.value.prefix).cloned(),
+            });
+
+        NetworkMap {
+            our_prefix: *self.our_prefix(),
+            sections: iter::once(our_entry)
+                .chain(neighbour_entries)
+                .map(|entry| (entry.elders_info.value.prefix, entry))
+                .collect(),
+            proof_chain: self.our_history.clone(),
+        }
+    }
+
+    /// Returns the section `name` belongs to, if it is our section or one of our neighbours. The
+    /// proof starts at our first key.
+    pub fn locate(&self, name: &XorName) -> Option<LocatedSection> {
+        let elders_info = if self.our_prefix().matches(name) {
+            self.sections.proven_our()
+        } else {
+            self.sections.proven_neighbour_by_name(name)?
+        };
+
+        Some(LocatedSection {
+            elders_info: elders_info.clone(),
+            proof_chain: self.prove_compact_including(0, &elders_info.proof.public_key),
+        })
+    }
+
     /// Update our knowledge of their section and their knowledge of ours. Returns the events to
     /// vote for (if any).
     pub fn update_section_knowledge(
@@ -475,11 +619,13 @@ impl SharedState {
             &our_prefix,
             network_params.elder_size,
             self.sections.our(),
+            &self.dkg_excluded,
         );
         let other_elders = self.our_members.elder_candidates_matching_prefix(
             &other_prefix,
             network_params.elder_size,
             self.sections.our(),
+            &self.dkg_excluded,
         );
 
         let our_info = EldersInfo::new(our_elders, our_prefix);
@@ -491,9 +637,9 @@ impl SharedState {
     // Returns the candidates for elders out of all the nodes in the section, even out of the
     // relocating nodes if there would not be enough instead.
     fn elder_candidates(&self, elder_size: usize) -> BTreeMap<XorName, P2pNode> {
-        let mut elders = self
-            .our_members
-            .elder_candidates(elder_size, self.sections.our());
+        let mut elders =
+            self.our_members
+                .elder_candidates(elder_size, self.sections.our(), &self.dkg_excluded);
 
         // Ensure that we can still handle one node lost when relocating.
         // Ensure that the node we eject are the one we want to relocate first.
@@ -513,6 +659,7 @@ impl SharedState {
             .map(|details| details.pub_id.name())
             .filter_map(move |name| self.our_members.get(name))
             .filter(|info| info.state != MemberState::Left)
+            .filter(move |info| !self.dkg_excluded.contains(info.p2p_node.name()))
             .take(count)
             .map(|info| (*info.p2p_node.name(), info.p2p_node.clone()))
     }
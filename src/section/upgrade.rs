@@ -0,0 +1,196 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::EldersInfo;
+use std::collections::BTreeSet;
+use xor_name::XorName;
+
+/// State of the rolling upgrade of our section to a new protocol version.
+///
+/// Once an upgrade is agreed on, the elders restart one at a time in the order of their names.
+/// An elder's turn comes when all the elders before it reported running the target version. In
+/// sections too small to keep a quorum with one elder down, the upgrade stalls unless it was
+/// proposed allowing quorum loss, in which case consensus pauses while an elder restarts and
+/// resumes once it is back.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Upgrade {
+    /// Protocol version every elder of our section reported running. Features introduced by later
+    /// versions must stay switched off.
+    active_version: u16,
+    /// Version being rolled out, if any.
+    target: Option<u16>,
+    /// Nodes reported running the target version.
+    upgraded: BTreeSet<XorName>,
+    /// Whether elders may restart even if the others don't form a quorum without them.
+    allow_quorum_loss: bool,
+}
+
+impl Upgrade {
+    pub fn new(active_version: u16) -> Self {
+        Self {
+            active_version,
+            target: None,
+            upgraded: BTreeSet::new(),
+            allow_quorum_loss: false,
+        }
+    }
+
+    pub fn active_version(&self) -> u16 {
+        self.active_version
+    }
+
+    pub fn target(&self) -> Option<u16> {
+        self.target
+    }
+
+    pub fn is_upgraded(&self, name: &XorName) -> bool {
+        self.upgraded.contains(name)
+    }
+
+    /// Starts rolling out `version`. Returns whether it is newer than both the active version and
+    /// the version currently being rolled out, if any. Proposing the version being rolled out
+    /// again allowing quorum loss lets a stalled upgrade go on without restarting it, in which
+    /// case this returns true as well.
+    pub fn start(&mut self, version: u16, allow_quorum_loss: bool) -> bool {
+        if self.target == Some(version) && allow_quorum_loss && !self.allow_quorum_loss {
+            self.allow_quorum_loss = true;
+            return true;
+        }
+
+        if version <= self.active_version || self.target.map_or(false, |target| version <= target) {
+            return false;
+        }
+
+        self.target = Some(version);
+        self.upgraded.clear();
+        self.allow_quorum_loss = allow_quorum_loss;
+        true
+    }
+
+    /// Records that `name` runs `version`. Returns whether that completes the node's upgrade.
+    pub fn report(&mut self, name: XorName, version: u16) -> bool {
+        match self.target {
+            Some(target) if version >= target => self.upgraded.insert(name),
+            _ => false,
+        }
+    }
+
+    /// Returns the elder whose turn it is to restart. Elders are restarted in the order of their
+    /// names. Check `may_restart` before restarting it.
+    pub fn next_to_restart<'a>(&self, elders_info: &'a EldersInfo) -> Option<&'a XorName> {
+        self.target?;
+
+        elders_info
+            .elders
+            .keys()
+            .find(|name| !self.upgraded.contains(name))
+    }
+
+    /// Returns whether the other elders still form a quorum while `name` restarts.
+    pub fn keeps_quorum(elders_info: &EldersInfo, name: &XorName) -> bool {
+        elders_info.is_quorum(elders_info.elders.keys().filter(|other| *other != name))
+    }
+
+    /// Returns whether `name` may restart: either the other elders keep a quorum without it or
+    /// the upgrade was proposed allowing quorum loss.
+    pub fn may_restart(&self, elders_info: &EldersInfo, name: &XorName) -> bool {
+        self.allow_quorum_loss || Self::keeps_quorum(elders_info, name)
+    }
+
+    /// Completes the upgrade if every elder reported running the target version. Returns the new
+    /// active version if so.
+    pub fn try_complete(&mut self, elders_info: &EldersInfo) -> Option<u16> {
+        let target = self.target?;
+        if !elders_info
+            .elders
+            .keys()
+            .all(|name| self.upgraded.contains(name))
+        {
+            return None;
+        }
+
+        self.active_version = target;
+        self.target = None;
+        self.upgraded.clear();
+        self.allow_quorum_loss = false;
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        id::{FullId, P2pNode},
+        rng,
+    };
+
+    fn gen_elders_info(count: u16) -> EldersInfo {
+        let mut rng = rng::new();
+        let elders = (0..count)
+            .map(|index| {
+                let pub_id = *FullId::gen(&mut rng).public_id();
+                (
+                    *pub_id.name(),
+                    P2pNode::new(pub_id, ([127, 0, 0, 1], 9000 + index).into()),
+                )
+            })
+            .collect();
+        EldersInfo::new(elders, Default::default())
+    }
+
+    #[test]
+    fn elders_restart_one_at_a_time() {
+        let elders_info = gen_elders_info(5);
+        let names: Vec<_> = elders_info.elders.keys().copied().collect();
+
+        let mut upgrade = Upgrade::new(1);
+        assert_eq!(upgrade.next_to_restart(&elders_info), None);
+        assert!(!upgrade.start(1, false));
+        assert!(upgrade.start(2, false));
+
+        for name in &names {
+            assert_eq!(upgrade.next_to_restart(&elders_info), Some(name));
+            assert!(upgrade.may_restart(&elders_info, name));
+            assert_eq!(upgrade.try_complete(&elders_info), None);
+            assert!(upgrade.report(*name, 2));
+        }
+
+        assert_eq!(upgrade.try_complete(&elders_info), Some(2));
+        assert_eq!(upgrade.active_version(), 2);
+        assert_eq!(upgrade.target(), None);
+    }
+
+    #[test]
+    fn small_section_upgrades_only_if_allowed_to_lose_quorum() {
+        // Restarting any of three elders leaves only two of them, which is not a quorum.
+        let elders_info = gen_elders_info(3);
+        let names: Vec<_> = elders_info.elders.keys().copied().collect();
+
+        let mut upgrade = Upgrade::new(1);
+        assert!(upgrade.start(2, false));
+        assert!(!Upgrade::keeps_quorum(&elders_info, &names[0]));
+        assert!(!upgrade.may_restart(&elders_info, &names[0]));
+
+        // Proposing the same version again allowing quorum loss unblocks it, but only once.
+        assert!(upgrade.start(2, true));
+        assert!(!upgrade.start(2, true));
+
+        for name in &names {
+            assert_eq!(upgrade.next_to_restart(&elders_info), Some(name));
+            assert!(upgrade.may_restart(&elders_info, name));
+            assert!(upgrade.report(*name, 2));
+        }
+
+        assert_eq!(upgrade.try_complete(&elders_info), Some(2));
+
+        // The allowance doesn't carry over to the next upgrade.
+        assert!(upgrade.start(3, false));
+        assert!(!upgrade.may_restart(&elders_info, &names[0]));
+    }
+}
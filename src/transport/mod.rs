@@ -6,41 +6,61 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod scheduler;
 mod sending_targets_cache;
 
-pub use sending_targets_cache::{Resend, RESEND_DELAY, RESEND_MAX_ATTEMPTS};
+pub use scheduler::{Priority, QueueDepths};
+pub use sending_targets_cache::{
+    FailureHistory, Resend, ResendPolicy, RESEND_DELAY, RESEND_MAX_ATTEMPTS,
+};
 
 use crate::{
     quic_p2p::{EventSenders, Peer, QuicP2p, QuicP2pError, Token},
+    rng::MainRng,
     time::Duration,
     timer::Timer,
-    TransportConfig,
+    TransportConfig, TransportEvent,
 };
 use bytes::Bytes;
+use crossbeam_channel::Sender;
 use hex_fmt::HexFmt;
 use std::{collections::HashMap, net::SocketAddr};
 
+use scheduler::Scheduler;
 use sending_targets_cache::SendingTargetsCache;
 
 /// Struct that handles network operations: sending and receiving messages, as well as resending on
-/// failure.
+/// failure. Messages to the same peer are sent in order of their priority.
 pub struct Transport {
     quic_p2p: QuicP2p,
+    // Reports the messages dropped by the scheduler as unsent, like quic-p2p does.
+    event_tx: Sender<TransportEvent>,
     cache: SendingTargetsCache,
+    scheduler: Scheduler,
     next_msg_token: Token,
     scheduled_messages: HashMap<u64, ScheduledMessage>,
+    // Priorities of the dropped messages not yet handled as unsent, by target and token.
+    dropped_messages: HashMap<(SocketAddr, Token), Priority>,
 }
 
 impl Transport {
-    pub fn new(event_tx: EventSenders, config: TransportConfig) -> Result<Self, QuicP2pError> {
+    pub fn new(
+        event_tx: EventSenders,
+        config: TransportConfig,
+        resend_policy: ResendPolicy,
+    ) -> Result<Self, QuicP2pError> {
         {
+            let node_tx = event_tx.node_tx.clone();
             let quic_p2p = QuicP2p::with_config(event_tx, Some(config), Default::default(), false)?;
 
             Ok(Transport {
                 quic_p2p,
-                cache: Default::default(),
+                event_tx: node_tx,
+                cache: SendingTargetsCache::new(resend_policy),
+                scheduler: Default::default(),
                 next_msg_token: 0,
                 scheduled_messages: Default::default(),
+                dropped_messages: Default::default(),
             })
         }
     }
@@ -54,6 +74,7 @@ impl Transport {
         conn_infos: &[SocketAddr],
         delivery_group_size: usize,
         msg: Bytes,
+        priority: Priority,
     ) {
         if conn_infos.len() < delivery_group_size {
             warn!(
@@ -67,7 +88,8 @@ impl Transport {
         let token = self.next_msg_token();
 
         trace!(
-            "Sending message with token {} to {:?}",
+            "Sending {:?} message with token {} to {:?}",
+            priority,
             token,
             &conn_infos[..delivery_group_size.min(conn_infos.len())]
         );
@@ -75,7 +97,7 @@ impl Transport {
         // initially only send to delivery_group_size targets
         for addr in conn_infos.iter().take(delivery_group_size) {
             // NetworkBytes is refcounted and cheap to clone.
-            self.send_now(*addr, msg.clone(), token);
+            self.send_now(*addr, msg.clone(), token, priority);
         }
 
         self.cache
@@ -88,17 +110,29 @@ impl Transport {
     }
 
     pub fn target_succeeded(&mut self, token: Token, target: SocketAddr) {
+        let _ = self.complete(target, token);
         self.cache.target_succeeded(token, target)
     }
 
+    // Resends the message to the next target, if any. Returns the status of the failed peer and,
+    // if the message was given up on without reaching any of its targets, the message.
     pub fn target_failed(
         &mut self,
         msg: Bytes,
         msg_token: Token,
         failed_target: SocketAddr,
         timer: &Timer,
-    ) -> PeerStatus {
-        match self.cache.target_failed(msg_token, failed_target) {
+        rng: &mut MainRng,
+    ) -> (PeerStatus, Option<Bytes>) {
+        // Messages we didn't send through the scheduler, if any, are resent as control ones.
+        let priority = self
+            .complete(failed_target, msg_token)
+            .or_else(|| self.dropped_messages.remove(&(failed_target, msg_token)))
+            .unwrap_or(Priority::Control);
+
+        let (resend, undelivered) = self.cache.target_failed(msg_token, failed_target, rng);
+
+        match resend {
             Resend::Now(next_target) => {
                 trace!(
                     "Sending message with token {} to {} failed - resending to {} now",
@@ -107,8 +141,8 @@ impl Transport {
                     next_target
                 );
 
-                self.send_now(next_target, msg, msg_token);
-                PeerStatus::Normal
+                self.send_now(next_target, msg, msg_token, priority);
+                (PeerStatus::Normal, None)
             }
             Resend::Later(next_target, delay) => {
                 trace!(
@@ -119,8 +153,8 @@ impl Transport {
                     delay
                 );
 
-                self.send_later(next_target, msg, msg_token, timer, delay);
-                PeerStatus::Normal
+                self.send_later(next_target, msg, msg_token, priority, timer, delay);
+                (PeerStatus::Normal, None)
             }
             Resend::Never => {
                 trace!(
@@ -129,7 +163,7 @@ impl Transport {
                     failed_target,
                 );
 
-                PeerStatus::Lost
+                (PeerStatus::Lost, Some(msg).filter(|_| undelivered))
             }
         }
     }
@@ -138,27 +172,73 @@ impl Transport {
         self.quic_p2p.our_connection_info()
     }
 
+    // Disconnects from the peer. The messages still queued to it are reported as unsent, so they
+    // are resent to other targets or given up on.
     pub fn disconnect(&mut self, addr: SocketAddr) {
+        for (content, token, priority) in self.scheduler.remove_peer(&addr) {
+            self.drop_message(addr, content, token, priority);
+        }
+
         self.quic_p2p.disconnect_from(addr)
     }
 
     pub fn handle_timeout(&mut self, timer_token: u64) -> bool {
         if let Some(msg) = self.scheduled_messages.remove(&timer_token) {
-            self.quic_p2p
-                .send(Peer::Node(msg.target), msg.content, msg.token);
+            self.send_now(msg.target, msg.content, msg.token, msg.priority);
             true
         } else {
             false
         }
     }
 
+    /// Number of recent consecutive failures of sending to each peer.
+    pub fn failure_history(&self) -> &FailureHistory {
+        self.cache.history()
+    }
+
+    /// Number of messages waiting to be sent, by priority class.
+    pub fn queue_depths(&self) -> QueueDepths {
+        self.scheduler.queue_depths()
+    }
+
     fn next_msg_token(&mut self) -> Token {
         self.next_msg_token = self.next_msg_token.wrapping_add(1);
         self.next_msg_token
     }
 
-    fn send_now(&mut self, target: SocketAddr, content: Bytes, token: Token) {
-        self.quic_p2p.send(Peer::Node(target), content, token)
+    // Sends the message as soon as the scheduler lets it. If the queue to the target is full, the
+    // message is reported as unsent instead.
+    fn send_now(&mut self, target: SocketAddr, content: Bytes, token: Token, priority: Priority) {
+        match self.scheduler.push(target, content, token, priority) {
+            Ok(Some((content, token))) => self.quic_p2p.send(Peer::Node(target), content, token),
+            Ok(None) => (),
+            Err(content) => {
+                trace!(
+                    "Queue of {:?} messages to {} is full - dropping message with token {}",
+                    priority,
+                    target,
+                    token
+                );
+                self.drop_message(target, content, token, priority)
+            }
+        }
+    }
+
+    // Reports the message to `target` as unsent, the same way quic-p2p reports the messages it
+    // failed to send, so it goes through `target_failed` like them.
+    fn drop_message(
+        &mut self,
+        target: SocketAddr,
+        content: Bytes,
+        token: Token,
+        priority: Priority,
+    ) {
+        let _ = self.dropped_messages.insert((target, token), priority);
+        let _ = self.event_tx.send(TransportEvent::UnsentUserMessage {
+            peer: Peer::Node(target),
+            msg: content,
+            token,
+        });
     }
 
     fn send_later(
@@ -166,6 +246,7 @@ impl Transport {
         target: SocketAddr,
         content: Bytes,
         token: Token,
+        priority: Priority,
         timer: &Timer,
         delay: Duration,
     ) {
@@ -176,15 +257,27 @@ impl Transport {
                 content,
                 token,
                 target,
+                priority,
             },
         );
     }
+
+    // Frees the slot of the message to `target` that was reported as sent or unsent and sends the
+    // next queued message to it, if any. Returns the priority of the freed message.
+    fn complete(&mut self, target: SocketAddr, token: Token) -> Option<Priority> {
+        let (priority, next) = self.scheduler.complete(target, token);
+        if let Some((content, token)) = next {
+            self.quic_p2p.send(Peer::Node(target), content, token)
+        }
+        priority
+    }
 }
 
 struct ScheduledMessage {
     content: Bytes,
     token: Token,
     target: SocketAddr,
+    priority: Priority,
 }
 
 #[derive(Debug)]
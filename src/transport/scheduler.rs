@@ -0,0 +1,342 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::quic_p2p::Token;
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display, Formatter},
+    net::SocketAddr,
+};
+
+// Maximum number of messages to a single peer that haven't been reported as sent or unsent yet.
+// Further messages to the peer wait in its queues, where urgent ones can overtake them.
+const MAX_IN_FLIGHT: usize = 8;
+
+// Maximum number of messages of a single class queued to a single peer. Further messages of the
+// class are rejected, so a peer that stops accepting messages can't make us buffer without limit.
+const MAX_QUEUED: usize = 512;
+
+/// Priority class of an outgoing message.
+///
+/// The queued messages to a peer are sent in rounds. In each round, every class can send up to its
+/// weight in messages, higher classes first, so the lower classes are slowed down but never
+/// starved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Priority {
+    /// Parsec gossip.
+    Consensus,
+    /// Section key generation.
+    Dkg,
+    /// Membership, routing and other control messages.
+    Control,
+    /// User messages.
+    Bulk,
+}
+
+impl Priority {
+    const ALL: [Self; 4] = [Self::Consensus, Self::Dkg, Self::Control, Self::Bulk];
+
+    // Number of messages of this class sent per round.
+    fn weight(self) -> usize {
+        match self {
+            Self::Consensus => 8,
+            Self::Dkg => 4,
+            Self::Control => 4,
+            Self::Bulk => 1,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Number of outgoing messages waiting in the per-peer queues, by priority class.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct QueueDepths {
+    /// Parsec gossip.
+    pub consensus: usize,
+    /// Section key generation.
+    pub dkg: usize,
+    /// Membership, routing and other control messages.
+    pub control: usize,
+    /// User messages.
+    pub bulk: usize,
+}
+
+impl Display for QueueDepths {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "consensus: {}, dkg: {}, control: {}, bulk: {}",
+            self.consensus, self.dkg, self.control, self.bulk
+        )
+    }
+}
+
+// Schedules the messages to send to each peer according to their priority.
+#[derive(Default)]
+pub struct Scheduler {
+    peers: HashMap<SocketAddr, PeerQueues>,
+}
+
+impl Scheduler {
+    // Queues the message to `target`. Returns the message to send to it now, if any, or the
+    // message back if the queue of its class is full.
+    pub fn push(
+        &mut self,
+        target: SocketAddr,
+        content: Bytes,
+        token: Token,
+        priority: Priority,
+    ) -> Result<Option<(Bytes, Token)>, Bytes> {
+        let peer = self.peers.entry(target).or_default();
+        let queue = &mut peer.queues[priority.index()];
+        if queue.len() >= MAX_QUEUED {
+            return Err(content);
+        }
+
+        queue.push_back((content, token));
+        Ok(peer.pop())
+    }
+
+    // Marks the message to `target` as no longer in flight. Returns its priority, if known, and the
+    // next message to send to the target, if any.
+    pub fn complete(
+        &mut self,
+        target: SocketAddr,
+        token: Token,
+    ) -> (Option<Priority>, Option<(Bytes, Token)>) {
+        let peer = if let Some(peer) = self.peers.get_mut(&target) {
+            peer
+        } else {
+            return (None, None);
+        };
+
+        let priority = peer.in_flight.remove(&token);
+        let next = peer.pop();
+
+        if peer.is_idle() {
+            let _ = self.peers.remove(&target);
+        }
+
+        (priority, next)
+    }
+
+    // Drops all the messages queued for `target` and returns them with their priorities. Messages
+    // already in flight are left to the transport to report.
+    pub fn remove_peer(&mut self, target: &SocketAddr) -> Vec<(Bytes, Token, Priority)> {
+        let mut peer = if let Some(peer) = self.peers.remove(target) {
+            peer
+        } else {
+            return Vec::new();
+        };
+
+        peer.queues
+            .iter_mut()
+            .zip(Priority::ALL.iter())
+            .flat_map(|(queue, &priority)| {
+                queue
+                    .drain(..)
+                    .map(move |(content, token)| (content, token, priority))
+            })
+            .collect()
+    }
+
+    pub fn queue_depths(&self) -> QueueDepths {
+        let mut depths = QueueDepths::default();
+        for peer in self.peers.values() {
+            depths.consensus += peer.queues[Priority::Consensus.index()].len();
+            depths.dkg += peer.queues[Priority::Dkg.index()].len();
+            depths.control += peer.queues[Priority::Control.index()].len();
+            depths.bulk += peer.queues[Priority::Bulk.index()].len();
+        }
+        depths
+    }
+}
+
+#[derive(Default)]
+struct PeerQueues {
+    // Priorities of the messages sent but not yet reported as sent or unsent.
+    in_flight: HashMap<Token, Priority>,
+    // Queued messages, by priority class.
+    queues: [VecDeque<(Bytes, Token)>; 4],
+    // Number of messages each class can still send in the current round.
+    credits: [usize; 4],
+}
+
+impl PeerQueues {
+    // Takes the next message to send, unless too many are already in flight.
+    fn pop(&mut self) -> Option<(Bytes, Token)> {
+        if self.in_flight.len() >= MAX_IN_FLIGHT {
+            return None;
+        }
+
+        let priority = self.next_priority()?;
+        let (content, token) = self.queues[priority.index()].pop_front()?;
+        self.credits[priority.index()] -= 1;
+        let _ = self.in_flight.insert(token, priority);
+
+        Some((content, token))
+    }
+
+    // Returns the highest class with queued messages and credits left, starting a new round if
+    // no such class remains.
+    fn next_priority(&mut self) -> Option<Priority> {
+        if let Some(priority) = self.find_ready() {
+            return Some(priority);
+        }
+
+        for priority in &Priority::ALL {
+            self.credits[priority.index()] = priority.weight();
+        }
+
+        self.find_ready()
+    }
+
+    fn find_ready(&self) -> Option<Priority> {
+        Priority::ALL.iter().copied().find(|priority| {
+            !self.queues[priority.index()].is_empty() && self.credits[priority.index()] > 0
+        })
+    }
+
+    fn is_idle(&self) -> bool {
+        self.in_flight.is_empty() && self.queues.iter().all(VecDeque::is_empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urgent_messages_overtake_bulk_ones() {
+        let mut scheduler = Scheduler::default();
+        let target = ([127, 0, 0, 1], 9000).into();
+        let mut token = 0;
+
+        for _ in 0..MAX_IN_FLIGHT {
+            token += 1;
+            assert!(scheduler
+                .push(target, Bytes::new(), token, Priority::Bulk)
+                .unwrap()
+                .is_some());
+        }
+
+        // The peer is busy, so further messages are queued.
+        for &priority in &[Priority::Bulk, Priority::Bulk, Priority::Consensus] {
+            token += 1;
+            assert!(scheduler
+                .push(target, Bytes::new(), token, priority)
+                .unwrap()
+                .is_none());
+        }
+
+        assert_eq!(
+            scheduler.queue_depths(),
+            QueueDepths {
+                consensus: 1,
+                bulk: 2,
+                ..Default::default()
+            }
+        );
+
+        // The consensus message goes first once a slot frees up.
+        let (priority, next) = scheduler.complete(target, 1);
+        assert_eq!(priority, Some(Priority::Bulk));
+        assert_eq!(next.map(|(_, token)| token), Some(token));
+    }
+
+    #[test]
+    fn bulk_messages_are_not_starved() {
+        let mut scheduler = Scheduler::default();
+        let target = ([127, 0, 0, 1], 9000).into();
+        let mut in_flight = VecDeque::new();
+        let mut token = 0;
+
+        for _ in 0..MAX_IN_FLIGHT {
+            token += 1;
+            let _ = scheduler.push(target, Bytes::new(), token, Priority::Consensus);
+            in_flight.push_back(token);
+        }
+
+        token += 1;
+        let bulk_token = token;
+        let _ = scheduler.push(target, Bytes::new(), bulk_token, Priority::Bulk);
+
+        let round: usize = Priority::ALL.iter().map(|priority| priority.weight()).sum();
+        for _ in 0..round {
+            token += 1;
+            let _ = scheduler.push(target, Bytes::new(), token, Priority::Consensus);
+        }
+
+        // Complete the messages in the order they were sent until the bulk one is sent.
+        for _ in 0..round {
+            let completed = in_flight.pop_front().expect("nothing in flight");
+            let (_, next) = scheduler.complete(target, completed);
+            let (_, next) = next.expect("queue drained");
+            if next == bulk_token {
+                return;
+            }
+            in_flight.push_back(next);
+        }
+
+        panic!("bulk message starved");
+    }
+
+    #[test]
+    fn full_queue_rejects_messages() {
+        let mut scheduler = Scheduler::default();
+        let target = ([127, 0, 0, 1], 9000).into();
+        let mut token = 0;
+
+        for _ in 0..MAX_IN_FLIGHT + MAX_QUEUED {
+            token += 1;
+            assert!(scheduler
+                .push(target, Bytes::new(), token, Priority::Bulk)
+                .is_ok());
+        }
+
+        token += 1;
+        assert!(scheduler
+            .push(target, Bytes::new(), token, Priority::Bulk)
+            .is_err());
+
+        // Other classes have their own queues.
+        assert!(scheduler
+            .push(target, Bytes::new(), token, Priority::Control)
+            .is_ok());
+    }
+
+    #[test]
+    fn removed_peer_returns_queued_messages() {
+        let mut scheduler = Scheduler::default();
+        let target = ([127, 0, 0, 1], 9000).into();
+        let mut token = 0;
+
+        for _ in 0..MAX_IN_FLIGHT {
+            token += 1;
+            let _ = scheduler.push(target, Bytes::new(), token, Priority::Consensus);
+        }
+
+        let queued = [(token + 1, Priority::Dkg), (token + 2, Priority::Bulk)];
+        for &(token, priority) in &queued {
+            let _ = scheduler.push(target, Bytes::new(), token, priority);
+        }
+
+        let removed: Vec<_> = scheduler
+            .remove_peer(&target)
+            .into_iter()
+            .map(|(_, token, priority)| (token, priority))
+            .collect();
+        assert_eq!(removed, queued);
+        assert_eq!(scheduler.queue_depths(), QueueDepths::default());
+        assert!(scheduler.remove_peer(&target).is_empty());
+    }
+}
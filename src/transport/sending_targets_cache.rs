@@ -6,14 +6,94 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{quic_p2p::Token, time::Duration};
+use crate::{quic_p2p::Token, rng::MainRng, time::Duration};
+use lru_time_cache::LruCache;
+use rand::Rng;
 use std::{collections::HashMap, net::SocketAddr};
 
-/// Maximal number of resend attempts to the same target.
+/// Default maximal number of resend attempts to the same target.
 pub const RESEND_MAX_ATTEMPTS: u8 = 3;
-/// Delay before attempting to resend a previously failed message.
+/// Default delay before attempting to resend a previously failed message to the same target.
 pub const RESEND_DELAY: Duration = Duration::from_secs(10);
 
+// How long to remember the failures of a peer we haven't tried to send to since.
+const FAILURE_HISTORY_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+/// Policy for resending the messages that failed to be sent. A message is first resent to the
+/// spare targets right away, then to the failed targets again with exponential backoff.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ResendPolicy {
+    /// Maximal number of attempts to send a message to the same target.
+    pub max_attempts: u8,
+    /// Delay before the first resend to a target that already failed.
+    pub initial_delay: Duration,
+    /// Factor the delay is multiplied by after every further failure of the same target. Zero is
+    /// treated as one, so the delay never drops below `initial_delay`.
+    pub multiplier: u32,
+    /// Upper bound of the delay, before jitter.
+    pub max_delay: Duration,
+    /// Maximal random delay added to each resend, in percent of the delay, so that nodes failing
+    /// at the same time don't resend in lockstep.
+    pub jitter_percent: u32,
+}
+
+impl ResendPolicy {
+    // Delay before the next attempt to a target that already failed `failed_attempts` times. Any
+    // overflow saturates: the backoff at `max_delay` and the jitter at none.
+    fn delay(&self, failed_attempts: u8, rng: &mut MainRng) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1)
+            .saturating_pow(u32::from(failed_attempts.saturating_sub(1)));
+        let delay = self
+            .initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+
+        let percent = rng.gen_range(0, self.jitter_percent.saturating_add(1));
+        delay
+            .checked_mul(percent)
+            .and_then(|jitter| delay.checked_add(jitter / 100))
+            .unwrap_or(delay)
+    }
+}
+
+impl Default for ResendPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: RESEND_MAX_ATTEMPTS,
+            initial_delay: RESEND_DELAY,
+            multiplier: 2,
+            max_delay: Duration::from_secs(60),
+            jitter_percent: 20,
+        }
+    }
+}
+
+// Number of consecutive failures of sending to each peer. Used to prefer the reliable peers when
+// choosing the delivery targets of a message.
+pub struct FailureHistory(LruCache<SocketAddr, u32>);
+
+impl FailureHistory {
+    pub fn failures(&self, addr: &SocketAddr) -> u32 {
+        self.0.peek(addr).copied().unwrap_or(0)
+    }
+
+    fn record_failure(&mut self, addr: SocketAddr) {
+        *self.0.entry(addr).or_insert(0) += 1;
+    }
+
+    fn record_success(&mut self, addr: &SocketAddr) {
+        let _ = self.0.remove(addr);
+    }
+}
+
+impl Default for FailureHistory {
+    fn default() -> Self {
+        Self(LruCache::with_expiry_duration(FAILURE_HISTORY_EXPIRY))
+    }
+}
+
 enum TargetState {
     /// we don't know whether the last send attempt succeeded or failed
     /// the stored number of attempts already failed before
@@ -33,12 +113,25 @@ impl TargetState {
     }
 }
 
-#[derive(Default)]
 pub struct SendingTargetsCache {
     cache: HashMap<Token, Vec<(SocketAddr, TargetState)>>,
+    policy: ResendPolicy,
+    history: FailureHistory,
 }
 
 impl SendingTargetsCache {
+    pub fn new(policy: ResendPolicy) -> Self {
+        Self {
+            cache: HashMap::new(),
+            policy,
+            history: FailureHistory::default(),
+        }
+    }
+
+    pub fn history(&self) -> &FailureHistory {
+        &self.history
+    }
+
     pub fn insert_message(
         &mut self,
         token: Token,
@@ -102,11 +195,12 @@ impl SendingTargetsCache {
     /// Finds a Failed target with the lowest number of failed attempts so far. If there are
     /// multiple possibilities, the one with the highest priority (earliest in the list) is taken.
     /// Returns `Never` if no such targets exist.
-    fn take_next_target(&mut self, token: Token) -> Resend {
+    fn take_next_target(&mut self, token: Token, rng: &mut MainRng) -> Resend {
+        let max_attempts = self.policy.max_attempts;
         if let Some((addr, failed_attempts, state)) = self
             .target_states_mut(token)
             .filter_map(|(addr, state)| match state {
-                TargetState::Failed(x) if *x < RESEND_MAX_ATTEMPTS => Some((*addr, *x, state)),
+                TargetState::Failed(x) if *x < max_attempts => Some((*addr, *x, state)),
                 TargetState::Failed(_) | TargetState::Sending(_) | TargetState::Sent => None,
             })
             .min_by_key(|(_addr, failed_attempts, _state)| *failed_attempts)
@@ -116,7 +210,7 @@ impl SendingTargetsCache {
             if failed_attempts == 0 {
                 Resend::Now(addr)
             } else {
-                Resend::Later(addr, RESEND_DELAY)
+                Resend::Later(addr, self.policy.delay(failed_attempts, rng))
             }
         } else {
             Resend::Never
@@ -133,19 +227,37 @@ impl SendingTargetsCache {
             .all(|(_info, state)| !state.is_sending())
     }
 
-    pub fn target_failed(&mut self, token: Token, target: SocketAddr) -> Resend {
+    // Returns how to resend the message and whether it was given up on without reaching any of
+    // its targets.
+    pub fn target_failed(
+        &mut self,
+        token: Token,
+        target: SocketAddr,
+        rng: &mut MainRng,
+    ) -> (Resend, bool) {
+        self.history.record_failure(target);
         self.fail_target(token, target);
 
-        let next_target = self.take_next_target(token);
+        let next_target = self.take_next_target(token, rng);
 
-        if self.should_drop(token) {
-            let _ = self.cache.remove(&token);
-        }
+        let undelivered = if self.should_drop(token) {
+            self.cache
+                .remove(&token)
+                .map(|targets| {
+                    targets
+                        .iter()
+                        .all(|(_, state)| !matches!(state, TargetState::Sent))
+                })
+                .unwrap_or(false)
+        } else {
+            false
+        };
 
-        next_target
+        (next_target, undelivered)
     }
 
     pub fn target_succeeded(&mut self, token: Token, target: SocketAddr) {
+        self.history.record_success(&target);
         let _ = self
             .target_states_mut(token)
             .find(|(addr, _state)| *addr == target)
@@ -167,3 +279,94 @@ pub enum Resend {
     // Don't resend - all attempts failed.
     Never,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+
+    #[test]
+    fn resend_delay_backs_off_exponentially() {
+        let policy = ResendPolicy::default();
+        let mut cache = SendingTargetsCache::new(policy);
+        let mut rng = rng::new();
+        let target = ([127, 0, 0, 1], 9000).into();
+        let token = 1;
+
+        cache.insert_message(token, &[target], 1);
+
+        let mut expected = policy.initial_delay;
+        for _ in 1..policy.max_attempts {
+            let (resend, undelivered) = cache.target_failed(token, target, &mut rng);
+            assert!(!undelivered);
+            match resend {
+                Resend::Later(addr, delay) => {
+                    assert_eq!(addr, target);
+                    assert!(delay >= expected);
+                    assert!(delay <= expected + expected * policy.jitter_percent / 100);
+                }
+                Resend::Now(_) | Resend::Never => panic!("unexpected resend"),
+            }
+            expected = (expected * policy.multiplier).min(policy.max_delay);
+        }
+
+        let (resend, undelivered) = cache.target_failed(token, target, &mut rng);
+        assert!(matches!(resend, Resend::Never));
+        assert!(undelivered);
+        assert_eq!(
+            cache.history().failures(&target),
+            u32::from(policy.max_attempts)
+        );
+    }
+
+    #[test]
+    fn message_delivered_to_some_target_is_not_undelivered() {
+        let mut cache = SendingTargetsCache::new(ResendPolicy::default());
+        let mut rng = rng::new();
+        let target_a = ([127, 0, 0, 1], 9000).into();
+        let target_b = ([127, 0, 0, 1], 9001).into();
+        let token = 1;
+
+        cache.insert_message(token, &[target_a, target_b], 2);
+        cache.target_succeeded(token, target_a);
+
+        let policy = ResendPolicy::default();
+        for _ in 1..policy.max_attempts {
+            let (_, undelivered) = cache.target_failed(token, target_b, &mut rng);
+            assert!(!undelivered);
+        }
+
+        let (resend, undelivered) = cache.target_failed(token, target_b, &mut rng);
+        assert!(matches!(resend, Resend::Never));
+        assert!(!undelivered);
+        assert_eq!(cache.history().failures(&target_a), 0);
+    }
+
+    #[test]
+    fn resend_delay_handles_extreme_policies() {
+        let mut rng = rng::new();
+
+        let policy = ResendPolicy {
+            multiplier: 0,
+            jitter_percent: 0,
+            ..Default::default()
+        };
+        for failed_attempts in 1..=3 {
+            assert_eq!(
+                policy.delay(failed_attempts, &mut rng),
+                policy.initial_delay
+            );
+        }
+
+        let policy = ResendPolicy {
+            max_attempts: u8::MAX,
+            initial_delay: Duration::from_secs(u64::MAX / 2),
+            multiplier: u32::MAX,
+            max_delay: Duration::from_secs(u64::MAX),
+            jitter_percent: u32::MAX,
+        };
+        for &failed_attempts in &[1, 2, u8::MAX] {
+            assert!(policy.delay(failed_attempts, &mut rng) >= policy.initial_delay);
+        }
+    }
+}
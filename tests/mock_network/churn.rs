@@ -68,6 +68,7 @@ fn remove_unresponsive_node() {
     let env = Environment::new(NetworkParams {
         elder_size,
         recommended_section_size,
+        ..Default::default()
     });
 
     let mut nodes = create_connected_nodes(&env, recommended_section_size);
@@ -170,6 +171,7 @@ impl Default for Params {
             network: NetworkParams {
                 elder_size: 4,
                 recommended_section_size: 5,
+                ..Default::default()
             },
             initial_prefix_lens: vec![],
             message_schedule: MessageSchedule::AfterChurn,
@@ -0,0 +1,264 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::utils::*;
+use crossbeam_channel as mpmc;
+use fake_clock::FakeClock;
+use routing::{
+    event::Event, mock::Environment, Client, ClientConfig, DstLocation, NetworkParams, SrcLocation,
+    TransportConfig, CLIENT_REFRESH_INTERVAL,
+};
+use std::collections::BTreeSet;
+use xor_name::XorName;
+
+// Maximum number of iterations of the `poll_client_until` function.
+const POLL_CLIENT_UNTIL_MAX_ITERATIONS: usize = 2000;
+
+struct TestClient {
+    inner: Client,
+    event_rx: mpmc::Receiver<Event>,
+}
+
+impl TestClient {
+    fn new(env: &Environment, contact: &mut TestNode, trusted_keys: Vec<bls::PublicKey>) -> Self {
+        let (inner, event_rx) = Client::new(ClientConfig {
+            transport_config: TransportConfig::node().with_hard_coded_contact(contact.endpoint()),
+            trusted_keys,
+            rng: env.new_rng(),
+            ..Default::default()
+        });
+
+        Self { inner, event_rx }
+    }
+
+    fn poll(&mut self) -> bool {
+        let mut result = false;
+
+        loop {
+            let mut sel = mpmc::Select::new();
+            self.inner.register(&mut sel);
+
+            if let Ok(op_index) = sel.try_ready() {
+                if self.inner.handle_selected_operation(op_index).is_ok() {
+                    result = true;
+                }
+            } else {
+                break;
+            }
+        }
+
+        result
+    }
+
+    fn name(&self) -> &XorName {
+        self.inner.name()
+    }
+
+    fn try_recv_event(&self) -> Option<Event> {
+        self.event_rx.try_recv().ok()
+    }
+}
+
+// Polls the nodes and the client until the given predicate returns `true`.
+fn poll_client_until<F>(
+    env: &Environment,
+    nodes: &mut [TestNode],
+    client: &mut TestClient,
+    mut predicate: F,
+) where
+    F: FnMut(&[TestNode], &TestClient) -> bool,
+{
+    for _ in 0..POLL_CLIENT_UNTIL_MAX_ITERATIONS {
+        if predicate(nodes, client) {
+            return;
+        }
+
+        poll_all(env, nodes);
+        let _ = client.poll();
+        poll_all(env, nodes);
+        FakeClock::advance_time(1000);
+    }
+
+    panic!(
+        "poll_client_until has been called {} times.",
+        POLL_CLIENT_UNTIL_MAX_ITERATIONS
+    );
+}
+
+fn setup(trust_genesis_key: bool) -> (Environment, Vec<TestNode>, TestClient) {
+    let env = Environment::new(NetworkParams {
+        elder_size: MIN_ELDER_SIZE,
+        recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
+    });
+    let mut nodes = create_connected_nodes(&env, MIN_ELDER_SIZE);
+
+    let trusted_keys = if trust_genesis_key {
+        vec![*nodes[0].inner.our_history().unwrap().first_key()]
+    } else {
+        Vec::new()
+    };
+    let client = TestClient::new(&env, &mut nodes[0], trusted_keys);
+
+    (env, nodes, client)
+}
+
+// Returns whether the client follows the current elders and key of the section of the nodes.
+fn client_up_to_date(nodes: &[TestNode], client: &TestClient) -> bool {
+    let elders: BTreeSet<_> = nodes[0]
+        .inner
+        .our_elders()
+        .map(|p2p_node| *p2p_node.name())
+        .collect();
+
+    client.inner.section_key() == nodes[0].inner.section_key()
+        && client
+            .inner
+            .elders_info()
+            .map(|info| info.elders.keys().copied().collect::<BTreeSet<_>>())
+            == Some(elders)
+}
+
+// Returns whether the client was notified about the current elders and key of the section of the
+// nodes since the last call.
+fn elders_changed_received(nodes: &[TestNode], client: &TestClient) -> bool {
+    let expected_key = nodes[0].inner.section_key().copied();
+
+    while let Some(event) = client.try_recv_event() {
+        if let Event::EldersChanged { key, .. } = event {
+            if Some(key) == expected_key {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+fn content_received(event_rx: impl Fn() -> Option<Event>, expected_content: &[u8]) -> bool {
+    while let Some(event) = event_rx() {
+        if let Event::MessageReceived { content, .. } = event {
+            if content == expected_content {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+#[test]
+fn bootstrap_with_trust_on_first_use() {
+    let (env, mut nodes, mut client) = setup(false);
+
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, client| {
+        client_up_to_date(nodes, client)
+    });
+    assert!(elders_changed_received(&nodes, &client));
+    assert!(client.inner.is_running());
+}
+
+#[test]
+fn bootstrap_with_trusted_genesis_key() {
+    let (env, mut nodes, mut client) = setup(true);
+
+    // The section key is only accepted once proven from the genesis key.
+    assert_eq!(client.inner.section_key(), None);
+
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, client| {
+        client_up_to_date(nodes, client)
+    });
+    assert!(elders_changed_received(&nodes, &client));
+}
+
+#[test]
+fn follow_section_key_change() {
+    let (env, mut nodes, mut client) = setup(true);
+
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, client| {
+        client_up_to_date(nodes, client)
+    });
+    assert!(elders_changed_received(&nodes, &client));
+
+    let old_key = *nodes[0].inner.section_key().unwrap();
+    for node in &mut nodes {
+        node.inner.refresh_section_key().unwrap();
+    }
+    poll_until(&env, &mut nodes, |nodes| {
+        nodes
+            .iter()
+            .all(|node| node.inner.section_key() != Some(&old_key))
+    });
+    assert_eq!(client.inner.section_key(), Some(&old_key));
+
+    // The client asks for a proof of the new key on its next refresh.
+    FakeClock::advance_time(CLIENT_REFRESH_INTERVAL.as_millis() as u64);
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, client| {
+        client_up_to_date(nodes, client)
+    });
+    assert!(elders_changed_received(&nodes, &client));
+}
+
+#[test]
+fn send_to_section() {
+    let (env, mut nodes, mut client) = setup(true);
+
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, client| {
+        client_up_to_date(nodes, client)
+    });
+
+    let dst = DstLocation::Section(*nodes[0].name());
+    let content = gen_vec(&mut env.new_rng(), 1024);
+    client.inner.send_message(dst, content.clone()).unwrap();
+
+    let mut pending: Vec<_> = nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.inner.is_elder() && node.inner.in_dst_location(&dst))
+        .map(|(index, _)| index)
+        .collect();
+    assert!(!pending.is_empty());
+
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, _| {
+        pending.retain(|&index| !content_received(|| nodes[index].try_recv_event(), &content));
+        pending.is_empty()
+    });
+}
+
+#[test]
+fn receive_from_node() {
+    let (env, mut nodes, mut client) = setup(true);
+
+    poll_client_until(&env, &mut nodes, &mut client, |nodes, client| {
+        client_up_to_date(nodes, client)
+    });
+
+    let src = SrcLocation::Node(*nodes[0].name());
+    let dst = DstLocation::Node(*client.name());
+    let content = gen_vec(&mut env.new_rng(), 1024);
+    nodes[0]
+        .inner
+        .send_message(src, dst, content.clone())
+        .unwrap();
+
+    poll_client_until(&env, &mut nodes, &mut client, |_, client| {
+        content_received(|| client.try_recv_event(), &content)
+    });
+}
+
+#[test]
+fn send_before_bootstrap_fails() {
+    let (_env, nodes, mut client) = setup(true);
+
+    let dst = DstLocation::Section(*nodes[0].name());
+    assert!(client.inner.send_message(dst, vec![0]).is_err());
+    assert!(client
+        .inner
+        .send_message(DstLocation::Direct, vec![0])
+        .is_err());
+}
@@ -0,0 +1,54 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::utils::*;
+use routing::{mock::Environment, Compression, CompressionStats, NetworkParams};
+
+// Forms a section, which is dominated by parsec gossip, and returns the bytes sent by all its
+// nodes.
+fn form_section(compression: Option<Compression>) -> CompressionStats {
+    let env = Environment::new(NetworkParams {
+        elder_size: MIN_ELDER_SIZE,
+        recommended_section_size: 2 * MIN_ELDER_SIZE,
+        compression,
+        ..Default::default()
+    });
+    let nodes = create_connected_nodes(&env, 2 * MIN_ELDER_SIZE);
+    verify_invariants_for_nodes(&env, &nodes);
+
+    nodes
+        .iter()
+        .map(|node| node.inner.compression_stats())
+        .fold(CompressionStats::default(), |total, stats| {
+            CompressionStats {
+                uncompressed: total.uncompressed + stats.uncompressed,
+                sent: total.sent + stats.sent,
+            }
+        })
+}
+
+#[test]
+fn uncompressed() {
+    let stats = form_section(None);
+    assert_eq!(stats.sent, stats.uncompressed);
+}
+
+#[test]
+fn bandwidth_saved() {
+    for &codec in &[Compression::Deflate, Compression::Snappy] {
+        let stats = form_section(Some(codec));
+        info!(
+            "{:?}: sent {} of {} bytes ({:.1}% saved)",
+            codec,
+            stats.sent,
+            stats.uncompressed,
+            100.0 * (1.0 - stats.sent as f64 / stats.uncompressed as f64)
+        );
+        assert!(stats.sent < stats.uncompressed);
+    }
+}
@@ -15,6 +15,7 @@ fn node_drops() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE + 1,
+        ..Default::default()
     });
     let mut rng = env.new_rng();
     let mut nodes = create_connected_nodes(&env, MIN_ELDER_SIZE + 2);
@@ -21,6 +21,7 @@ fn send() {
     let env = Environment::new(NetworkParams {
         elder_size,
         recommended_section_size,
+        ..Default::default()
     });
     let mut rng = env.new_rng();
     let mut nodes = create_connected_nodes(&env, elder_size + 1);
@@ -64,6 +65,7 @@ fn send_and_receive() {
     let env = Environment::new(NetworkParams {
         elder_size,
         recommended_section_size,
+        ..Default::default()
     });
     let mut rng = env.new_rng();
     let mut nodes = create_connected_nodes(&env, elder_size + 1);
@@ -8,9 +8,12 @@
 
 mod accumulate;
 mod churn;
+mod client;
+mod compression;
 mod drop;
 mod messages;
 mod node_ageing;
+mod recovery;
 pub mod utils;
 
 use self::utils::*;
@@ -18,10 +21,12 @@ use fake_clock::FakeClock;
 use itertools::Itertools;
 use rand::{seq::SliceRandom, Rng};
 use routing::{
-    event::Event, mock::Environment, test_consts, NetworkParams, PausedState, Prefix,
-    RelocationOverrides, TransportConfig,
+    event::{Connected, Event},
+    mock::Environment,
+    test_consts, FullId, NetworkParams, P2pNode, PausedState, Prefix, RelocationOverrides,
+    TransportConfig,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 // -----  Miscellaneous tests below  -----
 
@@ -31,6 +36,7 @@ fn test_nodes(percentage_size: usize) {
         elder_size: MIN_ELDER_SIZE,
         // Require at least one non-elder to make things more interesting.
         recommended_section_size: MIN_ELDER_SIZE + 1,
+        ..Default::default()
     });
     let nodes = create_connected_nodes(&env, size);
     verify_invariants_for_nodes(&env, &nodes);
@@ -48,6 +54,7 @@ fn disconnect_on_rebootstrap() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
     });
     let mut nodes = create_connected_nodes(&env, 2);
 
@@ -68,6 +75,7 @@ fn single_section() {
     let env = Environment::new(NetworkParams {
         elder_size: sec_size,
         recommended_section_size: sec_size,
+        ..Default::default()
     });
     let nodes = create_connected_nodes(&env, sec_size);
     verify_invariants_for_nodes(&env, &nodes);
@@ -93,6 +101,7 @@ fn node_joins_in_front() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
     });
     let mut nodes = create_connected_nodes(&env, 2 * MIN_ELDER_SIZE);
     let transport_config = TransportConfig::node().with_hard_coded_contact(nodes[0].endpoint());
@@ -107,11 +116,110 @@ fn node_joins_in_front() {
     verify_invariants_for_nodes(&env, &nodes);
 }
 
+#[test]
+fn genesis_ceremony() {
+    let env = Environment::new(NetworkParams {
+        elder_size: MIN_ELDER_SIZE,
+        recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
+    });
+    let mut rng = env.new_rng();
+
+    let full_ids: Vec<_> = (0..MIN_ELDER_SIZE)
+        .map(|_| (FullId::gen(&mut rng), env.gen_addr()))
+        .collect();
+    let participants: Vec<_> = full_ids
+        .iter()
+        .map(|(full_id, addr)| P2pNode::new(*full_id.public_id(), *addr))
+        .collect();
+
+    let mut nodes: Vec<_> = full_ids
+        .into_iter()
+        .map(|(full_id, addr)| {
+            TestNode::builder(&env)
+                .genesis(participants.clone())
+                .full_id(full_id)
+                .transport_config(TransportConfig::node().with_endpoint(addr))
+                .create()
+        })
+        .collect();
+
+    poll_until(&env, &mut nodes, |nodes| {
+        all_nodes_joined(nodes, 0..nodes.len())
+    });
+    verify_invariants_for_nodes(&env, &nodes);
+
+    // All the genesis nodes are elders holding different shares of the same section key.
+    let section_key = *nodes[0].inner.section_key().unwrap();
+    let mut indices = BTreeSet::new();
+    for node in &mut nodes {
+        expect_next_event!(node, Event::Connected(Connected::First));
+        assert!(node.inner.is_elder());
+        assert_eq!(node.inner.section_key(), Some(&section_key));
+        assert!(indices.insert(node.inner.our_index().unwrap()));
+    }
+
+    // The network accepts new nodes.
+    nodes.push(create_node_with_contact(&env, &mut nodes[0]));
+    poll_until(&env, &mut nodes, |nodes| node_joined(nodes, MIN_ELDER_SIZE));
+}
+
+#[test]
+fn refresh_section_key() {
+    let env = Environment::new(NetworkParams {
+        elder_size: MIN_ELDER_SIZE,
+        recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
+    });
+    let mut nodes = create_connected_nodes(&env, MIN_ELDER_SIZE);
+
+    let old_key = *nodes[0].inner.section_key().unwrap();
+    let old_elders: BTreeSet<_> = nodes[0]
+        .inner
+        .our_elders()
+        .map(|p2p_node| *p2p_node.name())
+        .collect();
+
+    for node in &mut nodes {
+        node.inner.refresh_section_key().unwrap();
+    }
+
+    poll_until(&env, &mut nodes, |nodes| {
+        nodes
+            .iter()
+            .all(|node| node.inner.section_key() != Some(&old_key))
+    });
+    verify_invariants_for_nodes(&env, &nodes);
+
+    // The same elders now hold shares of the new key, which is linked to the old one.
+    let new_key = *nodes[0].inner.section_key().unwrap();
+    for node in &nodes {
+        let elders: BTreeSet<_> = node
+            .inner
+            .our_elders()
+            .map(|p2p_node| *p2p_node.name())
+            .collect();
+        assert_eq!(elders, old_elders);
+
+        let history = node.inner.our_history().unwrap();
+        assert_eq!(history.last_key(), &new_key);
+        assert!(history.has_key(&old_key));
+
+        let public_key_set = node.inner.public_key_set().unwrap();
+        assert_eq!(public_key_set.public_key(), new_key);
+        assert_eq!(
+            node.inner.secret_key_share().unwrap().public_key_share(),
+            public_key_set.public_key_share(node.inner.our_index().unwrap())
+        );
+    }
+}
+
 #[test]
 fn multiple_joining_nodes() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
     });
 
     let iterations = 10;
@@ -150,6 +258,7 @@ fn single_split() {
         // post-split in at least one of the sub-sections might be completely different from the
         // set of elders pre-split. This setup exposed a bug before and we want to have it covered.
         recommended_section_size: MIN_ELDER_SIZE + 3,
+        ..Default::default()
     });
     let mut nodes = vec![];
     trigger_split(&env, &mut nodes, &Prefix::default());
@@ -160,6 +269,7 @@ fn multi_split() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE + 1,
+        ..Default::default()
     });
     let nodes = create_connected_nodes_until_split(&env, &[2, 2, 2, 2]);
     verify_invariants_for_nodes(&env, &nodes);
@@ -260,6 +370,7 @@ fn simultaneous_joining_nodes_two_sections() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
     });
     let nodes = create_connected_nodes_until_split(&env, &[1, 1]);
 
@@ -288,6 +399,7 @@ fn simultaneous_joining_nodes_two_sections_switch_section() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
     });
     let nodes = create_connected_nodes_until_split(&env, &[1, 1]);
 
@@ -321,6 +433,7 @@ fn simultaneous_joining_nodes_three_section_with_one_ready_to_split() {
     let env = Environment::new(NetworkParams {
         elder_size,
         recommended_section_size,
+        ..Default::default()
     });
     let mut nodes = create_connected_nodes_until_split(&env, &[1, 2, 2]);
 
@@ -367,6 +480,7 @@ fn check_close_names_for_elder_size_nodes() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
     });
     let mut nodes = create_connected_nodes(&env, MIN_ELDER_SIZE);
 
@@ -384,6 +498,7 @@ fn sibling_knowledge_update_after_split() {
     let env = Environment::new(NetworkParams {
         elder_size,
         recommended_section_size,
+        ..Default::default()
     });
 
     let mut nodes = create_connected_nodes_until_split(&env, &[1, 1]);
@@ -413,6 +528,7 @@ fn carry_out_parsec_pruning() {
     let env = Environment::new(NetworkParams {
         elder_size,
         recommended_section_size,
+        ..Default::default()
     });
     let mut rng = env.new_rng();
     let mut nodes = create_connected_nodes(&env, init_network_size);
@@ -471,6 +587,7 @@ fn node_pause_and_resume_simple() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE + 1,
+        ..Default::default()
     });
 
     let mut nodes = create_connected_nodes(&env, env.recommended_section_size());
@@ -503,6 +620,7 @@ fn node_pause_and_resume_during_split() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE + 1,
+        ..Default::default()
     });
 
     let mut nodes = vec![];
@@ -556,6 +674,7 @@ fn neighbour_update() {
     let env = Environment::new(NetworkParams {
         elder_size: MIN_ELDER_SIZE,
         recommended_section_size: MIN_ELDER_SIZE + 1,
+        ..Default::default()
     });
     let mut rng = env.new_rng();
     let mut nodes = create_connected_nodes_until_split(&env, &[1, 1]);
@@ -13,7 +13,8 @@ use rand::{
     Rng,
 };
 use routing::{
-    mock::Environment, rng::MainRng, NetworkParams, Prefix, RelocationOverrides, XorName, MIN_AGE,
+    mock::Environment, rng::MainRng, NetworkParams, Prefix, RelocationOverrides, XorName,
+    COMPRESSION_THRESHOLD, MAX_HOPS, MAX_INFANTS, MIN_AGE,
 };
 use std::iter;
 
@@ -22,6 +23,11 @@ use std::iter;
 const NETWORK_PARAMS: NetworkParams = NetworkParams {
     elder_size: MIN_ELDER_SIZE,
     recommended_section_size: MIN_ELDER_SIZE + 4,
+    max_infants: MAX_INFANTS,
+    compression: None,
+    compression_threshold: COMPRESSION_THRESHOLD,
+    max_hops: MAX_HOPS,
+    record_route: false,
 };
 
 #[test]
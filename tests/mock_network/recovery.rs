@@ -0,0 +1,105 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::utils::*;
+use routing::{mock::Environment, NetworkParams, Prefix};
+use std::collections::BTreeSet;
+use xor_name::XorName;
+
+#[test]
+fn section_recovers_after_losing_all_elders() {
+    let env = Environment::new(NetworkParams {
+        elder_size: MIN_ELDER_SIZE,
+        recommended_section_size: MIN_ELDER_SIZE,
+        ..Default::default()
+    });
+    let mut nodes = create_connected_nodes_until_split(&env, &[1, 1]);
+    let prefix = Prefix::default().pushed(false);
+
+    // Make sure enough members survive the loss of the elders to take over from them.
+    while nodes_with_prefix(&nodes, &prefix).count() < 2 * MIN_ELDER_SIZE {
+        add_node_to_section(&env, &mut nodes, &prefix);
+        let index = nodes.len() - 1;
+        poll_until(&env, &mut nodes, |nodes| node_joined(nodes, index));
+    }
+
+    // Wait for a snapshot taken after all the members joined.
+    let next_index = nodes_with_prefix(&nodes, &prefix)
+        .filter_map(|node| node.inner.recovery_snapshot_index())
+        .max()
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    poll_until(&env, &mut nodes, |nodes| {
+        nodes_with_prefix(nodes, &prefix)
+            .all(|node| node.inner.recovery_snapshot_index() >= Some(next_index))
+    });
+
+    let lost_elders: BTreeSet<_> = elders_with_prefix(&nodes, &prefix)
+        .map(|node| *node.name())
+        .collect();
+    nodes.retain(|node| !lost_elders.contains(node.name()));
+
+    poll_until(&env, &mut nodes, |nodes| {
+        section_recovered(nodes, &prefix, &lost_elders)
+    });
+}
+
+// Returns whether the survivors of the section with the given prefix all agree on new elders chosen
+// among them, and whether the elders of the neighbour sections know these new elders too.
+fn section_recovered(nodes: &[TestNode], prefix: &Prefix, lost_elders: &BTreeSet<XorName>) -> bool {
+    let survivors: BTreeSet<_> = nodes_with_prefix(nodes, prefix)
+        .map(|node| *node.name())
+        .collect();
+    let new_elders: BTreeSet<_> = if let Some(info) = nodes_with_prefix(nodes, prefix)
+        .next()
+        .and_then(|node| node.inner.our_section())
+    {
+        info.elders.keys().copied().collect()
+    } else {
+        return false;
+    };
+
+    if !new_elders.is_subset(&survivors) || !new_elders.is_disjoint(lost_elders) {
+        trace!("Section {:?} not recovered yet", prefix);
+        return false;
+    }
+
+    let survivors_agree = nodes_with_prefix(nodes, prefix).all(|node| {
+        node.inner
+            .our_section()
+            .map(|info| info.elders.keys().copied().collect::<BTreeSet<_>>() == new_elders)
+            .unwrap_or(false)
+    });
+    if !survivors_agree {
+        trace!(
+            "Survivors of {:?} don't agree on the new elders yet",
+            prefix
+        );
+        return false;
+    }
+
+    nodes
+        .iter()
+        .filter(|node| !prefix.matches(node.name()) && node.inner.is_elder())
+        .all(|node| {
+            let known = node
+                .inner
+                .neighbour_sections()
+                .find(|info| info.prefix == *prefix)
+                .map(|info| info.elders.keys().copied().collect::<BTreeSet<_>>() == new_elders)
+                .unwrap_or(false);
+            if !known {
+                trace!(
+                    "Neighbour {} doesn't know the recovered {:?} yet",
+                    node.name(),
+                    prefix
+                );
+            }
+            known
+        })
+}
@@ -17,7 +17,7 @@ use routing::{
     event::{Connected, Event},
     mock::Environment,
     rng::MainRng,
-    test_consts, DstLocation, FullId, Node, NodeConfig, PausedState, Prefix, PublicId,
+    test_consts, DstLocation, FullId, Node, NodeConfig, P2pNode, PausedState, Prefix, PublicId,
     RelocationOverrides, SrcLocation, TransportConfig,
 };
 use std::{
@@ -150,6 +150,11 @@ impl<'a> TestNodeBuilder<'a> {
         self
     }
 
+    pub fn genesis(mut self, participants: Vec<P2pNode>) -> Self {
+        self.config.genesis = Some(participants);
+        self
+    }
+
     pub fn transport_config(mut self, config: TransportConfig) -> Self {
         self.config.transport_config = config;
         self